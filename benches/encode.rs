@@ -0,0 +1,39 @@
+//! Benchmarks of the encoders' output paths, which write a code per input
+//! byte (or sequence).
+//!
+//! Run with `cargo bench -p compressing`.
+
+use std::io;
+
+use compressing::huffman;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+/// About 4 MB of the test data (text, source code and an image).
+fn input() -> Vec<u8> {
+    let mut data = Vec::new();
+    for _ in 0..200 {
+        data.extend_from_slice(include_bytes!("../tests/data/text.txt"));
+        data.extend_from_slice(include_bytes!("../tests/data/source.rs"));
+        data.extend_from_slice(include_bytes!("../tests/data/image.bmp"));
+    }
+    data
+}
+
+fn encode(c: &mut Criterion) {
+    let data = input();
+    let mut group = c.benchmark_group("encode");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.sample_size(10);
+    group.bench_function("huffman::compress", |b| {
+        b.iter(|| huffman::compress(&data, &mut io::sink()).unwrap());
+    });
+    let encoder =
+        huffman::Encoder::new(&huffman::freq_map_from_reader(&mut data.as_slice()).unwrap());
+    group.bench_function("huffman::Encoder::encode", |b| {
+        b.iter(|| encoder.encode(&data, &mut io::sink()).unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, encode);
+criterion_main!(benches);
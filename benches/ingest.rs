@@ -0,0 +1,43 @@
+//! Benchmarks of the byte-ingestion paths, which read the whole input once:
+//! the LZW encoder and the Huffman frequency count.
+//!
+//! Run with `cargo bench -p compressing`.
+
+use std::io;
+
+use compressing::{huffman, lzw};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+/// About 4 MB of the test data (text, source code and an image).
+fn input() -> Vec<u8> {
+    let mut data = Vec::new();
+    for _ in 0..200 {
+        data.extend_from_slice(include_bytes!("../tests/data/text.txt"));
+        data.extend_from_slice(include_bytes!("../tests/data/source.rs"));
+        data.extend_from_slice(include_bytes!("../tests/data/image.bmp"));
+    }
+    data
+}
+
+fn ingest(c: &mut Criterion) {
+    let data = input();
+    let mut group = c.benchmark_group("ingest");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.sample_size(10);
+    // Variable-width codes freeze the dictionary once full, which fixed-width
+    // ones (overflowing instead) don't on this much data.
+    let options = lzw::LzwOptions {
+        variable_width: true,
+        ..lzw::LzwOptions::default()
+    };
+    group.bench_function("lzw::enc", |b| {
+        b.iter(|| lzw::enc_with(&mut data.as_slice(), &mut io::sink(), &options).unwrap());
+    });
+    group.bench_function("huffman::freq_map_from_reader", |b| {
+        b.iter(|| huffman::freq_map_from_reader(&mut data.as_slice()).unwrap());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, ingest);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+use std::time::Instant;
+
+/// A source of the current time.
+pub trait Clock {
+    /// Returns the current instant.
+    fn now(&self) -> Instant;
+}
+
+/// The real, monotonic system clock.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use compressing::{stats, Algorithm};
+
+use crate::error::AppError;
+
+#[derive(Debug, Args)]
+pub struct CompareData {
+    /// The first file to compare.
+    a: PathBuf,
+
+    /// The second file to compare.
+    b: PathBuf,
+}
+
+/// How compressible a file is.
+struct Report {
+    entropy: f64,
+    best: Algorithm,
+    ratio: f64,
+}
+
+impl Report {
+    /// Compresses the data with every algorithm, keeping the best ratio.
+    fn new(data: &[u8]) -> Self {
+        let (best, size) = Algorithm::ALL
+            .into_iter()
+            // Some codecs (e.g., LZW, with its bounded dictionary) may fail on
+            // large inputs, but `stored` never does.
+            .filter_map(|algorithm| {
+                let compressed = compressing::compress(algorithm, data).ok()?;
+                Some((algorithm, compressed.len()))
+            })
+            .min_by_key(|&(_, size)| size)
+            .unwrap();
+        Report {
+            entropy: stats::entropy(data),
+            best,
+            ratio: if data.is_empty() {
+                1.0
+            } else {
+                size as f64 / data.len() as f64
+            },
+        }
+    }
+}
+
+/// Runs the `compare` action.
+pub fn run(data: &CompareData) -> Result<(), AppError> {
+    let read = |path: &Path| {
+        fs::read(path).map_err(AppError::io(format!("could not read '{}'", path.display())))
+    };
+    let a = read(&data.a)?;
+    let b = read(&data.b)?;
+
+    let stdout = io::stdout();
+    let files = [
+        (data.a.as_path(), a.as_slice()),
+        (data.b.as_path(), b.as_slice()),
+    ];
+    write_comparison(files, &mut stdout.lock())
+        .map_err(AppError::io("could not write the comparison"))
+}
+
+/// Writes a table with the entropy and best ratio of both files, followed by
+/// which one compresses better.
+fn write_comparison(files: [(&Path, &[u8]); 2], out: &mut dyn Write) -> io::Result<()> {
+    let reports = files.map(|(_, data)| Report::new(data));
+    let names = files.map(|(path, _)| path.display().to_string());
+    let width = names.iter().map(String::len).max().unwrap().max(4);
+
+    writeln!(out, "{:width$}  entropy  best     ratio", "file")?;
+    for (name, report) in names.iter().zip(&reports) {
+        writeln!(
+            out,
+            "{name:width$}  {:7.3}  {:7}  {:.3}",
+            report.entropy,
+            report.best.name(),
+            report.ratio
+        )?;
+    }
+
+    let [a, b] = &reports;
+    if a.ratio < b.ratio {
+        writeln!(out, "{} compresses better", names[0])
+    } else if b.ratio < a.ratio {
+        writeln!(out, "{} compresses better", names[1])
+    } else {
+        writeln!(out, "both compress equally")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let repetitive = b"ABBABBBABBA".repeat(200);
+        let mut state = 0x2545_F491_u32;
+        let random: Vec<u8> = (0..repetitive.len())
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+
+        let mut out = Vec::new();
+        let files = [
+            (Path::new("random.bin"), random.as_slice()),
+            (Path::new("repetitive.txt"), repetitive.as_slice()),
+        ];
+        write_comparison(files, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("file            entropy"));
+        // Random data doesn't compress, so it's best stored.
+        assert!(lines[1].starts_with("random.bin        7.9"));
+        assert!(lines[1].contains("  stored   1.000"));
+        assert!(lines[2].starts_with("repetitive.txt    0.9"));
+        assert_eq!(lines[3], "repetitive.txt compresses better");
+    }
+}
@@ -0,0 +1,78 @@
+use std::{fmt, io, process::ExitCode};
+
+use clap::error::ErrorKind;
+
+/// An error which ends the program.
+#[derive(Debug)]
+pub enum AppError {
+    /// The command line is invalid. Reported by `clap`, along with the usage.
+    Usage { kind: ErrorKind, message: String },
+    /// An I/O operation failed while doing what `context` describes.
+    Io { context: String, source: io::Error },
+}
+
+impl AppError {
+    /// Constructs a new [`AppError::Usage`].
+    pub fn usage(kind: ErrorKind, message: impl Into<String>) -> Self {
+        AppError::Usage {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Returns a function which wraps an [`io::Error`] into an
+    /// [`AppError::Io`] with the given context, for use with `map_err`.
+    pub fn io(context: impl Into<String>) -> impl FnOnce(io::Error) -> Self {
+        let context = context.into();
+        move |source| AppError::Io { context, source }
+    }
+
+    /// Returns the exit code of the program: 2 for usage errors (as `clap`
+    /// does) and 1 for I/O errors.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            AppError::Usage { .. } => ExitCode::from(2),
+            AppError::Io { .. } => ExitCode::from(1),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Usage { message, .. } => f.write_str(message),
+            AppError::Io { context, source } => write!(f, "{context}: {source}"),
+        }
+    }
+}
+
+impl std::error::Error for AppError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AppError::Usage { .. } => None,
+            AppError::Io { source, .. } => Some(source),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let error = AppError::io("could not open 'foo'")(io::Error::new(
+            io::ErrorKind::NotFound,
+            "No such file or directory",
+        ));
+        assert_eq!(
+            error.to_string(),
+            "could not open 'foo': No such file or directory"
+        );
+        assert_eq!(error.exit_code(), ExitCode::from(1));
+
+        let error = AppError::usage(ErrorKind::ValueValidation, "bad value");
+        assert_eq!(error.to_string(), "bad value");
+        assert_eq!(error.exit_code(), ExitCode::from(2));
+    }
+}
@@ -0,0 +1,108 @@
+//! Comparison against external compression tools.
+
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use clap::ValueEnum;
+
+/// The external tools which `compress` may compare itself with.
+#[derive(Copy, Clone, Debug, ValueEnum)]
+pub enum Tool {
+    Gzip,
+}
+
+impl Tool {
+    /// Returns the name of the tool's program.
+    pub fn program(self) -> &'static str {
+        match self {
+            Tool::Gzip => "gzip",
+        }
+    }
+}
+
+/// The outcome of compressing a file.
+#[derive(Copy, Clone, Debug)]
+pub struct Run {
+    /// The compressed size, in bytes.
+    pub size: u64,
+    pub elapsed: Duration,
+}
+
+/// Compresses `input` with the given tool (at its default level), discarding
+/// the output, or returns `None` if the tool isn't installed.
+///
+/// # Errors
+///
+/// Fails if running the tool fails, or if it exits unsuccessfully.
+pub fn run(tool: Tool, input: &Path) -> io::Result<Option<Run>> {
+    let start = Instant::now();
+    let mut child = match Command::new(tool.program())
+        .args(["-c", "--"])
+        .arg(input)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error),
+    };
+    let size = io::copy(&mut child.stdout.take().unwrap(), &mut io::sink())?;
+    let status = child.wait()?;
+    if !status.success() {
+        let message = format!("`{}` failed ({status})", tool.program());
+        return Err(io::Error::other(message));
+    }
+    Ok(Some(Run {
+        size,
+        elapsed: start.elapsed(),
+    }))
+}
+
+/// Writes a table with the size and time of both runs.
+pub fn write_comparison(ours: Run, tool: Tool, theirs: Run, out: &mut dyn Write) -> io::Result<()> {
+    let width = tool.program().len().max(4);
+    writeln!(
+        out,
+        "{:width$}  {:>12}  {:>9}",
+        "tool", "size (bytes)", "time (ms)"
+    )?;
+    for (name, run) in [("cmpr", ours), (tool.program(), theirs)] {
+        writeln!(
+            out,
+            "{name:width$}  {:>12}  {:>9}",
+            run.size,
+            run.elapsed.as_millis()
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_comparison() {
+        let ours = Run {
+            size: 1234,
+            elapsed: Duration::from_millis(5),
+        };
+        let theirs = Run {
+            size: 987,
+            elapsed: Duration::from_millis(12),
+        };
+        let mut out = Vec::new();
+        write_comparison(ours, Tool::Gzip, theirs, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "tool  size (bytes)  time (ms)\n\
+             cmpr          1234          5\n\
+             gzip           987         12\n"
+        );
+    }
+}
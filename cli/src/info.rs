@@ -0,0 +1,97 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Write},
+    path::PathBuf,
+};
+
+use clap::{Args, ValueEnum};
+use compressing::stats::ByteHistogram;
+
+use crate::error::AppError;
+
+#[derive(Debug, Args)]
+pub struct InfoData {
+    /// Prints the byte-frequency table of the given file.
+    #[arg(long, value_name = "FILE")]
+    freq_table: PathBuf,
+
+    /// The format of the frequency table.
+    #[arg(long, value_enum, default_value = "csv")]
+    format: TableFormat,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+pub enum TableFormat {
+    Csv,
+    Json,
+}
+
+/// Runs the `info` action.
+pub fn run(data: &InfoData) -> Result<(), AppError> {
+    let path = data.freq_table.display();
+    let file =
+        File::open(&data.freq_table).map_err(AppError::io(format!("could not open '{path}'")))?;
+    let histogram = ByteHistogram::from_reader(&mut BufReader::new(file))
+        .map_err(AppError::io(format!("could not read '{path}'")))?;
+
+    let stdout = io::stdout();
+    write_freq_table(&histogram, data.format, &mut stdout.lock())
+        .map_err(AppError::io("could not write the frequency table"))
+}
+
+/// Writes every byte with a non-zero count, in increasing byte order.
+fn write_freq_table(
+    histogram: &ByteHistogram,
+    format: TableFormat,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    match format {
+        TableFormat::Csv => {
+            writeln!(out, "byte,count")?;
+            for (byte, count) in histogram.iter() {
+                writeln!(out, "{byte},{count}")?;
+            }
+        }
+        TableFormat::Json => {
+            write!(out, "{{")?;
+            for (i, (byte, count)) in histogram.iter().enumerate() {
+                let sep = if i == 0 { "" } else { ", " };
+                write!(out, "{sep}\"{byte}\": {count}")?;
+            }
+            writeln!(out, "}}")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &[u8] = b"AAABBBAABACD";
+
+    fn table(format: TableFormat) -> String {
+        let mut out = Vec::new();
+        write_freq_table(&ByteHistogram::from(DATA), format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_csv() {
+        let csv = table(TableFormat::Csv);
+        assert_eq!(csv, "byte,count\n65,6\n66,4\n67,1\n68,1\n");
+
+        let sum: usize = csv
+            .lines()
+            .skip(1)
+            .map(|line| line.split_once(',').unwrap().1.parse::<usize>().unwrap())
+            .sum();
+        assert_eq!(sum, DATA.len());
+    }
+
+    #[test]
+    fn test_json() {
+        let json = table(TableFormat::Json);
+        assert_eq!(json, "{\"65\": 6, \"66\": 4, \"67\": 1, \"68\": 1}\n");
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+/// An input file, either read through system calls or memory-mapped, or the
+/// standard input.
+pub enum Input {
+    File(File),
+    Stdin(io::Stdin),
+    #[cfg(feature = "mmap")]
+    Mapped(io::Cursor<memmap2::Mmap>),
+}
+
+impl Input {
+    /// Opens the given file for reading.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        File::open(path).map(Input::File)
+    }
+
+    /// Returns the standard input.
+    pub fn stdin() -> Self {
+        Input::Stdin(io::stdin())
+    }
+
+    /// Opens and memory-maps the given file.
+    ///
+    /// Empty files aren't mapped (since mapping zero bytes fails on most
+    /// platforms), but read as usual instead.
+    #[cfg(feature = "mmap")]
+    pub fn map(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(Input::File(file));
+        }
+        // SAFETY: The mapped file must not be modified (e.g., truncated) while
+        // it's being read, which is the same contract `cmpr` already assumes
+        // for its inputs.
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        Ok(Input::Mapped(io::Cursor::new(map)))
+    }
+
+    /// Returns whether the input is memory-mapped, in which case it gains
+    /// nothing from buffering.
+    pub fn is_mapped(&self) -> bool {
+        match self {
+            Input::File(_) | Input::Stdin(_) => false,
+            #[cfg(feature = "mmap")]
+            Input::Mapped(_) => true,
+        }
+    }
+}
+
+impl Read for Input {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Input::File(file) => file.read(buf),
+            Input::Stdin(stdin) => stdin.read(buf),
+            #[cfg(feature = "mmap")]
+            Input::Mapped(map) => map.read(buf),
+        }
+    }
+}
@@ -1,39 +1,146 @@
 use std::{
-    fs::{File, OpenOptions},
-    io::{self, BufReader, BufWriter, Read, Write},
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, IsTerminal, Read, Write},
     path::{Path, PathBuf},
-    time::{Duration, Instant},
+    process::ExitCode,
+    sync::{Arc, Mutex},
+    time::Duration,
 };
 
-use clap::{Args, Parser, Subcommand, ValueEnum};
-use compressing::lzw;
+use clap::{
+    error::ErrorKind, Args, Command, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
+use clock::{Clock, SystemClock};
+use compressing::{
+    armor::{self, ArmorWriter},
+    delta::{self, DeltaDecoder, DeltaEncoder},
+    lzw::{self, LzwOptions},
+    sniff::{self, Content},
+    stats::{RunStats, StatsAggregator},
+};
+use error::AppError;
+use input::Input;
+use output::Output;
+use progress::ProgressBar;
+use serde::Serialize;
 use stat::Stat;
 
+mod clock;
+mod compare;
+mod error;
+mod external;
+mod info;
+mod input;
+mod options;
+mod output;
+mod progress;
+mod repair;
+mod size;
+
 #[derive(Debug, Parser)]
 #[command(version)]
 struct Cli {
-    /// The algorithm to use for compress or decompress.
+    /// The algorithm to use for compress or decompress (`auto` picks one
+    /// which suits the input, when compressing).
     #[arg(short, value_enum)]
-    algorithm: Algorithm,
+    algorithm: Option<Algorithm>,
+
+    /// Whether the program should explain its choices, e.g., which algorithm
+    /// suits the input.
+    #[arg(short, long)]
+    verbose: bool,
 
     /// Whether the program should show statistics.
     #[arg(long)]
     stats: bool,
 
+    /// Whether the program should write its statistics to stderr as a JSON
+    /// object, for scripts (stdout is left alone).
+    #[arg(long)]
+    stats_json: bool,
+
+    /// Whether the program should show a progress bar on stderr.
+    #[arg(long)]
+    progress: bool,
+
+    /// Whether the outputs should be synced to disk before exiting.
+    #[arg(long)]
+    sync: bool,
+
+    /// Whether the input files should be kept (the default).
+    #[arg(long, overrides_with = "remove")]
+    keep: bool,
+
+    /// Whether the input files should be deleted once their output is fully
+    /// written (and synced to disk), which rules out writing to stdout.
+    #[arg(long, visible_alias = "rm", overrides_with = "keep")]
+    remove: bool,
+
+    /// Whether missing parent directories of the outputs should be created.
+    #[arg(long)]
+    mkdir: bool,
+
+    /// The capacity of the input and output buffers (e.g., `8k`, `1M`).
+    #[arg(long, value_parser = size::parse_size, default_value = "64k")]
+    buffer_size: u64,
+
+    #[command(flatten)]
+    options: options::OptionsArgs,
+
+    /// Whether the data should be delta-coded before compressing, which suits
+    /// slowly changing data. The output records it, for decompress to undo it
+    /// (as it does with this flag for unrecorded data).
+    #[arg(long)]
+    delta: bool,
+
+    /// Whether the input files should be memory-mapped instead of read.
+    #[cfg(feature = "mmap")]
+    #[arg(long)]
+    mmap: bool,
+
+    /// Lists the available algorithms (with their tags) and exits.
+    #[arg(long, exclusive = true)]
+    list_algorithms: bool,
+
+    /// Compresses stdin to stdout (or decompresses it, with `-d`), for shell
+    /// pipelines, instead of running a subcommand. The buffers are at least
+    /// 1 MiB, and the statistics go to stderr.
+    #[arg(long)]
+    pipe: bool,
+
+    /// With `--pipe`, decompresses instead of compressing.
+    #[arg(short, long, requires = "pipe")]
+    decompress: bool,
+
+    /// Whether compressed data may be written to a terminal (by `--pipe`, or
+    /// by compress without `-o`).
+    #[arg(long)]
+    to_terminal: bool,
+
     #[command(subcommand)]
-    action: Action,
+    action: Option<Action>,
 }
 
 #[derive(Copy, Clone, Debug, ValueEnum)]
 enum Algorithm {
     Lzw,
     Huffman,
+    Stored,
+    Auto,
 }
 
 #[derive(Debug, Subcommand)]
 enum Action {
     Compress(ActionData),
-    Decompress(ActionData),
+    Decompress(DecompressData),
+    /// Shows information about a file.
+    Info(info::InfoData),
+    /// Compares how well two files compress.
+    #[command(name = "compare")]
+    CompareFiles(compare::CompareData),
+    /// Recovers what precedes the corruption of a truncated or corrupt LZW
+    /// file.
+    Repair(repair::RepairData),
 }
 
 #[derive(Debug, Args)]
@@ -41,74 +148,671 @@ struct ActionData {
     /// The file to compress or decompress.
     input: PathBuf,
 
-    /// The output path.
+    /// The output path, or else stdout (unless it's a terminal, see
+    /// `--to-terminal`).
     #[arg(short)]
-    output: PathBuf,
+    output: Option<PathBuf>,
+
+    /// Armors the output as text, which `decompress` detects.
+    #[arg(long, value_enum)]
+    armor: Option<Armor>,
+
+    /// Also compresses the input with the given tool (if installed), printing
+    /// both sizes and times.
+    #[arg(long, value_enum, value_name = "TOOL", requires = "output")]
+    compare_with: Option<external::Tool>,
 }
 
-fn main() -> io::Result<()> {
-    let cmd = Cli::parse();
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Armor {
+    Hex,
+    Base64,
+}
+
+#[derive(Debug, Args)]
+struct DecompressData {
+    /// The files to decompress.
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// The output path (for a single input).
+    #[arg(
+        short,
+        required_unless_present = "output_dir",
+        conflicts_with = "output_dir"
+    )]
+    output: Option<PathBuf>,
+
+    /// The directory to decompress into. Each output is named after its
+    /// input, without the compressed extension (e.g., `foo.txt.lzw` becomes
+    /// `foo.txt`).
+    #[arg(long)]
+    output_dir: Option<PathBuf>,
 
-    let data = cmd.action.data();
-    let manager = IoManager::new(&data.input, &data.output)?;
+    /// Whether existing files in the output directory may be overwritten.
+    #[arg(long)]
+    force: bool,
+}
 
-    let stats = match cmd.action {
-        Action::Compress(_) => match cmd.algorithm {
-            Algorithm::Lzw => manager.run(lzw::enc)?,
-            Algorithm::Huffman => todo!(),
-        },
-        Action::Decompress(_) => match cmd.algorithm {
-            Algorithm::Lzw => manager.run(lzw::dec)?,
-            Algorithm::Huffman => todo!(),
-        },
+fn main() -> ExitCode {
+    let matches = command().get_matches();
+    // Like `--version`, `--list-algorithms` needs no subcommand.
+    if matches.get_flag("list_algorithms") {
+        return match list_algorithms(&mut io::stdout().lock()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("error: {error}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+    match (matches.subcommand(), matches.get_flag("pipe")) {
+        (None, false) => command()
+            .error(
+                ErrorKind::MissingSubcommand,
+                "a subcommand is required (unless `--pipe`)",
+            )
+            .exit(),
+        (Some(_), true) => command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "`--pipe` takes no subcommand, as it reads stdin and writes stdout",
+            )
+            .exit(),
+        _ => {}
+    }
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+    match run(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(AppError::Usage { kind, message }) => command().error(kind, message).exit(),
+        Err(error) => {
+            eprintln!("error: {error}");
+            error.exit_code()
+        }
+    }
+}
+
+/// Returns the command-line interface, whose `--version` also lists the
+/// library's version and algorithms.
+fn command() -> Command {
+    let long_version = format!(
+        "{}\n{}",
+        env!("CARGO_PKG_VERSION"),
+        compressing::version_info()
+    );
+    Cli::command()
+        .long_version(long_version)
+        .subcommand_required(false)
+}
+
+/// Writes the name, tag and description of each registered algorithm.
+fn list_algorithms(out: &mut dyn Write) -> io::Result<()> {
+    let registry = compressing::registry();
+    let width = registry.iter().map(|entry| entry.name().len()).max();
+    for entry in registry.iter() {
+        writeln!(
+            out,
+            "{:width$}  {:>3}  {}",
+            entry.name(),
+            entry.tag(),
+            entry.codec().description(),
+            width = width.unwrap_or(0)
+        )?;
+    }
+    Ok(())
+}
+
+fn run(cmd: &Cli) -> Result<(), AppError> {
+    let Some(action) = &cmd.action else {
+        // `main` only lets `--pipe` go without a subcommand.
+        return run_pipe(cmd);
+    };
+    let jobs = match action {
+        Action::Compress(data) => vec![(data.input.clone(), data.output.clone())],
+        Action::Decompress(data) => data
+            .jobs()
+            .map_err(|message| AppError::usage(ErrorKind::ValueValidation, message))?
+            .into_iter()
+            .map(|(input, output)| (input, Some(output)))
+            .collect(),
+        Action::Info(data) => return info::run(data),
+        Action::CompareFiles(data) => return compare::run(data),
+        Action::Repair(data) => return repair::run(data, &cmd.options.lzw_options()?),
     };
+    let algorithm = cmd.algorithm()?;
+    if algorithm.concrete().is_none() && !action.is_compress() {
+        return Err(AppError::usage(
+            ErrorKind::ValueValidation,
+            "`-a auto` only applies to compress",
+        ));
+    }
 
-    if cmd.stats {
-        println!("done.");
-        println!("    in {} ms", stats.elapsed.as_millis());
+    let lzw_options = cmd.options.lzw_options()?;
+    let batch = jobs.len() > 1;
+    let mut totals = StatsAggregator::new();
+    for (input, output) in jobs {
+        let concrete = algorithm.concrete();
+        let algorithm = if action.is_compress() && (cmd.verbose || concrete.is_none()) {
+            let content = sniff_input(&input)?;
+            let suggested = content.suggested_algorithm();
+            if cmd.verbose {
+                eprintln!(
+                    "note: '{}' looks like {content}, which `-a {}` suits best",
+                    input.display(),
+                    suggested.name()
+                );
+            }
+            concrete.unwrap_or(suggested)
+        } else {
+            // `auto` was ruled out for decompress.
+            concrete.unwrap()
+        };
+        totals.add(run_job(
+            cmd,
+            action,
+            algorithm,
+            &lzw_options,
+            &input,
+            output.as_deref(),
+        )?);
+    }
 
-        if cmd.action.is_compress() {
-            // https://en.wikipedia.org/wiki/Data_compression_ratio
-            let space_saved = (1.0 - stats.written as f64 / stats.read as f64) * 100.0;
-            println!("    saved {space_saved:.2}%");
+    // Only decompress takes several inputs, for now.
+    if cmd.stats && batch {
+        let total = totals.summary();
+        println!("all done ({} files).", totals.runs());
+        println!(
+            "    {} bytes in, {} bytes out",
+            total.input_bytes, total.output_bytes
+        );
+        println!("    in {} ms", total.elapsed.as_millis());
+    }
+
+    Ok(())
+}
+
+/// Compresses or decompresses (according to the action) a single file, into
+/// `output` or else stdout.
+fn run_job(
+    cmd: &Cli,
+    action: &Action,
+    algorithm: compressing::Algorithm,
+    lzw_options: &LzwOptions,
+    input: &Path,
+    output: Option<&Path>,
+) -> Result<RunStats, AppError> {
+    let armor = match action {
+        Action::Compress(data) => data.armor,
+        _ => None,
+    };
+    if action.is_compress() {
+        check_recordable(cmd, algorithm, armor)?;
+    }
+    let open_error = || AppError::io(format!("could not open '{}'", input.display()));
+    let input_file = cmd.open_input(input).map_err(open_error())?;
+    let buffer_size = cmd.buffer_size()?;
+    let mut manager = if let Some(output) = output {
+        let create_error = || AppError::io(format!("could not create '{}'", output.display()));
+        create_parent_dir(output, cmd.mkdir).map_err(create_error())?;
+        IoManager::with_input(input_file, output, buffer_size).map_err(create_error())?
+    } else {
+        // Only compress goes without an output.
+        if cmd.remove {
+            // Whatever reads stdout may still lose the data.
+            return Err(AppError::usage(
+                ErrorKind::ArgumentConflict,
+                "`--remove` needs `-o <OUTPUT>`, as stdout can't be synced to disk",
+            ));
         }
+        if refuses_terminal(io::stdout().is_terminal(), cmd.to_terminal, armor.is_some()) {
+            return Err(AppError::usage(
+                ErrorKind::ValueValidation,
+                "refusing to write compressed data to a terminal (give `-o <OUTPUT>`, \
+                 `--armor` to write text, or `--to-terminal` to write it anyway)",
+            ));
+        }
+        IoManager::with_streams(input_file, Output::stdout(), buffer_size)
+    };
+
+    let progress = if cmd.progress {
+        let metadata = fs::metadata(input).map_err(open_error())?;
+        let total = metadata.is_file().then_some(metadata.len());
+        let bar = Arc::new(Mutex::new(ProgressBar::new(io::stderr(), total)));
+        manager = manager.with_progress({
+            let bar = Arc::clone(&bar);
+            move |read| bar.lock().unwrap().update(read)
+        });
+        Some(bar)
+    } else {
+        None
+    };
+    // The input may only go once its output is sure to be on disk.
+    manager = manager.with_sync(cmd.sync || cmd.remove);
+
+    let compress = action.is_compress();
+    let stats = run_codec(cmd, algorithm, lzw_options, compress, armor, manager);
+    let verb = if compress { "compress" } else { "decompress" };
+    let stats = stats.map_err(AppError::io(format!(
+        "could not {verb} '{}'",
+        input.display()
+    )))?;
+
+    if let Some(bar) = progress {
+        bar.lock().unwrap().finish(stats.read);
+    }
+
+    if let Action::Compress(ActionData {
+        compare_with: Some(tool),
+        ..
+    }) = action
+    {
+        compare_with(*tool, input, &stats)?;
+    }
+
+    if cmd.remove {
+        fs::remove_file(input).map_err(AppError::io(format!(
+            "could not remove '{}'",
+            input.display()
+        )))?;
+    }
+
+    if output.is_some() {
+        report_stats(cmd, algorithm, compress, &stats, &mut io::stdout().lock())?;
+    } else {
+        // Stdout holds the data.
+        report_stats(cmd, algorithm, compress, &stats, &mut io::stderr().lock())?;
+    }
+    Ok(RunStats {
+        input_bytes: stats.read,
+        output_bytes: stats.written,
+        elapsed: stats.elapsed,
+    })
+}
+
+/// The buffer capacity `--pipe` uses at the least, as pipes favor large reads
+/// and writes.
+const PIPE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Compresses stdin to stdout, or decompresses it (see `--pipe`).
+fn run_pipe(cmd: &Cli) -> Result<(), AppError> {
+    let compress = !cmd.decompress;
+    let algorithm = cmd.algorithm()?.concrete().ok_or_else(|| {
+        AppError::usage(
+            ErrorKind::ValueValidation,
+            "`-a auto` needs a file to sniff, hence doesn't apply to `--pipe`",
+        )
+    })?;
+    if compress && refuses_terminal(io::stdout().is_terminal(), cmd.to_terminal, false) {
+        return Err(AppError::usage(
+            ErrorKind::ValueValidation,
+            "refusing to write compressed data to a terminal (`--to-terminal` writes it anyway)",
+        ));
     }
 
+    if compress {
+        check_recordable(cmd, algorithm, None)?;
+    }
+
+    let buffer_size = cmd.buffer_size()?.max(PIPE_BUFFER_SIZE);
+    let manager = IoManager::with_streams(Input::stdin(), Output::stdout(), buffer_size);
+    let lzw_options = cmd.options.lzw_options()?;
+    let stats = run_codec(cmd, algorithm, &lzw_options, compress, None, manager);
+    let verb = if compress { "compress" } else { "decompress" };
+    let stats = stats.map_err(AppError::io(format!("could not {verb} stdin")))?;
+    // Stdout holds the data.
+    report_stats(cmd, algorithm, compress, &stats, &mut io::stderr().lock())
+}
+
+/// Fails if compressing with `algorithm` can't record the armor or `--delta`:
+/// stored data may start like an armor header or a delta marker, so decompress
+/// doesn't detect them.
+fn check_recordable(
+    cmd: &Cli,
+    algorithm: compressing::Algorithm,
+    armor: Option<Armor>,
+) -> Result<(), AppError> {
+    if algorithm != compressing::Algorithm::Stored {
+        return Ok(());
+    }
+    let (flag, looks) = match (armor, cmd.delta) {
+        (Some(_), _) => ("--armor", "armored"),
+        (None, true) => ("--delta", "delta-coded"),
+        (None, false) => return Ok(()),
+    };
+    Err(AppError::usage(
+        ErrorKind::ArgumentConflict,
+        format!("`{flag}` doesn't apply to `-a stored`, whose data may itself look {looks}"),
+    ))
+}
+
+/// Returns whether compressed data mustn't go to stdout: if it's a terminal,
+/// which the binary data would garble, unless it's allowed by `--to-terminal`
+/// or armored as text.
+fn refuses_terminal(is_terminal: bool, to_terminal: bool, armored: bool) -> bool {
+    is_terminal && !to_terminal && !armored
+}
+
+/// Compresses (armoring the output, if given an armor) or decompresses the
+/// data of `manager`, applying `--delta`.
+fn run_codec(
+    cmd: &Cli,
+    algorithm: compressing::Algorithm,
+    lzw_options: &LzwOptions,
+    compress: bool,
+    armor: Option<Armor>,
+    manager: IoManager,
+) -> io::Result<Stats> {
+    let enc = |src: &mut dyn Read, out: &mut dyn Write| {
+        let mut delta;
+        let src = if cmd.delta {
+            out.write_all(delta::MARKER)?;
+            delta = DeltaEncoder::new(src);
+            &mut delta
+        } else {
+            src
+        };
+        match algorithm {
+            compressing::Algorithm::Lzw => lzw::enc_with(src, out, lzw_options),
+            _ => algorithm.enc(src, out),
+        }
+    };
+    match (compress, armor) {
+        (true, Some(armor)) => manager.run(|src, out| {
+            let mut writer = ArmorWriter::new(out, armor.into())?;
+            enc(src, &mut writer)?;
+            writer.finish()
+        }),
+        (true, None) => manager.run(enc),
+        (false, _) => manager.run(|src, out| {
+            // Only stored data (which may be anything) could start like an
+            // armor header or a delta marker, neither of which it records.
+            let stored = algorithm == compressing::Algorithm::Stored;
+            let mut src = if stored {
+                Box::new(src)
+            } else {
+                armor::reader(src)?
+            };
+            let (mut src, marked) = if stored {
+                (src, false)
+            } else {
+                delta::strip_marker(&mut *src)?
+            };
+            let src = &mut *src;
+            let mut delta;
+            let out = if marked || cmd.delta {
+                delta = DeltaDecoder::new(out);
+                &mut delta
+            } else {
+                out
+            };
+            match algorithm {
+                compressing::Algorithm::Lzw => lzw::dec_with(src, out, lzw_options),
+                _ => algorithm.dec(src, out),
+            }
+        }),
+    }
+}
+
+/// Writes the statistics of a run to `out` under `--stats`, and to stderr (as
+/// JSON) under `--stats-json`.
+fn report_stats(
+    cmd: &Cli,
+    algorithm: compressing::Algorithm,
+    compress: bool,
+    stats: &Stats,
+    out: &mut dyn Write,
+) -> Result<(), AppError> {
+    if cmd.stats {
+        write_stats(stats, compress, out)
+            .map_err(AppError::io("could not write the statistics"))?;
+    }
+    if cmd.stats_json {
+        let json = StatsJson::new(algorithm, compress, stats);
+        eprintln!("{}", serde_json::to_string(&json).unwrap());
+    }
     Ok(())
 }
 
-impl Action {
-    fn data(&self) -> &ActionData {
+fn write_stats(stats: &Stats, compress: bool, out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "done.")?;
+    writeln!(out, "    in {} ms", stats.elapsed.as_millis())?;
+    writeln!(
+        out,
+        "    {} read ops, {} write ops",
+        stats.read_ops, stats.write_ops
+    )?;
+    if compress {
+        // https://en.wikipedia.org/wiki/Data_compression_ratio
+        let space_saved = (1.0 - stats.written as f64 / stats.read as f64) * 100.0;
+        writeln!(out, "    saved {space_saved:.2}%")?;
+    }
+    Ok(())
+}
+
+/// Creates the missing parent directories of `output` if `mkdir` is set, or else
+/// fails with a clearer error than opening the output would.
+fn create_parent_dir(output: &Path, mkdir: bool) -> io::Result<()> {
+    let Some(parent) = output
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    else {
+        return Ok(());
+    };
+    if parent.exists() {
+        return Ok(());
+    }
+    if mkdir {
+        return fs::create_dir_all(parent);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "output directory '{}' does not exist (`--mkdir` creates it)",
+            parent.display()
+        ),
+    ))
+}
+
+/// Compresses `input` with the given external tool too, and prints how it
+/// compares with the run behind `stats`.
+fn compare_with(tool: external::Tool, input: &Path, stats: &Stats) -> Result<(), AppError> {
+    let program = tool.program();
+    let theirs = external::run(tool, input).map_err(AppError::io(format!(
+        "could not compress '{}' with {program}",
+        input.display()
+    )))?;
+    let Some(theirs) = theirs else {
+        eprintln!("note: `{program}` isn't installed, skipping the comparison");
+        return Ok(());
+    };
+    let ours = external::Run {
+        size: stats.written,
+        elapsed: stats.elapsed,
+    };
+    external::write_comparison(ours, tool, theirs, &mut io::stdout().lock())
+        .map_err(AppError::io("could not write the comparison"))
+}
+
+/// Sniffs what the given input holds, from its first bytes.
+fn sniff_input(input: &Path) -> Result<Content, AppError> {
+    let context = || format!("could not read '{}'", input.display());
+    let mut head = Vec::with_capacity(sniff::SNIFF_LEN);
+    File::open(input)
+        .and_then(|file| file.take(sniff::SNIFF_LEN as u64).read_to_end(&mut head))
+        .map_err(AppError::io(context()))?;
+    Ok(sniff::sniff(&head))
+}
+
+impl Algorithm {
+    /// Returns the library's algorithm, or `None` for `auto`.
+    fn concrete(self) -> Option<compressing::Algorithm> {
         match self {
-            Action::Compress(data) => data,
-            Action::Decompress(data) => data,
+            Algorithm::Lzw => Some(compressing::Algorithm::Lzw),
+            Algorithm::Huffman => Some(compressing::Algorithm::Huffman),
+            Algorithm::Stored => Some(compressing::Algorithm::Stored),
+            Algorithm::Auto => None,
+        }
+    }
+}
+
+impl From<Armor> for armor::Armor {
+    fn from(armor: Armor) -> Self {
+        match armor {
+            Armor::Hex => armor::Armor::Hex,
+            Armor::Base64 => armor::Armor::Base64,
+        }
+    }
+}
+
+impl Cli {
+    /// Returns the algorithm, which compress and decompress require.
+    fn algorithm(&self) -> Result<Algorithm, AppError> {
+        self.algorithm.ok_or_else(|| {
+            AppError::usage(
+                ErrorKind::MissingRequiredArgument,
+                "the algorithm (`-a <ALGORITHM>`) is required to compress or decompress",
+            )
+        })
+    }
+
+    fn buffer_size(&self) -> Result<usize, AppError> {
+        usize::try_from(self.buffer_size).map_err(|_| {
+            AppError::usage(ErrorKind::ValueValidation, "the buffer size is too large")
+        })
+    }
+
+    /// Opens the given input file, memory-mapping it if requested.
+    fn open_input(&self, path: &Path) -> io::Result<Input> {
+        #[cfg(feature = "mmap")]
+        if self.mmap {
+            return Input::map(path);
         }
+        Input::open(path)
     }
+}
 
+impl Action {
     fn is_compress(&self) -> bool {
         matches!(self, Action::Compress(_))
     }
 }
 
-struct IoManager {
-    reader: BufReader<Stat<File>>,
-    writer: BufWriter<Stat<File>>,
+impl DecompressData {
+    /// Returns the `(input, output)` path pairs to decompress.
+    fn jobs(&self) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+        let Some(dir) = &self.output_dir else {
+            let [input] = self.inputs.as_slice() else {
+                return Err("multiple inputs require `--output-dir`".into());
+            };
+            // `-o` is required unless `--output-dir` is present.
+            return Ok(vec![(input.clone(), self.output.clone().unwrap())]);
+        };
+
+        let mut jobs: Vec<(PathBuf, PathBuf)> = Vec::with_capacity(self.inputs.len());
+        for input in &self.inputs {
+            let output = dir.join(decompressed_name(input)?);
+            if jobs.iter().any(|(_, other)| *other == output) {
+                return Err(format!(
+                    "more than one input decompresses to '{}'",
+                    output.display()
+                ));
+            }
+            if !self.force && output.exists() {
+                return Err(format!(
+                    "'{}' already exists (use `--force` to overwrite)",
+                    output.display()
+                ));
+            }
+            jobs.push((input.clone(), output));
+        }
+        Ok(jobs)
+    }
+}
+
+/// Derives the name of a decompressed file by stripping the (compressed)
+/// extension of the input's name.
+fn decompressed_name(input: &Path) -> Result<PathBuf, String> {
+    match (input.file_stem(), input.extension()) {
+        (Some(stem), Some(_)) => Ok(PathBuf::from(stem)),
+        _ => Err(format!(
+            "can't derive an output name from '{}' (it has no extension)",
+            input.display()
+        )),
+    }
+}
+
+struct IoManager<C = SystemClock> {
+    reader: BufReader<Stat<Input>>,
+    writer: BufWriter<Stat<Output>>,
+    sync: bool,
+    clock: C,
 }
 
 impl IoManager {
-    /// Opens the given files and constructs a new [`IoManager`].
+    /// Opens the given files and constructs a new [`IoManager`], with buffers
+    /// of the default capacity.
+    #[cfg(test)]
     fn new(input: &Path, output: &Path) -> io::Result<Self> {
+        Self::with_input(Input::open(input)?, output, 64 * 1024)
+    }
+
+    /// Opens the given output file and constructs a new [`IoManager`] which
+    /// reads from `input`, buffering both with the given capacity.
+    fn with_input(input: Input, output: &Path, buffer_size: usize) -> io::Result<Self> {
+        Ok(Self::with_streams(
+            input,
+            Output::create(output)?,
+            buffer_size,
+        ))
+    }
+
+    /// Constructs a new [`IoManager`] which reads from `input` and writes to
+    /// `output`, buffering both with the given capacity.
+    fn with_streams(input: Input, output: Output, buffer_size: usize) -> Self {
         let reader = {
-            let file = File::open(input)?;
-            let stat = Stat::new(file);
-            BufReader::new(stat)
-        };
-        let writer = {
-            let file = OpenOptions::new().create(true).write(true).open(output)?;
-            let stat = Stat::new(file);
-            BufWriter::new(stat)
+            // A zero-capacity buffer passes every read through, which avoids
+            // copying out of an already in-memory input.
+            let capacity = if input.is_mapped() { 0 } else { buffer_size };
+            let stat = Stat::new(input);
+            BufReader::with_capacity(capacity, stat)
         };
-        Ok(Self { reader, writer })
+        let writer = BufWriter::with_capacity(buffer_size, Stat::new(output));
+        Self {
+            reader,
+            writer,
+            sync: false,
+            clock: SystemClock,
+        }
+    }
+}
+
+impl<C: Clock> IoManager<C> {
+    /// Sets a callback which is called with the number of bytes read from the
+    /// input so far.
+    fn with_progress<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(u64) + Send + 'static,
+    {
+        self.reader.get_mut().set_progress(move |read, _| f(read));
+        self
+    }
+
+    /// Sets whether the output should be synced to disk (see
+    /// [`Output::sync_all`]) once it's fully written.
+    fn with_sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Replaces the clock used to time [`IoManager::run`].
+    #[cfg(test)]
+    fn with_clock<D: Clock>(self, clock: D) -> IoManager<D> {
+        IoManager {
+            reader: self.reader,
+            writer: self.writer,
+            sync: self.sync,
+            clock,
+        }
     }
 
     /// Runs the provided function and collects statistics on the involved I/O
@@ -117,16 +821,25 @@ impl IoManager {
     where
         F: Fn(&mut dyn Read, &mut dyn Write) -> io::Result<()>,
     {
-        let start = Instant::now();
+        let start = self.clock.now();
         f(&mut self.reader, &mut self.writer)?;
-        let elapsed = start.elapsed();
+        let elapsed = self.clock.now() - start;
 
         let stat_r = self.reader.into_inner();
-        let stat_w = self.writer.into_inner()?;
+        let mut stat_w = self.writer.into_inner()?;
+        // Flushes the output's own buffer (e.g., stdout's), so that its write
+        // errors surface before the statistics are reported or the input goes.
+        stat_w.flush()?;
+        let (written, write_ops) = (stat_w.write_count(), stat_w.write_ops());
+        if self.sync {
+            stat_w.into_inner().sync_all()?;
+        }
 
         Ok(Stats {
             read: stat_r.read_count(),
-            written: stat_w.write_count(),
+            written,
+            read_ops: stat_r.read_ops(),
+            write_ops,
             elapsed,
         })
     }
@@ -135,5 +848,257 @@ impl IoManager {
 struct Stats {
     read: u64,
     written: u64,
+    read_ops: u64,
+    write_ops: u64,
     elapsed: Duration,
 }
+
+/// The statistics written by `--stats-json`.
+#[derive(Debug, Serialize)]
+struct StatsJson {
+    algorithm: &'static str,
+    input_bytes: u64,
+    output_bytes: u64,
+    /// The uncompressed size over the compressed one (whatever the action).
+    ratio: f64,
+    elapsed_us: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    space_saved_percent: Option<f64>,
+}
+
+impl StatsJson {
+    fn new(algorithm: compressing::Algorithm, compress: bool, stats: &Stats) -> Self {
+        let (uncompressed, compressed) = if compress {
+            (stats.read, stats.written)
+        } else {
+            (stats.written, stats.read)
+        };
+        Self {
+            algorithm: algorithm.name(),
+            input_bytes: stats.read,
+            output_bytes: stats.written,
+            ratio: uncompressed as f64 / compressed as f64,
+            elapsed_us: u64::try_from(stats.elapsed.as_micros()).unwrap_or(u64::MAX),
+            space_saved_percent: compress
+                .then(|| (1.0 - stats.written as f64 / stats.read as f64) * 100.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, env, fs, time::Instant};
+
+    use super::*;
+
+    /// A clock that advances by a fixed step each time it's queried.
+    struct FakeClock {
+        now: Cell<Instant>,
+        step: Duration,
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            let now = self.now.get();
+            self.now.set(now + self.step);
+            now
+        }
+    }
+
+    #[test]
+    fn test_run_with_progress() {
+        let input = temp_path("progress-in");
+        let compressed = temp_path("progress-cmp");
+        let output = temp_path("progress-out");
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 7 * 31 % 256) as u8).collect();
+        fs::write(&input, &data).unwrap();
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let with_progress = |manager: IoManager| {
+            let reports = Arc::clone(&reports);
+            manager.with_progress(move |read| reports.lock().unwrap().push(read))
+        };
+
+        let manager = with_progress(IoManager::new(&input, &compressed).unwrap());
+        manager.run(compressing::lzw::enc).unwrap();
+        let manager = with_progress(IoManager::new(&compressed, &output).unwrap());
+        manager.run(compressing::lzw::dec).unwrap();
+
+        assert_eq!(fs::read(&output).unwrap(), data);
+        let reports = reports.lock().unwrap();
+        assert!(reports.len() > 1);
+        assert!(reports.contains(&100_000));
+
+        for path in [input, compressed, output] {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_decompressed_name() {
+        let name = |path: &str| decompressed_name(Path::new(path));
+        assert_eq!(name("foo.txt.lzw"), Ok(PathBuf::from("foo.txt")));
+        assert_eq!(name("dir/foo.huffman"), Ok(PathBuf::from("foo")));
+        assert!(name("foo").is_err());
+        assert!(name("dir/.lzw").is_err());
+    }
+
+    #[test]
+    fn test_batch_decompress() {
+        let dir = temp_path("batch-out");
+        fs::create_dir_all(&dir).unwrap();
+
+        let files = ["a.txt", "b.bin", "c"].map(|name| {
+            let data = format!("contents of {name}, {name}, {name}").into_bytes();
+            let input = temp_path(&format!("{name}.lzw"));
+            fs::write(
+                &input,
+                compressing::compress(compressing::Algorithm::Lzw, &data).unwrap(),
+            )
+            .unwrap();
+            (name, data, input)
+        });
+
+        let mut data = DecompressData {
+            inputs: files.iter().map(|(_, _, input)| input.clone()).collect(),
+            output: None,
+            output_dir: Some(dir.clone()),
+            force: false,
+        };
+        for (input, output) in data.jobs().unwrap() {
+            IoManager::new(&input, &output)
+                .unwrap()
+                .run(compressing::lzw::dec)
+                .unwrap();
+        }
+
+        for (name, expected, _) in &files {
+            let name = format!("cmpr-{}-{name}", std::process::id());
+            assert_eq!(fs::read(dir.join(name)).unwrap(), *expected);
+        }
+
+        // The outputs now exist.
+        assert!(data.jobs().unwrap_err().contains("already exists"));
+        data.force = true;
+        assert_eq!(data.jobs().unwrap().len(), 3);
+
+        // Two inputs with the same stem collide.
+        data.inputs.push(temp_path("a.txt.huffman"));
+        assert!(data.jobs().unwrap_err().contains("more than one input"));
+
+        for (_, _, input) in files {
+            fs::remove_file(input).unwrap();
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// Returns a path in the temporary directory unique to the given test.
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("cmpr-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn test_run_with_fake_clock() {
+        let input = temp_path("clock-in");
+        let output = temp_path("clock-out");
+        fs::write(&input, b"ABBABBBABBA").unwrap();
+
+        let clock = FakeClock {
+            now: Cell::new(Instant::now()),
+            step: Duration::from_millis(5),
+        };
+        let manager = IoManager::new(&input, &output).unwrap().with_clock(clock);
+        let stats = manager.run(compressing::lzw::enc).unwrap();
+
+        assert_eq!(stats.elapsed, Duration::from_millis(5));
+        assert_eq!(stats.read, 11);
+        assert_eq!(stats.written, 14);
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_buffer_size() {
+        let input = temp_path("buffer-in");
+        let output = temp_path("buffer-out");
+        fs::write(&input, vec![b'A'; 256 * 1024]).unwrap();
+
+        // Copies a byte at a time, so that only the buffers batch the reads.
+        let copy_bytes = |src: &mut dyn Read, out: &mut dyn Write| {
+            let mut byte = [0];
+            while src.read(&mut byte)? == 1 {
+                out.write_all(&byte)?;
+            }
+            Ok(())
+        };
+        let run = |buffer_size| {
+            let input = Input::open(&input).unwrap();
+            let manager = IoManager::with_input(input, &output, buffer_size).unwrap();
+            manager.run(copy_bytes).unwrap()
+        };
+        let small = run(4 * 1024);
+        let large = run(64 * 1024);
+
+        assert_eq!(small.read, large.read);
+        // Both end with the same (empty) reads at the end of the input.
+        assert_eq!(small.read_ops - large.read_ops, 64 - 4);
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_refuses_terminal() {
+        assert!(refuses_terminal(true, false, false));
+        assert!(!refuses_terminal(true, true, false));
+        assert!(!refuses_terminal(true, false, true));
+        assert!(!refuses_terminal(false, false, false));
+    }
+
+    #[test]
+    fn test_run_with_sync() {
+        let input = temp_path("sync-in");
+        let output = temp_path("sync-out");
+        fs::write(&input, b"ABBABBBABBA").unwrap();
+
+        let manager = IoManager::new(&input, &output).unwrap().with_sync(true);
+        let stats = manager.run(compressing::lzw::enc).unwrap();
+        assert_eq!(stats.written, 14);
+        assert_eq!(
+            fs::read(&output).unwrap(),
+            compressing::compress(compressing::Algorithm::Lzw, b"ABBABBBABBA").unwrap()
+        );
+
+        fs::remove_file(input).unwrap();
+        fs::remove_file(output).unwrap();
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mmap_matches_read() {
+        let data: Vec<u8> = (0..50_000u32).map(|i| (i % 13 * 17 % 256) as u8).collect();
+        for (name, data) in [("mmap", data.as_slice()), ("mmap-empty", b"")] {
+            let input = temp_path(&format!("{name}-in"));
+            let read_out = temp_path(&format!("{name}-read"));
+            let mapped_out = temp_path(&format!("{name}-mapped"));
+            let compressed = compressing::compress(compressing::Algorithm::Lzw, data).unwrap();
+            fs::write(&input, compressed).unwrap();
+
+            let read = IoManager::new(&input, &read_out).unwrap();
+            read.run(compressing::lzw::dec).unwrap();
+            let mapped = Input::map(&input).unwrap();
+            assert_eq!(mapped.is_mapped(), !data.is_empty());
+            let mapped = IoManager::with_input(mapped, &mapped_out, 64 * 1024).unwrap();
+            let stats = mapped.run(compressing::lzw::dec).unwrap();
+
+            assert_eq!(stats.read, fs::metadata(&input).unwrap().len());
+            assert_eq!(fs::read(&mapped_out).unwrap(), fs::read(&read_out).unwrap());
+            assert_eq!(fs::read(&mapped_out).unwrap(), data);
+
+            for path in [input, read_out, mapped_out] {
+                fs::remove_file(path).unwrap();
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{fs, path::PathBuf};
+
+use clap::Args;
+use compressing::lzw::{LzwOptions, MAX_BITS_RANGE};
+use serde::Deserialize;
+
+use crate::error::AppError;
+
+/// The codec options, which may be loaded from a file and then overridden.
+#[derive(Debug, Args)]
+pub struct OptionsArgs {
+    /// A JSON file with the codec options, e.g., `{"lzw": {"max_key_len": 8}}`.
+    /// Any option also given on the command line is overridden.
+    #[arg(long, value_name = "FILE")]
+    options_file: Option<PathBuf>,
+
+    /// The maximum length of an LZW dictionary key (unbounded by default).
+    #[arg(long, value_name = "LEN")]
+    max_key_len: Option<usize>,
+
+    /// Whether LZW codes should have a variable width.
+    #[arg(long)]
+    variable_width: bool,
+
+    /// The maximum width of variable-width LZW codes, from 9 to 16 bits (16 by
+    /// default). Decompressing doesn't need it.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(
+            i64::from(*MAX_BITS_RANGE.start())..=i64::from(*MAX_BITS_RANGE.end())
+        )
+    )]
+    max_bits: Option<u32>,
+}
+
+/// The contents of an options file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct OptionsFile {
+    lzw: LzwOptions,
+}
+
+impl OptionsArgs {
+    /// Returns the LZW options: the ones in the options file (if any), with the
+    /// ones given on the command line on top.
+    pub fn lzw_options(&self) -> Result<LzwOptions, AppError> {
+        let mut options = match &self.options_file {
+            Some(path) => load(path)?.lzw,
+            None => LzwOptions::default(),
+        };
+        if let Some(max_key_len) = self.max_key_len {
+            options.max_key_len = Some(max_key_len);
+        }
+        if self.variable_width {
+            options.variable_width = true;
+        }
+        if let Some(max_bits) = self.max_bits {
+            options.max_bits = Some(max_bits);
+        }
+        Ok(options)
+    }
+}
+
+fn load(path: &PathBuf) -> Result<OptionsFile, AppError> {
+    let context = || format!("could not load the options in '{}'", path.display());
+    let contents = fs::read_to_string(path).map_err(AppError::io(context()))?;
+    serde_json::from_str(&contents).map_err(|error| AppError::io(context())(error.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        options: OptionsArgs,
+    }
+
+    fn parse(args: &[&str]) -> Result<Cli, clap::Error> {
+        Cli::try_parse_from(std::iter::once("cmpr").chain(args.iter().copied()))
+    }
+
+    fn lzw_options(args: &[&str]) -> Result<LzwOptions, AppError> {
+        parse(args).unwrap().options.lzw_options()
+    }
+
+    #[test]
+    fn test_max_bits() {
+        let options = lzw_options(&["--variable-width", "--max-bits", "12"]).unwrap();
+        assert_eq!(options.max_bits, Some(12));
+        for max_bits in ["8", "17", "x"] {
+            assert!(parse(&["--max-bits", max_bits]).is_err(), "{max_bits}");
+        }
+    }
+
+    #[test]
+    fn test_options_file() {
+        let path = env::temp_dir().join(format!("cmpr-{}-options.json", std::process::id()));
+        let path_str = path.to_str().unwrap();
+        fs::write(
+            &path,
+            r#"{"lzw": {"max_key_len": 8, "variable_width": true}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(lzw_options(&[]).unwrap(), LzwOptions::default());
+        let from_file = LzwOptions {
+            max_key_len: Some(8),
+            variable_width: true,
+            ..LzwOptions::default()
+        };
+        assert_eq!(
+            lzw_options(&["--options-file", path_str]).unwrap(),
+            from_file
+        );
+        // The command line wins.
+        assert_eq!(
+            lzw_options(&["--options-file", path_str, "--max-key-len", "3"]).unwrap(),
+            LzwOptions {
+                max_key_len: Some(3),
+                ..from_file
+            }
+        );
+
+        // Options not in the file keep their defaults.
+        fs::write(&path, r#"{"lzw": {"max_key_len": 8}}"#).unwrap();
+        assert_eq!(
+            lzw_options(&["--options-file", path_str, "--variable-width"]).unwrap(),
+            from_file
+        );
+
+        fs::write(&path, r#"{"lzw": {"max_key_length": 8}}"#).unwrap();
+        let error = lzw_options(&["--options-file", path_str]).unwrap_err();
+        assert!(error.to_string().contains("unknown field"));
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,53 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+};
+
+/// An output file, or the standard output.
+pub enum Output {
+    File(File),
+    Stdout(io::Stdout),
+}
+
+impl Output {
+    /// Creates (or truncates) the given file for writing.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path)
+            .map(Output::File)
+    }
+
+    /// Returns the standard output.
+    pub fn stdout() -> Self {
+        Output::Stdout(io::stdout())
+    }
+
+    /// Syncs a file to disk (see [`File::sync_all`]). The standard output has
+    /// nothing to sync, past flushing it (which `IoManager::run` does anyway).
+    pub fn sync_all(&self) -> io::Result<()> {
+        match self {
+            Output::File(file) => file.sync_all(),
+            Output::Stdout(_) => Ok(()),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::File(file) => file.write(buf),
+            Output::Stdout(stdout) => stdout.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::File(file) => file.flush(),
+            Output::Stdout(stdout) => stdout.flush(),
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+
+/// The minimum time between two redraws.
+const INTERVAL: Duration = Duration::from_millis(100);
+
+/// The width of the bar, in characters.
+const WIDTH: usize = 30;
+
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+/// A textual progress bar, driven by the number of bytes processed so far.
+///
+/// If the total is unknown, a spinner and the byte count are shown instead.
+/// Write errors are ignored, since the progress report is best-effort.
+pub struct ProgressBar<W> {
+    out: W,
+    total: Option<u64>,
+    last: Option<Instant>,
+    frame: usize,
+}
+
+impl<W: Write> ProgressBar<W> {
+    /// Constructs a new [`ProgressBar`].
+    pub fn new(out: W, total: Option<u64>) -> Self {
+        Self {
+            out,
+            total,
+            last: None,
+            frame: 0,
+        }
+    }
+
+    /// Redraws the bar, unless it was redrawn less than [`INTERVAL`] ago.
+    pub fn update(&mut self, done: u64) {
+        if self.last.is_none_or(|last| last.elapsed() >= INTERVAL) {
+            self.draw(done);
+            self.last = Some(Instant::now());
+        }
+    }
+
+    /// Draws the final state of the bar and ends its line.
+    pub fn finish(&mut self, done: u64) {
+        self.draw(done);
+        _ = writeln!(self.out);
+    }
+
+    fn draw(&mut self, done: u64) {
+        let line = render(self.total, done, self.frame);
+        self.frame = self.frame.wrapping_add(1);
+        _ = write!(self.out, "\r{line}");
+        _ = self.out.flush();
+    }
+}
+
+fn render(total: Option<u64>, done: u64, frame: usize) -> String {
+    match total {
+        Some(total) if total > 0 => {
+            let ratio = (done as f64 / total as f64).min(1.0);
+            let filled = (ratio * WIDTH as f64) as usize;
+            format!(
+                "[{}{}] {:>3}%",
+                "#".repeat(filled),
+                "-".repeat(WIDTH - filled),
+                (ratio * 100.0) as u32
+            )
+        }
+        _ => format!("{} {done} bytes", SPINNER[frame % SPINNER.len()]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(Some(200), 0, 0),
+            format!("[{}]   0%", "-".repeat(30))
+        );
+        assert_eq!(
+            render(Some(200), 100, 0),
+            format!("[{}{}]  50%", "#".repeat(15), "-".repeat(15))
+        );
+        assert_eq!(
+            render(Some(200), 200, 0),
+            format!("[{}] 100%", "#".repeat(30))
+        );
+        assert_eq!(render(None, 42, 1), "/ 42 bytes");
+    }
+
+    #[test]
+    fn test_throttle() {
+        let mut out = Vec::new();
+        let mut bar = ProgressBar::new(&mut out, Some(10));
+        for done in 0..10 {
+            bar.update(done);
+        }
+        bar.finish(10);
+
+        // The first update draws, the next ones (within the interval) don't.
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches('\r').count(), 2);
+        assert!(out.ends_with("100%\n"));
+    }
+}
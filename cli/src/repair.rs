@@ -0,0 +1,46 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::PathBuf,
+};
+
+use clap::Args;
+use compressing::lzw::{self, LzwOptions};
+
+use crate::error::AppError;
+
+#[derive(Debug, Args)]
+pub struct RepairData {
+    /// The (possibly truncated or corrupt) LZW file to recover.
+    input: PathBuf,
+
+    /// The output path, which receives the recovered data.
+    #[arg(short)]
+    output: PathBuf,
+}
+
+/// Runs the `repair` action, decoding as much of the input as possible.
+pub fn run(data: &RepairData, options: &LzwOptions) -> Result<(), AppError> {
+    let input = data.input.display();
+    let output = data.output.display();
+    let src = File::open(&data.input).map_err(AppError::io(format!("could not open '{input}'")))?;
+    let out =
+        File::create(&data.output).map_err(AppError::io(format!("could not create '{output}'")))?;
+    let mut out = BufWriter::new(out);
+    let report = lzw::dec_best_effort_with(&mut BufReader::new(src), &mut out, options)
+        .and_then(|report| out.flush().map(|()| report))
+        .map_err(AppError::io(format!("could not repair '{input}'")))?;
+
+    if report.complete {
+        eprintln!(
+            "recovered {} bytes (the stream is complete)",
+            report.recovered
+        );
+    } else {
+        eprintln!(
+            "recovered {} bytes, up to where the stream is cut off or corrupt",
+            report.recovered
+        );
+    }
+    Ok(())
+}
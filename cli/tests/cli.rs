@@ -0,0 +1,755 @@
+use std::{
+    fs,
+    io::Write,
+    process::{self, Stdio},
+};
+
+use assert_cmd::{cargo::CommandCargoExt, Command};
+use predicates::prelude::*;
+use tempfile::TempDir;
+
+const DATA: &[u8] = b"ABBABBBABBA, ol\xC3\xA1, mundo! como vai? ABBABBBABBA";
+
+fn cmpr() -> Command {
+    Command::cargo_bin("cmpr").unwrap()
+}
+
+#[test]
+fn test_roundtrip() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    let compressed = dir.path().join("input.txt.cmp");
+    let output = dir.path().join("output.txt");
+    fs::write(&input, DATA).unwrap();
+
+    for algorithm in ["lzw", "huffman", "stored"] {
+        cmpr()
+            .args(["-a", algorithm, "compress", "-o"])
+            .args([&compressed, &input])
+            .assert()
+            .success();
+        cmpr()
+            .args(["-a", algorithm, "decompress", "-o"])
+            .args([&output, &compressed])
+            .assert()
+            .success();
+        assert_eq!(fs::read(&output).unwrap(), DATA, "{algorithm}");
+    }
+}
+
+#[test]
+fn test_stats() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    fs::write(&input, DATA).unwrap();
+
+    cmpr()
+        .args(["-a", "lzw", "--stats", "compress", "-o"])
+        .arg(dir.path().join("input.txt.lzw"))
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("done.\n"))
+        .stdout(predicate::str::is_match(r"(?m)^    saved -?\d+\.\d{2}%$").unwrap());
+}
+
+#[test]
+fn test_batch_stats() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    fs::create_dir(&out).unwrap();
+    let mut inputs = Vec::new();
+    for name in ["a.txt", "b.txt"] {
+        let input = dir.path().join(name);
+        fs::write(&input, DATA).unwrap();
+        let compressed = dir.path().join(format!("{name}.lzw"));
+        cmpr()
+            .args(["-a", "lzw", "compress", "-o"])
+            .args([&compressed, &input])
+            .assert()
+            .success();
+        inputs.push(compressed);
+    }
+    let size: u64 = inputs.iter().map(|i| fs::metadata(i).unwrap().len()).sum();
+
+    cmpr()
+        .args(["-a", "lzw", "--stats", "decompress", "--output-dir"])
+        .arg(&out)
+        .args(&inputs)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "all done (2 files).\n    {size} bytes in, {} bytes out\n",
+            2 * DATA.len()
+        )));
+}
+
+#[test]
+fn test_force_overwrites() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("a.txt.lzw");
+    let out = dir.path().join("out");
+    fs::create_dir(&out).unwrap();
+    fs::write(out.join("a.txt"), b"stale").unwrap();
+    fs::write(dir.path().join("a.txt"), DATA).unwrap();
+    cmpr()
+        .args(["-a", "lzw", "compress", "-o"])
+        .args([&input, &dir.path().join("a.txt")])
+        .assert()
+        .success();
+    let decompress = |args: &[&str]| {
+        let mut cmd = cmpr();
+        cmd.args(["-a", "lzw"]).args(args).arg(&out).arg(&input);
+        cmd
+    };
+
+    decompress(&["decompress", "--output-dir"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("use `--force` to overwrite"));
+    // `--force` belongs to decompress, the terminal override being another
+    // flag.
+    decompress(&["--force", "decompress", "--output-dir"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unexpected argument '--force'"));
+    decompress(&["decompress", "--force", "--output-dir"])
+        .assert()
+        .success();
+    assert!(fs::read(out.join("a.txt")).unwrap() == DATA);
+}
+
+#[test]
+fn test_missing_input() {
+    let dir = TempDir::new().unwrap();
+    let output = dir.path().join("output.lzw");
+
+    cmpr()
+        .args(["-a", "lzw", "compress", "-o"])
+        .arg(&output)
+        .arg(dir.path().join("missing.txt"))
+        .assert()
+        .code(1)
+        .stderr(predicate::str::is_match(r"^error: could not open '.*missing\.txt': ").unwrap());
+    assert!(!output.exists());
+}
+
+#[test]
+fn test_missing_algorithm() {
+    cmpr()
+        .args(["compress", "-o", "out.lzw", "in.txt"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("-a <ALGORITHM>"));
+}
+
+#[test]
+fn test_invalid_data() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.huffman");
+    fs::write(&input, [1, 1]).unwrap();
+
+    cmpr()
+        .args(["-a", "huffman", "decompress", "-o"])
+        .arg(dir.path().join("output"))
+        .arg(&input)
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("could not decompress"))
+        .stderr(predicate::str::contains("too many Huffman symbols"));
+}
+
+#[test]
+fn test_remove() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    let compressed = dir.path().join("input.txt.lzw");
+    fs::write(&input, DATA).unwrap();
+
+    // Kept by default (and by `--keep`, which `--remove` overrides).
+    cmpr()
+        .args(["-a", "lzw", "--keep", "compress", "-o"])
+        .args([&compressed, &input])
+        .assert()
+        .success();
+    assert!(input.exists());
+
+    cmpr()
+        .args(["-a", "lzw", "--keep", "--rm", "compress", "-o"])
+        .args([&compressed, &input])
+        .assert()
+        .success();
+    assert!(!input.exists());
+
+    let output = dir.path().join("output.txt");
+    cmpr()
+        .args(["-a", "lzw", "--remove", "decompress", "-o"])
+        .args([&output, &compressed])
+        .assert()
+        .success();
+    assert!(!compressed.exists());
+    assert_eq!(fs::read(&output).unwrap(), DATA);
+}
+
+#[test]
+fn test_remove_keeps_input_on_failure() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.huffman");
+    fs::write(&input, [1, 1]).unwrap();
+
+    cmpr()
+        .args(["-a", "huffman", "--remove", "decompress", "-o"])
+        .arg(dir.path().join("output"))
+        .arg(&input)
+        .assert()
+        .code(1);
+    assert!(input.exists());
+}
+
+/// Writing to `/dev/full` always fails (with `ENOSPC`).
+#[cfg(target_os = "linux")]
+#[test]
+fn test_remove_keeps_input_on_write_failure() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    fs::write(&input, DATA).unwrap();
+
+    cmpr()
+        .args(["-a", "stored", "--remove", "compress", "-o", "/dev/full"])
+        .arg(&input)
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("could not compress"));
+    assert!(input.exists());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_stdout_write_failure() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    fs::write(&input, DATA).unwrap();
+    let full = || {
+        fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/full")
+            .unwrap()
+    };
+
+    let output = process::Command::cargo_bin("cmpr")
+        .unwrap()
+        .args(["-a", "lzw", "compress"])
+        .arg(&input)
+        .stdout(full())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("could not compress"), "{stderr}");
+
+    // Stdout can't be synced, so the input never goes.
+    let output = process::Command::cargo_bin("cmpr")
+        .unwrap()
+        .args(["-a", "lzw", "--remove", "compress"])
+        .arg(&input)
+        .stdout(full())
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("`--remove` needs `-o <OUTPUT>`"),
+        "{stderr}"
+    );
+    assert!(input.exists());
+}
+
+#[test]
+fn test_long_version() {
+    cmpr()
+        .arg("--version")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("compressing "))
+        .stdout(predicate::str::contains("lzw: 1"));
+}
+
+#[test]
+fn test_pipe() {
+    let data = DATA.repeat(100);
+    let spawn = |args: &[&str], stdin: Stdio| {
+        process::Command::cargo_bin("cmpr")
+            .unwrap()
+            .args(args)
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap()
+    };
+    let mut compress = spawn(&["-a", "lzw", "--stats", "--pipe"], Stdio::piped());
+    let compressed = compress.stdout.take().unwrap();
+    let decompress = spawn(&["-a", "lzw", "--pipe", "-d"], compressed.into());
+    compress.stdin.take().unwrap().write_all(&data).unwrap();
+
+    let decompressed = decompress.wait_with_output().unwrap();
+    assert!(decompressed.status.success());
+    assert!(decompressed.stdout == data);
+    let compressed = compress.wait_with_output().unwrap();
+    assert!(compressed.status.success());
+    // The statistics stay out of the data.
+    let stats = String::from_utf8(compressed.stderr).unwrap();
+    assert!(stats.starts_with("done.\n"), "{stats}");
+    assert!(stats.contains("    saved "), "{stats}");
+}
+
+/// Stdout buffers its writes, which must still fail (see `/dev/full` above).
+#[cfg(target_os = "linux")]
+#[test]
+fn test_pipe_write_failure() {
+    let mut child = process::Command::cargo_bin("cmpr")
+        .unwrap()
+        .args(["-a", "lzw", "--pipe"])
+        .stdin(Stdio::piped())
+        .stdout(
+            fs::OpenOptions::new()
+                .write(true)
+                .open("/dev/full")
+                .unwrap(),
+        )
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(DATA).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("could not compress stdin"), "{stderr}");
+}
+
+#[test]
+fn test_pipe_usage() {
+    cmpr()
+        .args(["-a", "lzw", "--pipe", "compress", "-o", "out", "in"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("`--pipe` takes no subcommand"));
+    cmpr()
+        .args(["-a", "lzw", "-d", "decompress", "-o", "out", "in"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--pipe"));
+    cmpr()
+        .args(["-a", "auto", "--pipe"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("`-a auto`"));
+}
+
+#[test]
+fn test_compress_to_stdout() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    let output = dir.path().join("input.lzw");
+    fs::write(&input, DATA.repeat(10)).unwrap();
+    cmpr()
+        .args(["-a", "lzw", "compress", "-o"])
+        .args([&output, &input])
+        .assert()
+        .success();
+
+    // The test's stdout is no terminal, so the guard lets the data through.
+    let assert = cmpr()
+        .args(["-a", "lzw", "--stats", "compress"])
+        .arg(&input)
+        .assert()
+        .success()
+        .stderr(predicate::str::starts_with("done.\n"));
+    assert!(assert.get_output().stdout == fs::read(&output).unwrap());
+
+    cmpr()
+        .args(["-a", "lzw", "compress", "--compare-with", "gzip"])
+        .arg(&input)
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("-o <OUTPUT>"));
+}
+
+#[test]
+fn test_list_algorithms() {
+    cmpr()
+        .arg("--list-algorithms")
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::is_match(r"(?m)^lzw +1  Lempel-Ziv-Welch dictionary coder$").unwrap(),
+        )
+        .stdout(predicate::str::is_match(r"(?m)^huffman +\d+  Huffman entropy coder$").unwrap());
+
+    // Without it, a subcommand is still required.
+    cmpr()
+        .args(["-a", "lzw"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("a subcommand is required"));
+}
+
+#[test]
+fn test_armor() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    let armored = dir.path().join("input.txt.txt");
+    let output = dir.path().join("output.txt");
+    fs::write(&input, DATA).unwrap();
+
+    for (armor, name) in [("hex", "HEX"), ("base64", "BASE64")] {
+        cmpr()
+            .args(["-a", "lzw", "compress", "--armor", armor, "-o"])
+            .args([&armored, &input])
+            .assert()
+            .success();
+        let text = fs::read_to_string(&armored).unwrap();
+        assert!(text.starts_with(&format!("-----BEGIN COMPRESSING {name}-----\n")));
+
+        // The armor is detected.
+        cmpr()
+            .args(["-a", "lzw", "decompress", "-o"])
+            .args([&output, &armored])
+            .assert()
+            .success();
+        assert_eq!(fs::read(&output).unwrap(), DATA, "{armor}");
+    }
+}
+
+#[test]
+fn test_stored_armored_file() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    let armored = dir.path().join("input.txt.txt");
+    let stored = dir.path().join("input.txt.txt.cmp");
+    let output = dir.path().join("output.txt");
+    fs::write(&input, DATA).unwrap();
+    cmpr()
+        .args(["-a", "lzw", "compress", "--armor", "hex", "-o"])
+        .args([&armored, &input])
+        .assert()
+        .success();
+
+    // Stored data which looks armored comes back as is.
+    cmpr()
+        .args(["-a", "stored", "compress", "-o"])
+        .args([&stored, &armored])
+        .assert()
+        .success();
+    cmpr()
+        .args(["-a", "stored", "decompress", "-o"])
+        .args([&output, &stored])
+        .assert()
+        .success();
+    assert!(fs::read(&output).unwrap() == fs::read(&armored).unwrap());
+
+    cmpr()
+        .args(["-a", "stored", "compress", "--armor", "hex", "-o"])
+        .args([&stored, &input])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("doesn't apply to `-a stored`"));
+}
+
+#[test]
+fn test_max_bits() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    let compressed = dir.path().join("input.txt.lzw");
+    let output = dir.path().join("output.txt");
+    // Pseudo-random data over a few symbols, which fills 12-bit codes.
+    let mut state = 0x2545_F491_u32;
+    let data: Vec<u8> = (0..50_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            b"ACGT"[(state % 4) as usize]
+        })
+        .collect();
+    fs::write(&input, &data).unwrap();
+
+    let mut sizes = Vec::new();
+    for max_bits in ["12", "16"] {
+        cmpr()
+            .args(["-a", "lzw", "--variable-width", "--max-bits", max_bits])
+            .args(["compress", "-o"])
+            .args([&compressed, &input])
+            .assert()
+            .success();
+        sizes.push(fs::metadata(&compressed).unwrap().len());
+
+        // The width comes from the stream.
+        cmpr()
+            .args(["-a", "lzw", "--variable-width", "decompress", "-o"])
+            .args([&output, &compressed])
+            .assert()
+            .success();
+        assert!(fs::read(&output).unwrap() == data, "{max_bits}");
+    }
+    assert_ne!(sizes[0], sizes[1]);
+
+    cmpr()
+        .args(["-a", "lzw", "--max-bits", "17", "compress", "-o"])
+        .args([&compressed, &input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--max-bits"));
+}
+
+#[test]
+fn test_delta() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.bin");
+    let compressed = dir.path().join("input.bin.lzw");
+    let output = dir.path().join("output.bin");
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i * 3 / 2) as u8).collect();
+    fs::write(&input, &data).unwrap();
+
+    let mut sizes = Vec::new();
+    for args in [&[][..], &["--delta"]] {
+        cmpr()
+            .args(["-a", "lzw"])
+            .args(args)
+            .args(["compress", "-o"])
+            .args([&compressed, &input])
+            .assert()
+            .success();
+        sizes.push(fs::metadata(&compressed).unwrap().len());
+
+        // The output records `--delta`.
+        cmpr()
+            .args(["-a", "lzw", "decompress", "-o"])
+            .args([&output, &compressed])
+            .assert()
+            .success();
+        assert!(fs::read(&output).unwrap() == data, "{args:?}");
+    }
+    assert!(sizes[1] < sizes[0], "{sizes:?}");
+
+    // Under an armor too.
+    cmpr()
+        .args([
+            "-a", "huffman", "--delta", "compress", "--armor", "hex", "-o",
+        ])
+        .args([&compressed, &input])
+        .assert()
+        .success();
+    cmpr()
+        .args(["-a", "huffman", "decompress", "-o"])
+        .args([&output, &compressed])
+        .assert()
+        .success();
+    assert!(fs::read(&output).unwrap() == data);
+}
+
+#[test]
+fn test_stored_delta_coded_file() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.bin");
+    let marked = dir.path().join("input.bin.lzw");
+    let stored = dir.path().join("input.bin.lzw.cmp");
+    let output = dir.path().join("output.bin");
+    fs::write(&input, DATA).unwrap();
+    cmpr()
+        .args(["-a", "lzw", "--delta", "compress", "-o"])
+        .args([&marked, &input])
+        .assert()
+        .success();
+
+    // Stored data which starts with a delta marker comes back as is.
+    cmpr()
+        .args(["-a", "stored", "compress", "-o"])
+        .args([&stored, &marked])
+        .assert()
+        .success();
+    cmpr()
+        .args(["-a", "stored", "decompress", "-o"])
+        .args([&output, &stored])
+        .assert()
+        .success();
+    assert!(fs::read(&output).unwrap() == fs::read(&marked).unwrap());
+
+    let refused = predicate::str::contains("`--delta` doesn't apply to `-a stored`");
+    cmpr()
+        .args(["-a", "stored", "--delta", "compress", "-o"])
+        .args([&stored, &input])
+        .assert()
+        .code(2)
+        .stderr(refused.clone());
+    cmpr()
+        .args(["-a", "stored", "--delta", "--pipe"])
+        .assert()
+        .code(2)
+        .stderr(refused);
+}
+
+#[test]
+fn test_missing_output_dir() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    let output = dir.path().join("foo/bar/input.txt.lzw");
+    fs::write(&input, DATA).unwrap();
+
+    cmpr()
+        .args(["-a", "lzw", "compress", "-o"])
+        .args([&output, &input])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("foo/bar' does not exist"));
+    assert!(!dir.path().join("foo").exists());
+
+    cmpr()
+        .args(["-a", "lzw", "--mkdir", "compress", "-o"])
+        .args([&output, &input])
+        .assert()
+        .success();
+    assert!(output.is_file());
+}
+
+#[test]
+fn test_auto_algorithm() {
+    let dir = TempDir::new().unwrap();
+    let png = dir.path().join("image.png");
+    let text = dir.path().join("input.txt");
+    let output = dir.path().join("output.cmp");
+    fs::write(
+        &png,
+        b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x00\x01",
+    )
+    .unwrap();
+    fs::write(&text, DATA).unwrap();
+
+    cmpr()
+        .args(["-a", "auto", "-v", "compress", "-o"])
+        .args([&output, &png])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "looks like already compressed data (PNG), which `-a stored` suits best",
+        ));
+    // Stored mode copies the data as is.
+    assert_eq!(fs::read(&output).unwrap(), fs::read(&png).unwrap());
+
+    cmpr()
+        .args(["-a", "huffman", "-v", "compress", "-o"])
+        .args([&output, &text])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "looks like text, which `-a lzw` suits best",
+        ));
+
+    cmpr()
+        .args(["-a", "auto", "decompress", "-o"])
+        .args([&text, &output])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("only applies to compress"));
+}
+
+#[test]
+fn test_compare_with_gzip() {
+    if std::process::Command::new("gzip")
+        .arg("--version")
+        .output()
+        .is_err()
+    {
+        eprintln!("skipped: `gzip` isn't installed");
+        return;
+    }
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    let output = dir.path().join("input.txt.lzw");
+    fs::write(&input, DATA.repeat(100)).unwrap();
+
+    cmpr()
+        .args(["-a", "lzw", "compress", "--compare-with", "gzip", "-o"])
+        .args([&output, &input])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("tool  size (bytes)  time (ms)"))
+        .stdout(predicate::str::is_match(r"(?m)^cmpr +\d+ +\d+$").unwrap())
+        .stdout(predicate::str::is_match(r"(?m)^gzip +\d+ +\d+$").unwrap());
+}
+
+#[test]
+fn test_repair() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    let compressed = dir.path().join("input.txt.lzw");
+    let output = dir.path().join("output.txt");
+    fs::write(&input, DATA.repeat(10)).unwrap();
+    cmpr()
+        .args(["-a", "lzw", "compress", "-o"])
+        .args([&compressed, &input])
+        .assert()
+        .success();
+
+    // Cut off in the middle of a code.
+    let mut encoded = fs::read(&compressed).unwrap();
+    encoded.truncate(encoded.len() / 2 * 2 - 3);
+    fs::write(&compressed, &encoded).unwrap();
+    cmpr()
+        .args(["-a", "lzw", "decompress", "-o"])
+        .args([&output, &compressed])
+        .assert()
+        .code(1);
+
+    cmpr()
+        .args(["repair", "-o"])
+        .args([&output, &compressed])
+        .assert()
+        .success()
+        .stderr(
+            predicate::str::is_match(r"^recovered \d+ bytes, up to where the stream is cut off")
+                .unwrap(),
+        );
+    let recovered = fs::read(&output).unwrap();
+    assert!(!recovered.is_empty());
+    assert!(DATA.repeat(10).starts_with(&recovered));
+}
+
+#[test]
+fn test_stats_json() {
+    let dir = TempDir::new().unwrap();
+    let input = dir.path().join("input.txt");
+    let compressed = dir.path().join("input.txt.lzw");
+    let output = dir.path().join("output.txt");
+    let data = DATA.repeat(10);
+    fs::write(&input, &data).unwrap();
+
+    let assert = cmpr()
+        .args(["-a", "lzw", "--stats-json", "compress", "-o"])
+        .args([&compressed, &input])
+        .assert()
+        .success()
+        .stdout("");
+    let size = fs::metadata(&compressed).unwrap().len() as f64;
+    let json: serde_json::Value = serde_json::from_slice(&assert.get_output().stderr).unwrap();
+    assert_eq!(json["algorithm"], "lzw");
+    assert_eq!(json["input_bytes"], data.len());
+    assert_eq!(json["output_bytes"], size);
+    assert_eq!(json["ratio"], data.len() as f64 / size);
+    assert_eq!(
+        json["space_saved_percent"],
+        (1.0 - size / data.len() as f64) * 100.0
+    );
+    assert!(json["elapsed_us"].is_u64());
+
+    let assert = cmpr()
+        .args(["-a", "lzw", "--stats-json", "decompress", "-o"])
+        .args([&output, &compressed])
+        .assert()
+        .success();
+    let json: serde_json::Value = serde_json::from_slice(&assert.get_output().stderr).unwrap();
+    assert_eq!(json["input_bytes"], size);
+    assert_eq!(json["output_bytes"], data.len());
+    assert_eq!(json["ratio"], data.len() as f64 / size);
+    assert!(json.get("space_saved_percent").is_none());
+}
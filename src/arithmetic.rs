@@ -0,0 +1,346 @@
+//! An arithmetic coder over bytes, with a static frequency model.
+//!
+//! Unlike Huffman coding, which spends a whole number of bits on each symbol,
+//! arithmetic coding spends about `-log2(p)` bits on a symbol of probability
+//! `p`. It hence does better whenever the probabilities aren't powers of two.
+
+use std::io;
+
+use crate::{
+    huffman::{self, FreqMap},
+    shared::{BitReader, BitWriter},
+};
+
+/// The number of bits of the coder's state.
+const BITS: u32 = 32;
+const MAX: u64 = (1 << BITS) - 1;
+const HALF: u64 = 1 << (BITS - 1);
+const QUARTER: u64 = 1 << (BITS - 2);
+/// The total of the (scaled) frequencies may not exceed a quarter of the
+/// range, otherwise distinct symbols could map to the same interval.
+const MAX_TOTAL: u64 = QUARTER;
+
+/// The encoder emits this many bits after the terminating ones so that the
+/// decoder, which reads `BITS` bits ahead, never reads past the end of the
+/// data. This way, truncated data is detected.
+const TAIL_BITS: u32 = BITS - 2;
+
+/// Encodes the given data.
+///
+/// The output starts with the frequency table, in the same format as the one
+/// of [`huffman::enc`]. The arithmetic-coded bits follow, packed most
+/// significant bit first, with the last byte padded with zeroes.
+///
+/// Since two passes are needed over the data, `src` is buffered in memory.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`).
+pub fn enc(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let mut data = Vec::new();
+    src.read_to_end(&mut data)?;
+
+    let freq_map = huffman::freq_map_from_reader(&mut data.as_slice())?;
+    huffman::write_header(&freq_map, out)?;
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    let model = Model::new(&freq_map);
+    let mut encoder = Encoder {
+        writer: BitWriter::new(out),
+        low: 0,
+        high: MAX,
+        pending: 0,
+    };
+    for &char in &data {
+        encoder.encode(&model, char)?;
+    }
+    encoder.finish()
+}
+
+/// Decodes the given data.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`), if the data is truncated, or with
+/// [`CompressError::BadHeader`](crate::CompressError::BadHeader) if the
+/// frequency table is malformed.
+pub fn dec(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let freq_map = huffman::read_header(src)?;
+    let total: u64 = freq_map.values().sum();
+    if total == 0 {
+        return Ok(());
+    }
+
+    let model = Model::new(&freq_map);
+    let mut decoder = Decoder {
+        reader: BitReader::new(src),
+        low: 0,
+        high: MAX,
+        value: 0,
+    };
+    for _ in 0..BITS {
+        decoder.value = (decoder.value << 1) | decoder.next_bit()?;
+    }
+    for _ in 0..total {
+        out.write_all(&[decoder.decode(&model)?])?;
+    }
+    Ok(())
+}
+
+/// The cumulative frequencies of the symbols, scaled down (if needed) so that
+/// their total is at most [`MAX_TOTAL`].
+struct Model {
+    /// The interval of symbol `c` is `cum[c]..cum[c + 1]`.
+    cum: [u64; 257],
+}
+
+impl Model {
+    fn new(freq_map: &FreqMap) -> Self {
+        let total: u64 = freq_map.values().sum();
+        // The scaled frequencies are rounded up so that no symbol is lost,
+        // which may add up to one per symbol to the total.
+        let limit = MAX_TOTAL - 256;
+        let scale = |freq: u64| {
+            if total <= limit || freq == 0 {
+                freq
+            } else {
+                // `freq * limit` may not fit in a `u64`, but the quotient is
+                // at most `limit`, as `freq <= total`.
+                let scaled = u128::from(freq) * u128::from(limit) / u128::from(total);
+                (scaled as u64).max(1)
+            }
+        };
+
+        let mut cum = [0; 257];
+        for char in 0..=255 {
+            let freq = freq_map.get(&char).copied().unwrap_or(0);
+            cum[usize::from(char) + 1] = cum[usize::from(char)] + scale(freq);
+        }
+        Self { cum }
+    }
+
+    fn total(&self) -> u64 {
+        self.cum[256]
+    }
+
+    /// Returns the symbol whose interval contains `target`.
+    fn find(&self, target: u64) -> u8 {
+        // `cum[0] == 0 <= target < cum[256]`, hence `1 <= i <= 256`.
+        debug_assert!(target < self.total());
+        let i = self.cum.partition_point(|&cum| cum <= target);
+        (i - 1) as u8
+    }
+}
+
+/// Narrows `low..=high` to the subinterval of `char`.
+fn narrow(model: &Model, char: u8, low: &mut u64, high: &mut u64) {
+    let range = *high - *low + 1;
+    let total = model.total();
+    let i = usize::from(char);
+    *high = *low + range * model.cum[i + 1] / total - 1;
+    *low += range * model.cum[i] / total;
+}
+
+struct Encoder<'a> {
+    writer: BitWriter<'a>,
+    low: u64,
+    high: u64,
+    /// The number of underflow bits, which are opposite to the next bit
+    /// emitted, whatever it turns out to be.
+    pending: u64,
+}
+
+impl Encoder<'_> {
+    fn encode(&mut self, model: &Model, char: u8) -> io::Result<()> {
+        narrow(model, char, &mut self.low, &mut self.high);
+        loop {
+            if self.high < HALF {
+                self.emit(false)?;
+            } else if self.low >= HALF {
+                self.emit(true)?;
+                self.low -= HALF;
+                self.high -= HALF;
+            } else if self.low >= QUARTER && self.high < 3 * QUARTER {
+                // The interval straddles the middle while shrinking (i.e.,
+                // underflows), so the bit is deferred.
+                self.pending += 1;
+                self.low -= QUARTER;
+                self.high -= QUARTER;
+            } else {
+                return Ok(());
+            }
+            self.low <<= 1;
+            self.high = (self.high << 1) | 1;
+        }
+    }
+
+    fn emit(&mut self, bit: bool) -> io::Result<()> {
+        self.writer.write_bit(bit)?;
+        for _ in 0..self.pending {
+            self.writer.write_bit(!bit)?;
+        }
+        self.pending = 0;
+        Ok(())
+    }
+
+    /// Emits two more bits (along with the pending ones), which identify a
+    /// quarter lying within the final interval.
+    fn finish(mut self) -> io::Result<()> {
+        self.pending += 1;
+        self.emit(self.low >= QUARTER)?;
+        self.writer.write_bits(0, TAIL_BITS)?;
+        self.writer.finish()
+    }
+}
+
+struct Decoder<'a> {
+    reader: BitReader<'a>,
+    low: u64,
+    high: u64,
+    /// The next `BITS` bits of the data.
+    value: u64,
+}
+
+impl Decoder<'_> {
+    fn decode(&mut self, model: &Model) -> io::Result<u8> {
+        let range = self.high - self.low + 1;
+        let target = ((self.value - self.low + 1) * model.total() - 1) / range;
+        let char = model.find(target);
+
+        narrow(model, char, &mut self.low, &mut self.high);
+        loop {
+            if self.high < HALF {
+                // Nothing to subtract.
+            } else if self.low >= HALF {
+                self.value -= HALF;
+                self.low -= HALF;
+                self.high -= HALF;
+            } else if self.low >= QUARTER && self.high < 3 * QUARTER {
+                self.value -= QUARTER;
+                self.low -= QUARTER;
+                self.high -= QUARTER;
+            } else {
+                return Ok(char);
+            }
+            self.low <<= 1;
+            self.high = (self.high << 1) | 1;
+            self.value = (self.value << 1) | self.next_bit()?;
+        }
+    }
+
+    fn next_bit(&mut self) -> io::Result<u64> {
+        let bit = self.reader.read_bit()?.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "truncated arithmetic data")
+        })?;
+        Ok(u64::from(bit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(data: &[u8]) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        enc(&mut &*data, &mut encoded).unwrap();
+        let mut decoded = Vec::new();
+        dec(&mut encoded.as_slice(), &mut decoded).unwrap();
+        assert_eq!(decoded, data);
+        encoded
+    }
+
+    /// Returns `len` pseudo-random bytes, following a skewed distribution over
+    /// `b'A'..=b'D'` (of probabilities 0.7, 0.2, 0.05 and 0.05).
+    fn skewed(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                match state % 100 {
+                    0..70 => b'A',
+                    70..90 => b'B',
+                    90..95 => b'C',
+                    _ => b'D',
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        roundtrip(b"AAABBBAABACD");
+        roundtrip("olá, mundo! como vai? tudo bem?".as_bytes());
+        roundtrip(&(0..=255).cycle().take(10_000).collect::<Vec<u8>>());
+        roundtrip(&skewed(50_000));
+    }
+
+    #[test]
+    fn test_roundtrip_empty() {
+        // Only the (empty) frequency table.
+        assert_eq!(roundtrip(b""), [0, 0]);
+    }
+
+    #[test]
+    fn test_roundtrip_single_symbol() {
+        let encoded = roundtrip(b"ZZZZ");
+        assert_eq!(encoded[..4], [0, 1, b'Z', 4]);
+    }
+
+    #[test]
+    fn test_header_matches_huffman() {
+        let data = b"AAABBBAABACD";
+        let mut arithmetic = Vec::new();
+        enc(&mut data.as_ref(), &mut arithmetic).unwrap();
+        let mut huffman = Vec::new();
+        huffman::enc(&mut data.as_ref(), &mut huffman).unwrap();
+        // The count, then 4 symbols and their 1-byte frequencies.
+        assert_eq!(arithmetic[..10], huffman[..10]);
+    }
+
+    #[test]
+    fn test_beats_huffman_on_skewed() {
+        // Huffman spends at least 1 bit on `A`, whereas the entropy is ~1.26
+        // bits per byte.
+        let data = skewed(100_000);
+        let arithmetic = roundtrip(&data);
+        let mut huffman = Vec::new();
+        huffman::enc(&mut data.as_slice(), &mut huffman).unwrap();
+        assert!(
+            arithmetic.len() * 100 < huffman.len() * 95,
+            "{} vs {} bytes",
+            arithmetic.len(),
+            huffman.len()
+        );
+    }
+
+    #[test]
+    fn test_scaled_model() {
+        // Frequencies whose total exceeds `MAX_TOTAL` are scaled down, but
+        // rare symbols keep a non-empty interval.
+        let freq_map = FreqMap::from([(b'A', 1 << 40), (b'B', 1), (b'C', 1_000)]);
+        let model = Model::new(&freq_map);
+        assert!(model.total() <= MAX_TOTAL);
+        assert_eq!(
+            model.cum[usize::from(b'B') + 1] - model.cum[usize::from(b'B')],
+            1
+        );
+        assert_eq!(model.find(0), b'A');
+        assert_eq!(model.find(model.total() - 1), b'C');
+    }
+
+    #[test]
+    fn test_dec_truncated() {
+        let mut encoded = Vec::new();
+        enc(&mut skewed(1_000).as_slice(), &mut encoded).unwrap();
+        encoded.pop();
+
+        let error = dec(&mut encoded.as_slice(), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,368 @@
+//! A text armor, which wraps binary (e.g., compressed) data as hex or base64
+//! text, for transports which only carry text.
+//!
+//! The armored text starts with a header line naming the encoding (e.g.,
+//! `-----BEGIN COMPRESSING BASE64-----`), and ends with a footer line
+//! (`-----END COMPRESSING-----`). The encoded data is split into lines in
+//! between; whitespace within it is ignored when decoding.
+
+use std::io::{self, BufRead, BufReader, Read};
+
+/// The start of the header line, which is followed by the encoding's name.
+const BEGIN: &str = "-----BEGIN COMPRESSING ";
+/// The footer line.
+const END: &str = "-----END COMPRESSING-----";
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The text encodings of the armor.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Armor {
+    Hex,
+    Base64,
+}
+
+impl Armor {
+    /// Returns the name of the encoding, as in the header line.
+    pub fn name(self) -> &'static str {
+        match self {
+            Armor::Hex => "HEX",
+            Armor::Base64 => "BASE64",
+        }
+    }
+
+    /// The number of text characters per line.
+    fn line_len(self) -> usize {
+        match self {
+            Armor::Hex => 64,
+            Armor::Base64 => 76,
+        }
+    }
+}
+
+/// A writer which armors the data written to it.
+///
+/// [`ArmorWriter::finish`] must be called once all data is written.
+pub struct ArmorWriter<'a> {
+    out: &'a mut dyn io::Write,
+    armor: Armor,
+    /// The bytes of an incomplete base64 group.
+    group: Vec<u8>,
+    /// The number of characters on the current line.
+    column: usize,
+}
+
+impl<'a> ArmorWriter<'a> {
+    /// Constructs a new [`ArmorWriter`], writing the header line right away.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing to `out` fails.
+    pub fn new(out: &'a mut dyn io::Write, armor: Armor) -> io::Result<Self> {
+        writeln!(out, "{BEGIN}{}-----", armor.name())?;
+        Ok(Self {
+            out,
+            armor,
+            group: Vec::with_capacity(3),
+            column: 0,
+        })
+    }
+
+    /// Writes the pending base64 group (padded) and the footer line.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing to the underlying writer fails.
+    pub fn finish(mut self) -> io::Result<()> {
+        if !self.group.is_empty() {
+            let chars = base64_group(&self.group);
+            self.put(&chars)?;
+        }
+        if self.column > 0 {
+            self.out.write_all(b"\n")?;
+        }
+        writeln!(self.out, "{END}")
+    }
+
+    fn put(&mut self, chars: &[u8]) -> io::Result<()> {
+        for &char in chars {
+            self.out.write_all(&[char])?;
+            self.column += 1;
+            if self.column == self.armor.line_len() {
+                self.out.write_all(b"\n")?;
+                self.column = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl io::Write for ArmorWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            match self.armor {
+                Armor::Hex => {
+                    let hex = |n: u8| b"0123456789abcdef"[usize::from(n)];
+                    self.put(&[hex(byte >> 4), hex(byte & 0xF)])?;
+                }
+                Armor::Base64 => {
+                    self.group.push(byte);
+                    if self.group.len() == 3 {
+                        let chars = base64_group(&self.group);
+                        self.group.clear();
+                        self.put(&chars)?;
+                    }
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Encodes a group of up to 3 bytes as 4 base64 characters, padded with `=`.
+fn base64_group(group: &[u8]) -> [u8; 4] {
+    let mut bytes = [0; 3];
+    bytes[..group.len()].copy_from_slice(group);
+    let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+    let mut chars = [b'='; 4];
+    for (i, char) in chars.iter_mut().enumerate().take(group.len() + 1) {
+        *char = BASE64[(n >> (18 - 6 * i) & 0x3F) as usize];
+    }
+    chars
+}
+
+/// Returns a reader over the data of `src`, dearmoring it if it starts with
+/// an armor header line, and passing it through otherwise.
+///
+/// Unarmored data must thus never start with an armor header: it holds for
+/// the LZW and Huffman streams, but not for arbitrary (e.g., stored) data.
+///
+/// # Errors
+///
+/// Fails if reading from `src` fails, or with [`io::ErrorKind::InvalidData`]
+/// if the header names an unknown encoding.
+pub fn reader<'a>(src: &'a mut dyn Read) -> io::Result<Box<dyn Read + 'a>> {
+    let mut src = BufReader::new(src);
+    if !src.fill_buf()?.starts_with(BEGIN.as_bytes()) {
+        return Ok(Box::new(src));
+    }
+
+    let mut header = String::new();
+    src.read_line(&mut header)?;
+    let armor = match header.trim_end().strip_prefix(BEGIN) {
+        Some("HEX-----") => Armor::Hex,
+        Some("BASE64-----") => Armor::Base64,
+        _ => return Err(invalid("unknown armor encoding")),
+    };
+    Ok(Box::new(Dearmor {
+        src,
+        armor,
+        digits: Vec::with_capacity(4),
+        decoded: Vec::new(),
+        pos: 0,
+        done: false,
+    }))
+}
+
+/// Decodes armored text, one line at a time.
+struct Dearmor<'a> {
+    src: BufReader<&'a mut dyn Read>,
+    armor: Armor,
+    /// The values of the digits (of a byte or base64 group) decoded so far,
+    /// which may span lines.
+    digits: Vec<u8>,
+    decoded: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl Dearmor<'_> {
+    /// Decodes the next line into `decoded`.
+    fn decode_line(&mut self) -> io::Result<()> {
+        self.decoded.clear();
+        self.pos = 0;
+
+        let mut line = String::new();
+        if self.src.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated armor (missing footer)",
+            ));
+        }
+        if line.trim() == END {
+            if !self.digits.is_empty() {
+                return Err(invalid("truncated armor data"));
+            }
+            self.done = true;
+            return Ok(());
+        }
+
+        for char in line.bytes().filter(|b| !b.is_ascii_whitespace()) {
+            match self.armor {
+                Armor::Hex => {
+                    let digit = (char as char)
+                        .to_digit(16)
+                        .ok_or_else(|| invalid("invalid hex armor"))?;
+                    self.digits.push(digit as u8);
+                    if let [hi, lo] = self.digits[..] {
+                        self.decoded.push(hi << 4 | lo);
+                        self.digits.clear();
+                    }
+                }
+                Armor::Base64 => {
+                    self.digits.push(char);
+                    if self.digits.len() == 4 {
+                        decode_base64_group(&self.digits, &mut self.decoded)?;
+                        self.digits.clear();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Read for Dearmor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.decoded.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.decode_line()?;
+        }
+        let n = buf.len().min(self.decoded.len() - self.pos);
+        buf[..n].copy_from_slice(&self.decoded[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Decodes 4 base64 characters (the last ones possibly being padding).
+fn decode_base64_group(chars: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+    let padding = chars.iter().rev().take_while(|&&c| c == b'=').count();
+    if padding > 2 {
+        return Err(invalid("invalid base64 armor"));
+    }
+    let mut n = 0;
+    for &char in &chars[..4 - padding] {
+        let value = BASE64
+            .iter()
+            .position(|&c| c == char)
+            .ok_or_else(|| invalid("invalid base64 armor"))?;
+        n = n << 6 | value as u32;
+    }
+    n <<= 6 * padding;
+    out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    Ok(())
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn armored(data: &[u8], armor: Armor) -> String {
+        let mut out = Vec::new();
+        let mut writer = ArmorWriter::new(&mut out, armor).unwrap();
+        writer.write_all(data).unwrap();
+        writer.finish().unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn dearmored(text: &[u8]) -> io::Result<Vec<u8>> {
+        let mut src = text;
+        let mut out = Vec::new();
+        reader(&mut src)?.read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    #[test]
+    fn test_armor_hex() {
+        assert_eq!(
+            armored(b"ABBA\xff", Armor::Hex),
+            "-----BEGIN COMPRESSING HEX-----\n\
+             41424241ff\n\
+             -----END COMPRESSING-----\n"
+        );
+    }
+
+    #[test]
+    fn test_armor_base64() {
+        // The padding cases of RFC 4648.
+        for (data, expected) in [
+            ("", ""),
+            ("f", "Zg==\n"),
+            ("fo", "Zm8=\n"),
+            ("foo", "Zm9v\n"),
+            ("foob", "Zm9vYg==\n"),
+            ("fooba", "Zm9vYmE=\n"),
+            ("foobar", "Zm9vYmFy\n"),
+        ] {
+            let text = armored(data.as_bytes(), Armor::Base64);
+            let body = text
+                .strip_prefix("-----BEGIN COMPRESSING BASE64-----\n")
+                .and_then(|text| text.strip_suffix("-----END COMPRESSING-----\n"));
+            assert_eq!(body, Some(expected));
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let data: Vec<u8> = (0..1_000u32).map(|i| (i * 7 % 256) as u8).collect();
+        for armor in [Armor::Hex, Armor::Base64] {
+            for len in [0, 1, 2, 3, 56, 57, 58, 1_000] {
+                let text = armored(&data[..len], armor);
+                assert!(text.lines().all(|line| line.len() <= 76));
+                assert_eq!(dearmored(text.as_bytes()).unwrap(), data[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_whitespace() {
+        let text = "-----BEGIN COMPRESSING BASE64-----\r\n  Zm9v\tYm\r\n\nFy \n-----END COMPRESSING-----\r\n";
+        assert_eq!(dearmored(text.as_bytes()).unwrap(), b"foobar");
+        let text = "-----BEGIN COMPRESSING HEX-----\n4 1\n4\n2\n-----END COMPRESSING-----";
+        assert_eq!(dearmored(text.as_bytes()).unwrap(), b"AB");
+    }
+
+    #[test]
+    fn test_passthrough() {
+        let data = b"\x1f\x9d\x90 not armored";
+        assert_eq!(dearmored(data).unwrap(), data);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let kind = |text: &str| dearmored(text.as_bytes()).unwrap_err().kind();
+        assert_eq!(
+            kind("-----BEGIN COMPRESSING ROT13-----\n"),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            kind("-----BEGIN COMPRESSING HEX-----\n4g\n-----END COMPRESSING-----\n"),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            kind("-----BEGIN COMPRESSING HEX-----\n414\n-----END COMPRESSING-----\n"),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            kind("-----BEGIN COMPRESSING BASE64-----\nZg=\n-----END COMPRESSING-----\n"),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            kind("-----BEGIN COMPRESSING BASE64-----\nZm9v\n"),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+}
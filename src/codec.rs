@@ -0,0 +1,214 @@
+use std::{fmt, io};
+
+use crate::Algorithm;
+
+/// A compression codec.
+pub trait Codec {
+    /// Encodes the given data.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the underlying I/O operations fail.
+    fn enc(&self, src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()>;
+
+    /// Decodes the given data.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the underlying I/O operations fail, or if the data is
+    /// invalid.
+    fn dec(&self, src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()>;
+
+    /// Returns a one-line description of the codec, e.g., for listings.
+    fn description(&self) -> &str {
+        ""
+    }
+}
+
+impl Codec for Algorithm {
+    fn enc(&self, src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+        Algorithm::enc(*self, src, out)
+    }
+
+    fn dec(&self, src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+        Algorithm::dec(*self, src, out)
+    }
+
+    fn description(&self) -> &str {
+        Algorithm::description(*self)
+    }
+}
+
+/// Constructs a new instance of a codec.
+pub type Factory = Box<dyn Fn() -> Box<dyn Codec> + Send + Sync>;
+
+/// A codec entry in a [`CodecRegistry`].
+pub struct Entry {
+    tag: u8,
+    name: String,
+    factory: Factory,
+}
+
+impl Entry {
+    /// Returns the tag which identifies the codec.
+    pub fn tag(&self) -> u8 {
+        self.tag
+    }
+
+    /// Returns the name of the codec.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Constructs a new instance of the codec.
+    pub fn codec(&self) -> Box<dyn Codec> {
+        (self.factory)()
+    }
+}
+
+impl fmt::Debug for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Entry")
+            .field("tag", &self.tag)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Maps algorithm tags (and names) to codec factories.
+#[derive(Debug, Default)]
+pub struct CodecRegistry {
+    entries: Vec<Entry>,
+}
+
+impl CodecRegistry {
+    /// Constructs a new, empty registry. See [`registry`] for one with the
+    /// built-in codecs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a new codec.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`io::ErrorKind::AlreadyExists`] if the tag or the name is
+    /// already registered.
+    pub fn register<F>(&mut self, tag: u8, name: &str, factory: F) -> io::Result<()>
+    where
+        F: Fn() -> Box<dyn Codec> + Send + Sync + 'static,
+    {
+        if let Some(entry) = self.entries.iter().find(|e| e.tag == tag || e.name == name) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!(
+                    "codec `{}` (tag {}) is already registered",
+                    entry.name, entry.tag
+                ),
+            ));
+        }
+        self.entries.push(Entry {
+            tag,
+            name: name.to_owned(),
+            factory: Box::new(factory),
+        });
+        Ok(())
+    }
+
+    /// Returns the entry with the given tag.
+    pub fn get(&self, tag: u8) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.tag == tag)
+    }
+
+    /// Returns the entry with the given name.
+    pub fn get_by_name(&self, name: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    /// Returns an iterator over the registered entries, in registration order.
+    pub fn iter(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter()
+    }
+}
+
+/// Returns a registry with the built-in codecs.
+pub fn registry() -> CodecRegistry {
+    let mut registry = CodecRegistry::new();
+    for algorithm in Algorithm::ALL {
+        registry
+            .register(algorithm.tag(), algorithm.name(), move || {
+                Box::new(algorithm)
+            })
+            .unwrap();
+    }
+    registry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Identity;
+
+    impl Codec for Identity {
+        fn enc(&self, src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+            io::copy(src, out).map(drop)
+        }
+
+        fn dec(&self, src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+            io::copy(src, out).map(drop)
+        }
+    }
+
+    #[test]
+    fn test_descriptions() {
+        let registry = registry();
+        let lzw = registry.get_by_name("lzw").unwrap().codec();
+        assert_eq!(lzw.description(), "Lempel-Ziv-Welch dictionary coder");
+        assert_eq!(Identity.description(), "");
+    }
+
+    #[test]
+    fn test_builtins() {
+        let registry = registry();
+        let names: Vec<_> = registry.iter().map(Entry::name).collect();
+        assert_eq!(names, ["lzw", "huffman", "stored"]);
+        assert_eq!(
+            registry.get(Algorithm::Huffman.tag()).unwrap().name(),
+            "huffman"
+        );
+        assert_eq!(
+            registry.get_by_name("lzw").unwrap().tag(),
+            Algorithm::Lzw.tag()
+        );
+    }
+
+    #[test]
+    fn test_custom_codec() {
+        let mut registry = registry();
+        registry
+            .register(0x7F, "identity", || Box::new(Identity))
+            .unwrap();
+
+        let codec = registry.get(0x7F).unwrap().codec();
+        let mut encoded = Vec::new();
+        codec.enc(&mut b"ABABABA".as_ref(), &mut encoded).unwrap();
+        let mut decoded = Vec::new();
+        codec.dec(&mut encoded.as_slice(), &mut decoded).unwrap();
+        assert_eq!(decoded, b"ABABABA");
+    }
+
+    #[test]
+    fn test_duplicate() {
+        let mut registry = registry();
+        let error = registry
+            .register(Algorithm::Lzw.tag(), "other", || Box::new(Identity))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+
+        let error = registry
+            .register(0x7F, "huffman", || Box::new(Identity))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    }
+}
@@ -0,0 +1,165 @@
+//! The CRC-32 checksum (as used by gzip, zlib's `crc32` and PNG).
+
+use std::io;
+
+use crate::CompressError;
+
+/// The reversed IEEE 802.3 polynomial.
+const POLY: u32 = 0xEDB8_8320;
+
+const TABLE: [u32; 256] = build_table();
+
+/// An incremental CRC-32 hasher.
+#[derive(Copy, Clone, Debug)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    /// Constructs a new hasher, over no data.
+    pub fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    /// Feeds the given data into the hasher.
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            let i = usize::from((self.state as u8) ^ byte);
+            self.state = TABLE[i] ^ (self.state >> 8);
+        }
+    }
+
+    /// Returns the checksum of the data fed so far.
+    pub fn sum(&self) -> u32 {
+        !self.state
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Computes the checksum of the given data.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// A reader which checksums the bytes of the underlying reader and, once it
+/// reaches their end, checks the checksum against the expected one (e.g.,
+/// from a trailer), such that any consumer reading to the end verifies them.
+pub struct Crc32Reader<'a> {
+    src: &'a mut dyn io::Read,
+    crc: Crc32,
+    expected: u32,
+}
+
+impl<'a> Crc32Reader<'a> {
+    /// Constructs a new [`Crc32Reader`] expecting the given checksum.
+    pub fn new(src: &'a mut dyn io::Read, expected: u32) -> Self {
+        Self {
+            src,
+            crc: Crc32::new(),
+            expected,
+        }
+    }
+
+    /// Returns the checksum of the bytes read so far.
+    pub fn sum(&self) -> u32 {
+        self.crc.sum()
+    }
+}
+
+impl io::Read for Crc32Reader<'_> {
+    /// Reads like the underlying reader, except that the read which reaches
+    /// the end fails with [`CompressError::ChecksumMismatch`] (as an
+    /// [`io::ErrorKind::InvalidData`] error) if the checksums differ.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.src.read(buf)?;
+        if n == 0 && !buf.is_empty() && self.crc.sum() != self.expected {
+            return Err(CompressError::ChecksumMismatch {
+                expected: self.expected,
+                actual: self.crc.sum(),
+            }
+            .into());
+        }
+        self.crc.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 == 1 {
+                POLY ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            k += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+    }
+
+    #[test]
+    fn test_reader() {
+        let mut src = b"123456789".as_ref();
+        let mut reader = Crc32Reader::new(&mut src, 0xCBF4_3926);
+        let mut out = Vec::new();
+        io::Read::read_to_end(&mut reader, &mut out).unwrap();
+        assert_eq!(out, b"123456789");
+        assert_eq!(reader.sum(), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_reader_mismatch_at_end() {
+        let mut src = b"123456780".as_ref();
+        let mut reader = Crc32Reader::new(&mut src, 0xCBF4_3926);
+        let mut buf = [0; 4];
+        // Every read but the last one (at the end) succeeds.
+        for expected in [4, 4, 1] {
+            assert_eq!(io::Read::read(&mut reader, &mut buf).unwrap(), expected);
+        }
+        let error = io::Read::read(&mut reader, &mut buf).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(matches!(
+            CompressError::from(error),
+            CompressError::ChecksumMismatch {
+                expected: 0xCBF4_3926,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_incremental() {
+        let mut crc = Crc32::new();
+        crc.update(b"12345");
+        crc.update(b"6789");
+        assert_eq!(crc.sum(), 0xCBF4_3926);
+    }
+}
@@ -0,0 +1,185 @@
+//! Delta coding, a pre-pass which replaces each byte by its difference from the
+//! previous one (wrapping around mod 256), with the first byte taken as is.
+//!
+//! Slowly changing data (e.g., sorted integers) becomes a few distinct small
+//! differences, which LZW and Huffman compress far better. The output has the
+//! same length as the input, and isn't tagged itself: it may be preceded by a
+//! [`MARKER`] (see [`strip_marker`]) for the decoder to tell it apart.
+
+use std::io::{self, Read};
+
+/// Marks the data which follows as delta-coded (before it's compressed), like
+/// an armor header line. Its leading byte starts no LZW or Huffman stream, and
+/// its line endings change under text mode transfers, as PNG's signature.
+///
+/// Arbitrary data (e.g., a stored stream) may start with it, though, so it may
+/// only precede streams which can't.
+pub const MARKER: &[u8] = b"\x89DELTA\r\n\x1A\n";
+
+/// Returns a reader over the data of `src` past its [`MARKER`], and whether
+/// it has one (otherwise, the data is passed through whole).
+///
+/// # Errors
+///
+/// Fails if reading from `src` fails.
+pub fn strip_marker<'a>(src: &'a mut dyn Read) -> io::Result<(Box<dyn Read + 'a>, bool)> {
+    let mut prefix = Vec::with_capacity(MARKER.len());
+    // `MARKER` is a few bytes long.
+    Read::take(&mut *src, MARKER.len() as u64).read_to_end(&mut prefix)?;
+    if prefix == MARKER {
+        Ok((Box::new(src), true))
+    } else {
+        Ok((Box::new(io::Cursor::new(prefix).chain(src)), false))
+    }
+}
+
+/// A reader which delta-encodes the bytes of the underlying reader.
+pub struct DeltaEncoder<'a> {
+    src: &'a mut dyn Read,
+    prev: u8,
+}
+
+impl<'a> DeltaEncoder<'a> {
+    /// Constructs a new [`DeltaEncoder`].
+    pub fn new(src: &'a mut dyn Read) -> Self {
+        Self { src, prev: 0 }
+    }
+}
+
+impl Read for DeltaEncoder<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.src.read(buf)?;
+        for byte in &mut buf[..n] {
+            let value = *byte;
+            *byte = value.wrapping_sub(self.prev);
+            self.prev = value;
+        }
+        Ok(n)
+    }
+}
+
+/// A writer which delta-decodes the bytes written to it into the underlying
+/// writer.
+pub struct DeltaDecoder<'a> {
+    out: &'a mut dyn io::Write,
+    prev: u8,
+    buf: Vec<u8>,
+}
+
+impl<'a> DeltaDecoder<'a> {
+    /// Constructs a new [`DeltaDecoder`].
+    pub fn new(out: &'a mut dyn io::Write) -> Self {
+        Self {
+            out,
+            prev: 0,
+            buf: Vec::new(),
+        }
+    }
+}
+
+impl io::Write for DeltaDecoder<'_> {
+    /// Writes the whole buffer (decoded) or fails, so that the decoder's state
+    /// stays in sync with the underlying writer.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.clear();
+        self.buf.extend(buf.iter().map(|&delta| {
+            self.prev = self.prev.wrapping_add(delta);
+            self.prev
+        }));
+        self.out.write_all(&self.buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Delta-encodes the given data.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`).
+pub fn encode(src: &mut dyn Read, out: &mut dyn io::Write) -> io::Result<()> {
+    io::copy(&mut DeltaEncoder::new(src), out)?;
+    Ok(())
+}
+
+/// Decodes data delta-encoded by [`encode`].
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`).
+pub fn decode(src: &mut dyn Read, out: &mut dyn io::Write) -> io::Result<()> {
+    io::copy(src, &mut DeltaDecoder::new(out))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lzw;
+
+    #[test]
+    fn test_encode() {
+        let mut out = Vec::new();
+        encode(&mut [5, 7, 7, 3, 255, 0].as_ref(), &mut out).unwrap();
+        assert_eq!(out, [5, 2, 0, 252, 252, 1]);
+
+        let mut decoded = Vec::new();
+        decode(&mut out.as_slice(), &mut decoded).unwrap();
+        assert_eq!(decoded, [5, 7, 7, 3, 255, 0]);
+    }
+
+    #[test]
+    fn test_strip_marker() {
+        let marked = [MARKER, b"ABC"].concat();
+        for (data, expected, has_marker) in [
+            (&marked[..], &b"ABC"[..], true),
+            (b"ABC", b"ABC", false),
+            (&MARKER[..4], &MARKER[..4], false),
+            (b"", b"", false),
+        ] {
+            let mut src = data;
+            let (mut reader, marker) = strip_marker(&mut src).unwrap();
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest).unwrap();
+            assert_eq!((rest.as_slice(), marker), (expected, has_marker));
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i * i % 251) as u8).collect();
+        let mut encoded = Vec::new();
+        encode(&mut data.as_slice(), &mut encoded).unwrap();
+        assert_eq!(encoded.len(), data.len());
+
+        let mut decoded = Vec::new();
+        decode(&mut encoded.as_slice(), &mut decoded).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_delta_beats_plain_lzw() {
+        // Increasing, such that the differences only take a few values.
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i * 3 / 2) as u8).collect();
+
+        let mut plain = Vec::new();
+        lzw::enc(&mut data.as_slice(), &mut plain).unwrap();
+        let mut delta = Vec::new();
+        lzw::enc(&mut DeltaEncoder::new(&mut data.as_slice()), &mut delta).unwrap();
+        assert!(
+            delta.len() * 4 < plain.len(),
+            "{} vs {}",
+            delta.len(),
+            plain.len()
+        );
+
+        let mut decoded = Vec::new();
+        lzw::dec(&mut delta.as_slice(), &mut DeltaDecoder::new(&mut decoded)).unwrap();
+        assert_eq!(decoded, data);
+    }
+}
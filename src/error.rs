@@ -0,0 +1,236 @@
+use std::{error, fmt, io};
+
+use crate::lzw::Code;
+
+/// An error produced by the codecs.
+///
+/// The codecs keep returning [`io::Result`], wrapping these errors in an
+/// [`io::Error`] (of the kind given by [`CompressError::kind`]). Converting
+/// such an [`io::Error`] back with [`From`] recovers the original error.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum CompressError {
+    /// An underlying I/O operation failed.
+    Io(io::Error),
+    /// The LZW dictionary grew past the bounds of [`Code`].
+    DictionaryOverflow,
+    /// The LZW decoder found a code which is neither in its dictionary nor the
+    /// next one to be assigned, after decoding `offset` bytes.
+    InvalidCode { code: Code, offset: u64 },
+    /// A header or table preceding the encoded data is malformed.
+    BadHeader(&'static str),
+    /// The checksum of the decoded data doesn't match the stored one.
+    ChecksumMismatch { expected: u32, actual: u32 },
+    /// The data was produced by an unsupported format version.
+    UnsupportedVersion(u8),
+    /// A codec didn't decode its own encoding back to the original data (see
+    /// [`selftest`](crate::selftest)).
+    SelfTestFailed { codec: String },
+}
+
+impl CompressError {
+    /// Returns the [`io::ErrorKind`] this error maps to.
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            CompressError::Io(error) => error.kind(),
+            CompressError::DictionaryOverflow => io::ErrorKind::Other,
+            CompressError::InvalidCode { .. }
+            | CompressError::BadHeader(_)
+            | CompressError::ChecksumMismatch { .. } => io::ErrorKind::InvalidData,
+            CompressError::UnsupportedVersion(_) => io::ErrorKind::Unsupported,
+            CompressError::SelfTestFailed { .. } => io::ErrorKind::Other,
+        }
+    }
+}
+
+impl fmt::Display for CompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompressError::Io(error) => error.fmt(f),
+            CompressError::DictionaryOverflow => f.write_str("LZW dictionary overflow"),
+            CompressError::InvalidCode { code, offset } => {
+                write!(f, "invalid LZW code {code} at output offset {offset}")
+            }
+            CompressError::BadHeader(msg) => write!(f, "bad header: {msg}"),
+            CompressError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch (expected {expected:#010x}, got {actual:#010x})"
+            ),
+            CompressError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {version}")
+            }
+            CompressError::SelfTestFailed { codec } => {
+                write!(f, "self-test failed: codec `{codec}` doesn't round-trip")
+            }
+        }
+    }
+}
+
+impl error::Error for CompressError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            CompressError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CompressError {
+    fn from(error: io::Error) -> Self {
+        if error
+            .get_ref()
+            .is_some_and(|inner| inner.is::<CompressError>())
+        {
+            // The checks above guarantee both `unwrap`s succeed.
+            *error.into_inner().unwrap().downcast().unwrap()
+        } else {
+            CompressError::Io(error)
+        }
+    }
+}
+
+impl From<CompressError> for io::Error {
+    fn from(error: CompressError) -> Self {
+        match error {
+            CompressError::Io(error) => error,
+            error => io::Error::new(error.kind(), error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gzip, huffman, lzw};
+
+    fn dec_error(
+        dec: fn(&mut dyn io::Read, &mut dyn io::Write) -> io::Result<()>,
+        src: &[u8],
+    ) -> CompressError {
+        dec(&mut &*src, &mut io::sink()).unwrap_err().into()
+    }
+
+    #[test]
+    fn test_dictionary_overflow() {
+        // Pseudo-random data adds a new dictionary entry every one or two bytes.
+        let mut x = 1u32;
+        let data: Vec<u8> = (0..200_000)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 17;
+                x ^= x << 5;
+                x as u8
+            })
+            .collect();
+
+        let error: CompressError = lzw::enc(&mut data.as_slice(), &mut io::sink())
+            .unwrap_err()
+            .into();
+        assert!(matches!(error, CompressError::DictionaryOverflow));
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        // The first code can't refer to a multi-byte sequence.
+        let error = dec_error(lzw::dec, &[1, 0]);
+        assert!(matches!(
+            error,
+            CompressError::InvalidCode {
+                code: 256,
+                offset: 0
+            }
+        ));
+
+        // Only the next code (here, 256) may be missing from the dictionary.
+        let error = dec_error(lzw::dec, &[0, 65, 1, 1]);
+        assert!(matches!(
+            error,
+            CompressError::InvalidCode {
+                code: 257,
+                offset: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn test_invalid_code_offset() {
+        let data = "olá, mundo! como vai? olá, mundo!".as_bytes();
+        let mut encoded = Vec::new();
+        lzw::enc(&mut &*data, &mut encoded).unwrap();
+
+        // Corrupts the `n`-th code, and checks the offset it reports is where
+        // its output would have started.
+        let codes: Vec<_> = lzw::codes(&mut &*data).collect::<io::Result<_>>().unwrap();
+        for n in [0, 5, codes.len() - 1] {
+            let mut corrupted = encoded.clone();
+            corrupted[2 * n..2 * n + 2].copy_from_slice(&0xFFFF_u16.to_be_bytes());
+
+            let mut out = Vec::new();
+            let error = lzw::dec(&mut corrupted.as_slice(), &mut out).unwrap_err();
+            let CompressError::InvalidCode { code, offset } = error.into() else {
+                panic!("expected an invalid code");
+            };
+            assert_eq!(code, 0xFFFF);
+            assert_eq!(offset, u64::try_from(out.len()).unwrap());
+
+            let mut prefix = Vec::new();
+            lzw::dec(&mut &encoded[..2 * n], &mut prefix).unwrap();
+            assert_eq!(out, prefix);
+        }
+    }
+
+    #[test]
+    fn test_bad_header() {
+        let error = dec_error(huffman::dec, &[1, 1]);
+        assert!(matches!(error, CompressError::BadHeader(_)));
+
+        let error = dec_error(
+            |src, out| gzip::read_member(src, out).map(drop),
+            &[0x1F, 0x8C, 8, 0, 0, 0, 0, 0, 0, 3],
+        );
+        assert!(matches!(error, CompressError::BadHeader(_)));
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let mut member = Vec::new();
+        gzip::write_member(&mut b"ABC".as_ref(), &mut member, 0).unwrap();
+        member[15] = b'X'; // The first data byte.
+
+        let error = dec_error(|src, out| gzip::read_member(src, out).map(drop), &member);
+        assert!(matches!(
+            error,
+            CompressError::ChecksumMismatch { expected, actual }
+                if expected == crate::crc32::crc32(b"ABC") && actual == crate::crc32::crc32(b"XBC")
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let error = io::Error::from(CompressError::UnsupportedVersion(9));
+        assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+        assert_eq!(error.to_string(), "unsupported format version 9");
+        assert!(matches!(
+            CompressError::from(error),
+            CompressError::UnsupportedVersion(9)
+        ));
+    }
+
+    #[test]
+    fn test_self_test_failed() {
+        let codec = String::from("lzw");
+        let error = CompressError::SelfTestFailed { codec };
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+        assert_eq!(
+            error.to_string(),
+            "self-test failed: codec `lzw` doesn't round-trip"
+        );
+    }
+
+    #[test]
+    fn test_io_passthrough() {
+        let error = CompressError::from(io::Error::from(io::ErrorKind::UnexpectedEof));
+        assert!(matches!(&error, CompressError::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof));
+        assert_eq!(io::Error::from(error).kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,122 @@
+//! Length-delimited framing of compressed messages, e.g., over a socket.
+//!
+//! Each frame is `[u32 length][u8 algorithm tag][compressed data]`, where the
+//! (big-endian) length counts the bytes after it.
+
+use std::io::{self, Read};
+
+use crate::{
+    compress, decompress,
+    shared::{len_u64, read_u8},
+    Algorithm, CompressError,
+};
+
+/// Compresses `data` with the given algorithm and writes it as a frame.
+///
+/// # Errors
+///
+/// Fails if the codec or writing to `out` fails, or with
+/// [`io::ErrorKind::InvalidInput`] if the compressed data doesn't fit in a
+/// frame.
+pub fn write_message(out: &mut dyn io::Write, data: &[u8], algorithm: Algorithm) -> io::Result<()> {
+    let payload = compress(algorithm, data)?;
+    let len = payload
+        .len()
+        .checked_add(1)
+        .and_then(|len| u32::try_from(len).ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "message too long"))?;
+    out.write_all(&len.to_be_bytes())?;
+    out.write_all(&[algorithm.tag()])?;
+    out.write_all(&payload)
+}
+
+/// Reads a frame and returns the decompressed message, or `None` if `src` is
+/// at its end (before any byte of the frame).
+///
+/// # Errors
+///
+/// Fails if reading from `src` or the codec fails, with
+/// [`io::ErrorKind::UnexpectedEof`] if the frame is truncated, or with
+/// [`CompressError::BadHeader`] if it's malformed.
+pub fn read_message(src: &mut dyn io::Read) -> io::Result<Option<Vec<u8>>> {
+    let Some(first) = read_u8(src)? else {
+        return Ok(None);
+    };
+    let mut len = [first, 0, 0, 0];
+    src.read_exact(&mut len[1..])?;
+    let len = u32::from_be_bytes(len);
+
+    let tag = read_u8(src)?.ok_or_else(|| {
+        if len == 0 {
+            io::Error::from(CompressError::BadHeader("empty frame"))
+        } else {
+            io::ErrorKind::UnexpectedEof.into()
+        }
+    })?;
+    let algorithm =
+        Algorithm::from_tag(tag).ok_or(CompressError::BadHeader("unknown algorithm tag"))?;
+
+    let mut payload = Vec::new();
+    let payload_len = u64::from(len).saturating_sub(1);
+    src.take(payload_len).read_to_end(&mut payload)?;
+    if len_u64(payload.len()) != payload_len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    decompress(algorithm, &payload).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    const MESSAGES: [&[u8]; 4] = [b"ABBABBBABBA", b"", b"A", "olá, mundo!".as_bytes()];
+
+    #[test]
+    fn test_pipe() {
+        let (mut reader, mut writer) = io::pipe().unwrap();
+        let sender = thread::spawn(move || {
+            for (i, message) in MESSAGES.into_iter().enumerate() {
+                let algorithm = Algorithm::ALL[i % Algorithm::ALL.len()];
+                write_message(&mut writer, message, algorithm).unwrap();
+            }
+        });
+
+        for message in MESSAGES {
+            assert_eq!(read_message(&mut reader).unwrap().unwrap(), message);
+        }
+        sender.join().unwrap();
+        // The writer was dropped with the thread, so the pipe is at its end.
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_layout() {
+        let mut out = Vec::new();
+        write_message(&mut out, b"AB", Algorithm::Lzw).unwrap();
+        assert_eq!(out, [0, 0, 0, 5, Algorithm::Lzw.tag(), 0, 65, 0, 66]);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let mut frame = Vec::new();
+        write_message(&mut frame, b"ABBABBBABBA", Algorithm::Huffman).unwrap();
+
+        for len in 1..frame.len() {
+            let error = read_message(&mut &frame[..len]).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof, "{len} bytes");
+        }
+    }
+
+    #[test]
+    fn test_bad_header() {
+        for frame in [&[0, 0, 0, 0][..], &[0, 0, 0, 1, 0xFF]] {
+            let error = read_message(&mut &frame[..]).unwrap_err();
+            assert!(matches!(
+                CompressError::from(error),
+                CompressError::BadHeader(_)
+            ));
+        }
+    }
+}
@@ -0,0 +1,321 @@
+//! Framing of gzip members ([RFC 1952]).
+//!
+//! Since there is no DEFLATE encoder yet, members are written with *stored*
+//! (i.e., uncompressed) DEFLATE blocks. This is enough for the output to be
+//! readable by `gunzip`, and the framing is ready for a real payload.
+//!
+//! [RFC 1952]: https://www.rfc-editor.org/rfc/rfc1952
+
+use std::io;
+
+use crate::{
+    crc32::Crc32,
+    shared::{read_retry, read_u8},
+    CompressError,
+};
+
+/// The gzip magic bytes.
+pub const MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// The DEFLATE compression method.
+const CM_DEFLATE: u8 = 8;
+
+/// The "unknown" operating system.
+const OS_UNKNOWN: u8 = 255;
+
+const FTEXT: u8 = 1 << 0;
+const FHCRC: u8 = 1 << 1;
+const FEXTRA: u8 = 1 << 2;
+const FNAME: u8 = 1 << 3;
+const FCOMMENT: u8 = 1 << 4;
+
+/// The maximum length of a stored DEFLATE block.
+const MAX_STORED_LEN: usize = u16::MAX as usize;
+
+/// The fields of a gzip member, as found in its header and trailer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Member {
+    /// The modification time, in seconds since the Unix epoch (`0` if not
+    /// available).
+    pub mtime: u32,
+    /// The operating system on which the member was produced.
+    pub os: u8,
+    /// The original file name, if present.
+    pub name: Option<Vec<u8>>,
+    /// The CRC-32 of the uncompressed data.
+    pub crc32: u32,
+    /// The size of the uncompressed data, modulo `2^32`.
+    pub isize: u32,
+}
+
+/// Writes a gzip member holding the data read from `src`.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`).
+pub fn write_member(src: &mut dyn io::Read, out: &mut dyn io::Write, mtime: u32) -> io::Result<()> {
+    let [m0, m1, m2, m3] = mtime.to_le_bytes();
+    out.write_all(&[
+        MAGIC[0], MAGIC[1], CM_DEFLATE, 0, // FLG
+        m0, m1, m2, m3, 0, // XFL
+        OS_UNKNOWN,
+    ])?;
+
+    let mut crc = Crc32::new();
+    let mut size = 0u32;
+    let mut buf = vec![0; MAX_STORED_LEN];
+    loop {
+        let n = read_full(src, &mut buf)?;
+        crc.update(&buf[..n]);
+        size = size.wrapping_add(n as u32);
+
+        // A short read means the end of the input was reached. A full block
+        // may be followed by an empty final one.
+        let last = n < MAX_STORED_LEN;
+        // At most `MAX_STORED_LEN`, the length `buf` was given.
+        let len = n as u16;
+        out.write_all(&[u8::from(last)])?; // BFINAL, BTYPE = 00.
+        out.write_all(&len.to_le_bytes())?;
+        out.write_all(&(!len).to_le_bytes())?;
+        out.write_all(&buf[..n])?;
+        if last {
+            break;
+        }
+    }
+
+    out.write_all(&crc.sum().to_le_bytes())?;
+    out.write_all(&size.to_le_bytes())
+}
+
+/// Reads a gzip member from `src`, writing its data to `out`.
+///
+/// Only stored DEFLATE blocks are supported.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail, with
+/// [`CompressError::BadHeader`] if the header is invalid, if the member uses
+/// compressed DEFLATE blocks, or if the trailer's CRC-32
+/// ([`CompressError::ChecksumMismatch`]) or size doesn't match the data.
+pub fn read_member(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<Member> {
+    let mut header = [0; 10];
+    src.read_exact(&mut header)?;
+    if header[..2] != MAGIC {
+        return Err(CompressError::BadHeader("not a gzip member").into());
+    }
+    if header[2] != CM_DEFLATE {
+        return Err(CompressError::BadHeader("unknown gzip compression method").into());
+    }
+    let flags = header[3];
+    if flags & !(FTEXT | FHCRC | FEXTRA | FNAME | FCOMMENT) != 0 {
+        return Err(CompressError::BadHeader("reserved gzip flags set").into());
+    }
+    let mtime = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let os = header[9];
+
+    if flags & FEXTRA != 0 {
+        let mut len = [0; 2];
+        src.read_exact(&mut len)?;
+        let len = u64::from(u16::from_le_bytes(len));
+        io::copy(&mut io::Read::take(&mut *src, len), &mut io::sink())?;
+    }
+    let name = if flags & FNAME != 0 {
+        Some(read_zero_terminated(src)?)
+    } else {
+        None
+    };
+    if flags & FCOMMENT != 0 {
+        read_zero_terminated(src)?;
+    }
+    if flags & FHCRC != 0 {
+        src.read_exact(&mut [0; 2])?;
+    }
+
+    let mut crc = Crc32::new();
+    let mut size = 0u32;
+    let mut buf = vec![0; MAX_STORED_LEN];
+    loop {
+        let block = read_u8(src)?.ok_or_else(|| eof("truncated DEFLATE stream"))?;
+        if (block >> 1) & 0b11 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only stored DEFLATE blocks are supported",
+            ));
+        }
+        let mut lens = [0; 4];
+        src.read_exact(&mut lens)?;
+        let len = u16::from_le_bytes([lens[0], lens[1]]);
+        let nlen = u16::from_le_bytes([lens[2], lens[3]]);
+        if len != !nlen {
+            return Err(invalid("corrupt stored block length"));
+        }
+
+        let data = &mut buf[..usize::from(len)];
+        src.read_exact(data)?;
+        crc.update(data);
+        size = size.wrapping_add(u32::from(len));
+        out.write_all(data)?;
+
+        if block & 1 == 1 {
+            break;
+        }
+    }
+
+    let mut trailer = [0; 8];
+    src.read_exact(&mut trailer)?;
+    let crc32 = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let isize = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if crc32 != crc.sum() {
+        return Err(CompressError::ChecksumMismatch {
+            expected: crc32,
+            actual: crc.sum(),
+        }
+        .into());
+    }
+    if isize != size {
+        return Err(invalid("gzip size mismatch"));
+    }
+
+    Ok(Member {
+        mtime,
+        os,
+        name,
+        crc32,
+        isize,
+    })
+}
+
+/// Reads into `buf` until it's full or the end of `src` is reached, returning
+/// the number of bytes read.
+fn read_full(src: &mut dyn io::Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match read_retry(src, &mut buf[n..])? {
+            0 => break,
+            k => n += k,
+        }
+    }
+    Ok(n)
+}
+
+fn read_zero_terminated(src: &mut dyn io::Read) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    loop {
+        match read_u8(src)? {
+            Some(0) => return Ok(bytes),
+            Some(byte) => bytes.push(byte),
+            None => return Err(eof("truncated gzip header")),
+        }
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn eof(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Command, Stdio};
+
+    use super::*;
+    use crate::crc32::crc32;
+
+    const DATA: &[u8] = "olá, mundo! como vai?".as_bytes();
+
+    #[test]
+    fn test_member_fields() {
+        let mut out = Vec::new();
+        write_member(&mut &*DATA, &mut out, 1_684_627_200).unwrap();
+
+        assert_eq!(out[..4], [0x1F, 0x8B, 8, 0]);
+        assert_eq!(out[4..8], 1_684_627_200u32.to_le_bytes());
+        assert_eq!(out[9], OS_UNKNOWN);
+
+        let trailer = &out[out.len() - 8..];
+        assert_eq!(trailer[..4], crc32(DATA).to_le_bytes());
+        assert_eq!(trailer[4..], 22u32.to_le_bytes());
+
+        let mut data = Vec::new();
+        let member = read_member(&mut out.as_slice(), &mut data).unwrap();
+        assert_eq!(data, DATA);
+        assert_eq!(
+            member,
+            Member {
+                mtime: 1_684_627_200,
+                os: OS_UNKNOWN,
+                name: None,
+                crc32: crc32(DATA),
+                isize: 22,
+            }
+        );
+    }
+
+    #[test]
+    fn test_multiple_blocks() {
+        for len in [
+            0,
+            MAX_STORED_LEN,
+            MAX_STORED_LEN + 1,
+            2 * MAX_STORED_LEN + 7,
+        ] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let mut out = Vec::new();
+            write_member(&mut data.as_slice(), &mut out, 0).unwrap();
+
+            let mut recovered = Vec::new();
+            read_member(&mut out.as_slice(), &mut recovered).unwrap();
+            assert!(recovered == data, "len {len}");
+        }
+    }
+
+    #[test]
+    fn test_crc_mismatch() {
+        let mut out = Vec::new();
+        write_member(&mut &*DATA, &mut out, 0).unwrap();
+        out[15] ^= 1; // Somewhere in the payload.
+
+        let error = read_member(&mut out.as_slice(), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_read_name() {
+        #[rustfmt::skip]
+        let member = [
+            0x1F, 0x8B, 8, FNAME, 0, 0, 0, 0, 0, 3,
+            b'a', b'.', b't', b'x', b't', 0,
+            1, 2, 0, !2, !0, b'h', b'i',
+            0xAC, 0x2A, 0x93, 0xD8, 2, 0, 0, 0,
+        ];
+        let mut data = Vec::new();
+        let member = read_member(&mut member.as_ref(), &mut data).unwrap();
+        assert_eq!(data, b"hi");
+        assert_eq!(member.name.as_deref(), Some(b"a.txt".as_ref()));
+    }
+
+    #[test]
+    fn test_gunzip_compatible() {
+        let Ok(mut child) = Command::new("gzip")
+            .arg("-dc")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+        else {
+            eprintln!("skipping: gzip not available");
+            return;
+        };
+
+        let mut member = Vec::new();
+        write_member(&mut &*DATA, &mut member, 0).unwrap();
+        io::Write::write_all(&mut child.stdin.take().unwrap(), &member).unwrap();
+
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, DATA);
+    }
+}
@@ -1,20 +1,28 @@
-#![allow(dead_code)] // XX: Remove this.
-
 use std::{
     cmp::{self, Reverse},
     collections::{BinaryHeap, HashMap},
-    io,
+    io, mem,
 };
 
 use bitvec::vec::BitVec;
 
-use crate::shared::read_u8;
+use crate::{
+    shared::{
+        len_u64, read_retry, read_u16, read_u8, read_varint, write_varint, BitReader, BitWriter,
+    },
+    stats::ByteHistogram,
+    CompressError,
+};
+
+mod table;
+
+pub use table::{HuffmanDecodeTable, MAX_TABLE_BITS};
 
-type Char = u8;
-type Freq = u32;
+pub type Char = u8;
+pub type Freq = u64;
 
-type FreqMap = HashMap<Char, Freq>;
-type CodeMap = HashMap<Char, BitVec>;
+pub type FreqMap = HashMap<Char, Freq>;
+pub type CodeMap = HashMap<Char, BitVec>;
 
 #[derive(Debug, PartialEq, Eq)]
 struct Stat {
@@ -36,11 +44,194 @@ type TreeArena = Vec<Tree>;
 
 /// Encodes the given data.
 ///
+/// The output starts with the frequency table: the number of symbols (as a
+/// `u16`), followed by each symbol and its frequency (as an LEB128 varint, so
+/// that small counts take a byte or two), in increasing symbol order. The
+/// codes follow, packed most significant bit first, with the last byte padded
+/// with zeroes.
+///
+/// Since two passes are needed over the data, `src` is buffered in memory. If
+/// the data is already in memory, prefer [`compress`], which avoids the copy,
+/// and if `src` is seekable (e.g., a file), prefer [`enc_seekable`].
+///
 /// # Errors
 ///
 /// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
-pub fn enc(_src: &mut dyn io::Read, _out: &mut dyn io::Write) -> io::Result<()> {
-    Ok(())
+/// or writing to `out`).
+pub fn enc(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let mut data = Vec::new();
+    src.read_to_end(&mut data)?;
+    compress(&data, out)
+}
+
+/// Encodes the given in-memory data, in the same format as [`enc`].
+///
+/// Both passes run over `data` itself, hence no copy of it is made.
+///
+/// # Errors
+///
+/// Fails if writing to `out` fails.
+pub fn compress(data: &[u8], out: &mut dyn io::Write) -> io::Result<()> {
+    let freq_map = freq_map_from_reader(&mut &*data)?;
+    write_header(&freq_map, out)?;
+
+    let freq_map_len = freq_map.len();
+    let tree_arena = tree_from_freq_map(freq_map);
+    let codes = PackedCodes::new(code_map_from_tree(freq_map_len, &tree_arena));
+
+    let mut writer = BitWriter::new(out);
+    // Every byte has a code, as the frequencies are the data's own.
+    codes.write(data, &mut writer)?;
+    writer.finish()
+}
+
+/// Encodes the data of a seekable source (from its current position), in the
+/// same format as [`enc`].
+///
+/// Rather than buffering the data for the second pass, it rewinds `src` and
+/// reads it again, such that memory use doesn't grow with the input.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from or
+/// seeking `src`, or writing to `out`), or with [`io::ErrorKind::InvalidData`]
+/// if the data changed between both passes.
+pub fn enc_seekable<R: io::Read + io::Seek>(
+    src: &mut R,
+    out: &mut dyn io::Write,
+) -> io::Result<()> {
+    let start = src.stream_position()?;
+    let freq_map = freq_map_from_reader(src)?;
+    src.seek(io::SeekFrom::Start(start))?;
+    write_header(&freq_map, out)?;
+
+    let mut remaining: u64 = freq_map.values().sum();
+    let freq_map_len = freq_map.len();
+    let tree_arena = tree_from_freq_map(freq_map);
+    let codes = PackedCodes::new(code_map_from_tree(freq_map_len, &tree_arena));
+
+    let changed = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the data changed while encoding",
+        )
+    };
+    let mut writer = BitWriter::new(out);
+    let mut buf = [0; 8 * 1024];
+    while remaining > 0 {
+        let len = usize::try_from(remaining).map_or(buf.len(), |r| r.min(buf.len()));
+        let n = read_retry(src, &mut buf[..len])?;
+        if n == 0 {
+            return Err(changed());
+        }
+        if !codes.write(&buf[..n], &mut writer)? {
+            return Err(changed());
+        }
+        remaining -= len_u64(n);
+    }
+    writer.finish()
+}
+
+/// An encoder with a fixed codebook (i.e., the codes of a frequency map, e.g.,
+/// of a sample of the inputs), which encodes any number of inputs without
+/// building the tree again.
+///
+/// Unlike [`enc`], it only writes the codes (packed most significant bit
+/// first, with the last byte padded with zeroes), since the decoder is
+/// expected to know the codebook already.
+#[derive(Clone, Debug)]
+pub struct Encoder {
+    code_map: CodeMap,
+    codes: PackedCodes,
+}
+
+impl Encoder {
+    /// Constructs an encoder with the codes of the given frequency map, which
+    /// must cover every byte of the inputs.
+    pub fn new(freq_map: &FreqMap) -> Self {
+        let tree_arena = tree_from_freq_map(freq_map.clone());
+        let code_map = code_map_from_tree(freq_map.len(), &tree_arena);
+        Self {
+            codes: PackedCodes::new(code_map.clone()),
+            code_map,
+        }
+    }
+
+    /// Returns the codes of the encoder.
+    pub fn code_map(&self) -> &CodeMap {
+        &self.code_map
+    }
+
+    /// Encodes the given input.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing to `out` fails, or with [`io::ErrorKind::InvalidInput`]
+    /// if the input has a byte which the codebook has no code for.
+    pub fn encode(&self, input: &[u8], out: &mut dyn io::Write) -> io::Result<()> {
+        let mut writer = BitWriter::new(out);
+        if !self.codes.write(input, &mut writer)? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "byte missing from the codebook",
+            ));
+        }
+        writer.finish()
+    }
+}
+
+/// The codes of a [`CodeMap`], arranged for encoding: indexed by byte, and
+/// packed into the low bits of a `u32` when they fit (as they do unless the
+/// frequencies are as skewed as Fibonacci numbers, over millions of bytes),
+/// such that a byte takes a lookup and a single write rather than hashing it
+/// and writing its code bit by bit.
+#[derive(Clone, Debug)]
+enum PackedCodes {
+    /// The code and its length of each byte, or `None` if it has no code.
+    Packed(Box<[Option<(u32, u8)>; 256]>),
+    /// Some code is longer than 32 bits.
+    Long(CodeMap),
+}
+
+impl PackedCodes {
+    fn new(code_map: CodeMap) -> Self {
+        let mut packed = Box::new([None; 256]);
+        for (&char, code) in &code_map {
+            if code.len() > 32 {
+                return PackedCodes::Long(code_map);
+            }
+            let bits = code.iter().fold(0, |bits, bit| bits << 1 | u32::from(*bit));
+            // At most 32, as checked above.
+            packed[usize::from(char)] = Some((bits, code.len() as u8));
+        }
+        PackedCodes::Packed(packed)
+    }
+
+    /// Writes the codes of `data`, returning whether every byte has one (or
+    /// else stopping at the first one which hasn't).
+    fn write(&self, data: &[u8], writer: &mut BitWriter) -> io::Result<bool> {
+        match self {
+            PackedCodes::Packed(packed) => {
+                for &char in data {
+                    let Some((bits, len)) = packed[usize::from(char)] else {
+                        return Ok(false);
+                    };
+                    writer.write_bits(bits, u32::from(len))?;
+                }
+            }
+            PackedCodes::Long(code_map) => {
+                for char in data {
+                    let Some(code) = code_map.get(char) else {
+                        return Ok(false);
+                    };
+                    for bit in code {
+                        writer.write_bit(*bit)?;
+                    }
+                }
+            }
+        }
+        Ok(true)
+    }
 }
 
 /// Decodes the given data.
@@ -48,11 +239,70 @@ pub fn enc(_src: &mut dyn io::Read, _out: &mut dyn io::Write) -> io::Result<()>
 /// # Errors
 ///
 /// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
-/// or writing to `out`).
-pub fn dec(_src: &mut dyn io::Read, _out: &mut dyn io::Write) -> io::Result<()> {
+/// or writing to `out`), if the data is truncated, or with
+/// [`CompressError::BadHeader`] if the frequency table is malformed.
+pub fn dec(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let freq_map = read_header(src)?;
+    let total: u64 = freq_map.values().sum();
+    let tree_arena = tree_from_freq_map(freq_map);
+
+    let mut reader = BitReader::new(src);
+    for _ in 0..total {
+        let mut i = 0;
+        let char = loop {
+            match &tree_arena[i] {
+                Tree::Node { left, right, .. } => {
+                    let bit = reader.read_bit()?.ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::UnexpectedEof, "truncated Huffman data")
+                    })?;
+                    i = if bit { *right } else { *left };
+                }
+                Tree::Leaf(Stat { char, .. }) => break *char,
+            }
+        };
+        out.write_all(&[char])?;
+    }
+
+    Ok(())
+}
+
+pub(crate) fn write_header(freq_map: &FreqMap, out: &mut dyn io::Write) -> io::Result<()> {
+    let mut stats: Vec<_> = freq_map.iter().collect();
+    stats.sort_unstable_by_key(|&(char, _)| char);
+
+    // At most 256, the number of distinct bytes.
+    let len = stats.len() as u16;
+    out.write_all(&len.to_be_bytes())?;
+    for (char, freq) in stats {
+        out.write_all(&[*char])?;
+        write_varint(out, *freq)?;
+    }
     Ok(())
 }
 
+pub(crate) fn read_header(src: &mut dyn io::Read) -> io::Result<FreqMap> {
+    let truncated = || io::Error::new(io::ErrorKind::UnexpectedEof, "truncated Huffman header");
+
+    let len = read_u16(src)?.ok_or_else(truncated)?;
+    if len > 256 {
+        return Err(CompressError::BadHeader("too many Huffman symbols").into());
+    }
+
+    let mut map = HashMap::with_capacity(usize::from(len));
+    for _ in 0..len {
+        let char = read_u8(src)?.ok_or_else(truncated)?;
+        let freq = read_varint(src)?.ok_or_else(truncated)?;
+        map.insert(char, freq);
+    }
+    // Bounding the total keeps the sums of frequencies (e.g., in the tree) from
+    // overflowing.
+    map.values()
+        .try_fold(0, |total: Freq, &freq| total.checked_add(freq))
+        .ok_or(CompressError::BadHeader("too large Huffman frequencies"))?;
+    Ok(map)
+}
+
+#[cfg(test)]
 fn code_map_from_reader(reader: &mut dyn io::Read) -> io::Result<CodeMap> {
     let freq_map = freq_map_from_reader(reader)?;
     let freq_map_len = freq_map.len();
@@ -60,22 +310,90 @@ fn code_map_from_reader(reader: &mut dyn io::Read) -> io::Result<CodeMap> {
     Ok(code_map_from_tree(freq_map_len, &tree_arena))
 }
 
-fn freq_map_from_reader(reader: &mut dyn io::Read) -> io::Result<FreqMap> {
-    let mut map = HashMap::new();
-    while let Some(char) = read_u8(reader)? {
-        *map.entry(char).or_insert(0) += 1;
-    }
-    Ok(map)
+/// Counts the occurrences of each byte in the given data.
+///
+/// # Errors
+///
+/// Fails if reading from `reader` fails.
+pub fn freq_map_from_reader(reader: &mut dyn io::Read) -> io::Result<FreqMap> {
+    let histogram = ByteHistogram::from_reader(reader)?;
+    Ok(histogram.iter().collect())
 }
 
+/// Builds the Huffman tree for the given frequency map.
+///
+/// Symbols with a zero frequency are skipped, hence they are not assigned any
+/// code. If no symbol has a non-zero frequency, the returned arena is empty.
 fn tree_from_freq_map(map: FreqMap) -> TreeArena {
-    let mut queue = BinaryHeap::with_capacity(map.len());
-    for (char, freq) in map {
+    let mut stats: Vec<_> = map.into_iter().filter(|&(_, freq)| freq != 0).collect();
+    stats.sort_unstable_by_key(|&(char, _)| char);
+
+    // Ties between equal-frequency trees are broken by the order in which they
+    // entered the queue (leaves first, in symbol order; then nodes, in creation
+    // order), which makes the resulting tree fully deterministic. This matters
+    // since the decoder must rebuild the exact same tree as the encoder.
+    let mut order = 0..;
+    let mut queue = BinaryHeap::with_capacity(stats.len());
+    for (char, freq) in stats {
         let leaf = Tree::Leaf(Stat { char, freq });
         // One needs a minimum heap.
-        queue.push(Reverse(leaf));
+        queue.push(Reverse((leaf, order.next().unwrap())));
+    }
+    if queue.is_empty() {
+        return Vec::new();
     }
 
+    let next_order = order.next().unwrap();
+    #[cfg(not(feature = "fast-arena"))]
+    let arena = arena_from_queue(queue, next_order);
+    // SAFETY: The queue isn't empty, as checked above.
+    #[cfg(feature = "fast-arena")]
+    let arena = unsafe { arena_from_queue_unchecked(queue, next_order) };
+
+    debug_assert_eq!(validate(&arena), Ok(()));
+    arena
+}
+
+/// The queue of [`tree_from_freq_map`]: a minimum heap of trees, ordered by
+/// frequency and then by order of entry.
+type TreeQueue = BinaryHeap<Reverse<(Tree, usize)>>;
+
+/// Merges the two least frequent trees of the queue until a single one is
+/// left, returning its arena (with the root at `0`). The merged trees take the
+/// orders from `next_order` on.
+#[cfg(any(not(feature = "fast-arena"), test))]
+fn arena_from_queue(mut queue: TreeQueue, next_order: usize) -> TreeArena {
+    let mut order = next_order..;
+    // A binary tree with `L` leaf nodes may have at most `2L - 1` nodes.
+    let mut arena = Vec::with_capacity((queue.len() * 2).saturating_sub(1));
+
+    // The root is the last tree to come out of the queue, but goes first in
+    // the arena: a placeholder holds its place until then.
+    arena.push(Tree::Leaf(Stat { freq: 0, char: 0 }));
+    while let Some(Reverse((fst, _))) = queue.pop() {
+        let Some(Reverse((snd, _))) = queue.pop() else {
+            arena[0] = fst;
+            break;
+        };
+
+        let freq = fst.freq() + snd.freq();
+        let left = ins(&mut arena, fst);
+        let right = ins(&mut arena, snd);
+
+        let node = Tree::Node { freq, left, right };
+        queue.push(Reverse((node, order.next().unwrap())));
+    }
+    arena
+}
+
+/// Does what [`arena_from_queue`] does, skipping its checks.
+///
+/// # Safety
+///
+/// The queue must not be empty.
+#[cfg(any(feature = "fast-arena", test))]
+unsafe fn arena_from_queue_unchecked(mut queue: TreeQueue, next_order: usize) -> TreeArena {
+    let mut order = next_order..;
     // A binary tree with `L` leaf nodes may have at most `2L - 1` nodes.
     let node_count = queue.len() * 2 - 1;
     let mut arena = Vec::with_capacity(node_count);
@@ -85,54 +403,230 @@ fn tree_from_freq_map(map: FreqMap) -> TreeArena {
     // its position here.
     //
     // The following is safe since the code below doesn't index `arena[0]`.
-    unsafe { arena.set_len(1) };
+    #[allow(clippy::uninit_vec)]
+    unsafe {
+        arena.set_len(1)
+    };
 
     while queue.len() >= 2 {
         // SAFETY: See `while` predicate.
-        let fst = unsafe { queue.pop().unwrap_unchecked() }.0;
-        let snd = unsafe { queue.pop().unwrap_unchecked() }.0;
+        let Reverse((fst, _)) = unsafe { queue.pop().unwrap_unchecked() };
+        let Reverse((snd, _)) = unsafe { queue.pop().unwrap_unchecked() };
 
         let freq = fst.freq() + snd.freq();
         let left = ins(&mut arena, fst);
         let right = ins(&mut arena, snd);
 
         let node = Tree::Node { freq, left, right };
-        queue.push(Reverse(node));
+        queue.push(Reverse((node, order.next().unwrap())));
     }
 
     // At the end of each `while` iteration, one always inserts a new node,
-    // hence the following is safe.
-    let root = unsafe { queue.pop().unwrap_unchecked() }.0;
+    // and the queue starts non-empty, hence the following is safe.
+    let Reverse((root, _)) = unsafe { queue.pop().unwrap_unchecked() };
 
-    // `0` is is bounds.
-    *unsafe { arena.get_unchecked_mut(0) } = root;
+    // `0` is is bounds. The slot is uninitialized, so it mustn't be dropped.
+    unsafe { arena.as_mut_ptr().write(root) };
 
     arena
 }
 
+/// Checks that `arena` holds a well-formed tree, as [`tree_from_freq_map`]
+/// builds it: every index is in bounds and reached exactly once from the root
+/// (at `0`), each node's frequency is the sum of its children's, there is one
+/// more leaf than nodes, and no symbol is repeated. An empty arena is valid.
+fn validate(arena: &TreeArena) -> Result<(), String> {
+    if arena.is_empty() {
+        return Ok(());
+    }
+    let mut reached = vec![false; arena.len()];
+    reached[0] = true;
+    let mut symbols = [false; 256];
+    let (mut nodes, mut leaves) = (0, 0);
+    let mut stack = vec![0];
+    while let Some(i) = stack.pop() {
+        match &arena[i] {
+            Tree::Node { freq, left, right } => {
+                nodes += 1;
+                for &child in [left, right] {
+                    let Some(seen) = reached.get_mut(child) else {
+                        return Err(format!("node {i} has out-of-bounds child {child}"));
+                    };
+                    if mem::replace(seen, true) {
+                        return Err(format!("index {child} is reached more than once"));
+                    }
+                    stack.push(child);
+                }
+                let sum = arena[*left].freq().checked_add(arena[*right].freq());
+                if sum != Some(*freq) {
+                    return Err(format!(
+                        "node {i} has frequency {freq}, not its children's sum"
+                    ));
+                }
+            }
+            Tree::Leaf(Stat { char, .. }) => {
+                leaves += 1;
+                if mem::replace(&mut symbols[usize::from(*char)], true) {
+                    return Err(format!("symbol {char} has more than one leaf"));
+                }
+            }
+        }
+    }
+    if let Some(i) = reached.iter().position(|&reached| !reached) {
+        return Err(format!("index {i} is unreachable from the root"));
+    }
+    if leaves != nodes + 1 {
+        return Err(format!("{leaves} leaves for {nodes} nodes"));
+    }
+    Ok(())
+}
+
+/// Assigns each leaf of the tree the path to it: `0` for a left branch and `1`
+/// for a right one.
+///
+/// The tree is walked with an explicit stack rather than recursively, so that
+/// deep trees (which skewed frequencies make) can't overflow the call stack.
 fn code_map_from_tree(size_hint: usize, arena: &TreeArena) -> CodeMap {
-    fn go(i: usize, arena: &TreeArena, map: &mut CodeMap, vec: BitVec) {
+    let mut map = HashMap::with_capacity(size_hint);
+    if arena.is_empty() {
+        return map;
+    }
+    let mut stack = vec![(/* root */ 0, BitVec::new())];
+    while let Some((i, vec)) = stack.pop() {
         match &arena[i] {
             Tree::Node { left, right, .. } => {
-                let mut left_vec = vec.clone();
-                left_vec.push(false);
-                go(*left, arena, map, left_vec);
-
-                let mut right_vec = vec;
+                let mut right_vec = vec.clone();
                 right_vec.push(true);
-                go(*right, arena, map, right_vec);
+                stack.push((*right, right_vec));
+
+                let mut left_vec = vec;
+                left_vec.push(false);
+                stack.push((*left, left_vec));
             }
             Tree::Leaf(Stat { char, .. }) => {
                 map.insert(*char, vec);
             }
         }
     }
-
-    let mut map = HashMap::with_capacity(size_hint);
-    go(/* root */ 0, arena, &mut map, BitVec::new());
     map
 }
 
+/// Builds prefix codes for the given frequency map such that no code is longer
+/// than `max_len` bits (e.g., 15, as DEFLATE does), for decoders which look
+/// codes up in fixed-size tables.
+///
+/// The code lengths come from the package-merge algorithm, which makes them
+/// optimal under the limit (hence the same total length as the tree's codes
+/// when the limit doesn't bind). The codes themselves are canonical: ordered
+/// by length, then by symbol, each one being the previous one plus one (and
+/// shifted left when the length grows). As in [`enc`], symbols with a zero
+/// frequency get no code, and a lone symbol gets an empty one.
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::InvalidInput`] if there are more symbols than
+/// `max_len` bits can tell apart (i.e., more than `2^max_len`).
+pub fn code_map_length_limited(freq_map: &FreqMap, max_len: u32) -> io::Result<CodeMap> {
+    let mut stats: Vec<_> = freq_map
+        .iter()
+        .filter(|&(_, &freq)| freq != 0)
+        .map(|(&char, &freq)| Stat { freq, char })
+        .collect();
+    stats.sort_unstable();
+    let n = stats.len();
+    if n > 1 && (n - 1).ilog2() >= max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too many symbols for the code length limit",
+        ));
+    }
+
+    let lens = package_merge(&stats, max_len);
+    let mut by_len: Vec<_> = stats.iter().map(|stat| stat.char).zip(lens).collect();
+    by_len.sort_unstable_by_key(|&(char, len)| (len, char));
+
+    let mut map = HashMap::with_capacity(n);
+    let mut code = BitVec::new();
+    for (i, (char, len)) in by_len.into_iter().enumerate() {
+        if i > 0 {
+            increment(&mut code);
+        }
+        code.resize(len, false);
+        map.insert(char, code.clone());
+    }
+    Ok(map)
+}
+
+/// Returns the optimal code length of each of the given stats (sorted by
+/// frequency), none of them exceeding `max_len`.
+///
+/// Each symbol is a coin of width `2^-len` for every `len` in `1..=max_len`,
+/// and the cheapest set of coins whose widths add up to `n - 1` yields the
+/// lengths: a symbol's length is the number of its coins in the set. Starting
+/// from the narrowest coins, adjacent pairs of each list are packaged into a
+/// coin twice as wide, merged with the symbols' own coins of that width.
+fn package_merge(stats: &[Stat], max_len: u32) -> Vec<usize> {
+    enum Coin {
+        Leaf(usize),
+        Package(usize, usize),
+    }
+
+    let n = stats.len();
+    if n <= 1 {
+        return vec![0; n];
+    }
+    // No code is longer than `n - 1` bits, whatever the limit.
+    let levels = usize::try_from(max_len).map_or(n - 1, |max_len| max_len.min(n - 1));
+
+    let mut arena: Vec<(u64, Coin)> = (0..n).map(|i| (stats[i].freq, Coin::Leaf(i))).collect();
+    let leaves: Vec<usize> = (0..n).collect();
+    let mut list = leaves.clone();
+    for _ in 1..levels {
+        let mut packages = Vec::with_capacity(list.len() / 2);
+        for pair in list.chunks_exact(2) {
+            packages.push(arena.len());
+            let weight = arena[pair[0]].0 + arena[pair[1]].0;
+            arena.push((weight, Coin::Package(pair[0], pair[1])));
+        }
+        // Merges both lists (sorted by weight), leaves first on ties.
+        let mut merged = Vec::with_capacity(n + packages.len());
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < packages.len() {
+            if j == packages.len() || (i < n && arena[leaves[i]].0 <= arena[packages[j]].0) {
+                merged.push(leaves[i]);
+                i += 1;
+            } else {
+                merged.push(packages[j]);
+                j += 1;
+            }
+        }
+        list = merged;
+    }
+
+    let mut lens = vec![0; n];
+    let mut stack: Vec<usize> = list[..2 * n - 2].to_vec();
+    while let Some(i) = stack.pop() {
+        match arena[i].1 {
+            Coin::Leaf(leaf) => lens[leaf] += 1,
+            Coin::Package(a, b) => stack.extend([a, b]),
+        }
+    }
+    lens
+}
+
+/// Adds one to the given code, read as a big-endian binary number (which must
+/// not be all ones).
+fn increment(code: &mut BitVec) {
+    for mut bit in code.iter_mut().rev() {
+        if !*bit {
+            *bit = true;
+            return;
+        }
+        *bit = false;
+    }
+    unreachable!("code overflow");
+}
+
 /// Pushes the given element into the vector and returns the inserted-to index.
 fn ins<T>(vec: &mut Vec<T>, el: T) -> usize {
     let index = vec.len();
@@ -152,19 +646,19 @@ impl Tree {
 
 impl PartialOrd for Stat {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        self.freq.partial_cmp(&other.freq)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for Stat {
     fn cmp(&self, other: &Self) -> cmp::Ordering {
-        self.freq.cmp(&other.freq)
+        (self.freq, self.char).cmp(&(other.freq, other.char))
     }
 }
 
 impl PartialOrd for Tree {
     fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
-        self.freq().partial_cmp(&other.freq())
+        Some(self.cmp(other))
     }
 }
 
@@ -186,8 +680,10 @@ mod tests {
         let a = Stat { freq: 5, char: b'A' };
         let b = Stat { freq: 5, char: b'A' };
         let c = Stat { freq: 6, char: b'A' };
+        let d = Stat { freq: 5, char: b'B' };
         assert_eq!(a, b);
         assert!(a < c);
+        assert!(a < d);
     }
 
     #[test]
@@ -207,16 +703,487 @@ mod tests {
 
         assert_eq!(map[&b'A'], bitvec![usize, Lsb0; 0]);
         assert_eq!(map[&b'B'], bitvec![usize, Lsb0; 1, 1]);
+        assert_eq!(map[&b'C'], bitvec![usize, Lsb0; 1, 0, 0]);
+        assert_eq!(map[&b'D'], bitvec![usize, Lsb0; 1, 0, 1]);
+    }
 
-        // The order is not specified, just the bit length.
-        assert_ne!(map[&b'C'], map[&b'D']);
-        assert!(
-            map[&b'C'] == bitvec![usize, Lsb0; 1, 0, 0]
-                || map[&b'C'] == bitvec![usize, Lsb0; 1, 0, 1]
-        );
-        assert!(
-            map[&b'D'] == bitvec![usize, Lsb0; 1, 0, 0]
-                || map[&b'D'] == bitvec![usize, Lsb0; 1, 0, 1]
-        );
+    #[test]
+    fn test_code_map_deterministic() {
+        // All ties: the codes only depend on the symbol order.
+        let freq_map = HashMap::from([(b'D', 1), (b'C', 1), (b'B', 1), (b'A', 1)]);
+        for _ in 0..10 {
+            let tree_arena = tree_from_freq_map(freq_map.clone());
+            let map = code_map_from_tree(4, &tree_arena);
+            assert_eq!(map[&b'A'], bitvec![usize, Lsb0; 0, 0]);
+            assert_eq!(map[&b'B'], bitvec![usize, Lsb0; 0, 1]);
+            assert_eq!(map[&b'C'], bitvec![usize, Lsb0; 1, 0]);
+            assert_eq!(map[&b'D'], bitvec![usize, Lsb0; 1, 1]);
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let data = b"AAABBBAABACD";
+        let mut encoded = Vec::new();
+        enc(&mut data.as_ref(), &mut encoded).unwrap();
+
+        #[rustfmt::skip]
+        let header = [
+            0, 4,
+            b'A', 6,
+            b'B', 4,
+            b'C', 1,
+            b'D', 1,
+        ];
+        assert_eq!(encoded[..header.len()], header);
+        // 6 * 1 + 4 * 2 + 2 * 3 = 20 bits.
+        assert_eq!(encoded.len(), header.len() + 3);
+
+        let mut decoded = Vec::new();
+        dec(&mut encoded.as_slice(), &mut decoded).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_compress_matches_enc() {
+        let data = "olá, mundo! como vai? tudo bem?".as_bytes();
+        let mut from_reader = Vec::new();
+        enc(&mut io::BufReader::new(data), &mut from_reader).unwrap();
+        let mut from_slice = Vec::new();
+        compress(data, &mut from_slice).unwrap();
+        assert_eq!(from_reader, from_slice);
+    }
+
+    #[test]
+    fn test_enc_seekable_matches_enc() {
+        let data: Vec<u8> = (0..100_000u32)
+            .map(|i| (i % 7 * (i % 5) + i % 3) as u8)
+            .collect();
+        let mut buffered = Vec::new();
+        enc(&mut data.as_slice(), &mut buffered).unwrap();
+
+        let mut seekable = Vec::new();
+        enc_seekable(&mut io::Cursor::new(&data), &mut seekable).unwrap();
+        assert_eq!(seekable, buffered);
+
+        // Only the data after the current position is encoded.
+        let mut cursor = io::Cursor::new(&data);
+        cursor.set_position(40_000);
+        let mut tail = Vec::new();
+        enc_seekable(&mut cursor, &mut tail).unwrap();
+        let mut expected = Vec::new();
+        compress(&data[40_000..], &mut expected).unwrap();
+        assert_eq!(tail, expected);
+    }
+
+    #[test]
+    fn test_enc_seekable_file() {
+        let path = std::env::temp_dir().join(format!("compressing-{}-huffman", std::process::id()));
+        let data = "olá, mundo! como vai? tudo bem? ".repeat(1000);
+        std::fs::write(&path, &data).unwrap();
+
+        let mut encoded = Vec::new();
+        enc_seekable(&mut std::fs::File::open(&path).unwrap(), &mut encoded).unwrap();
+        let mut decoded = Vec::new();
+        dec(&mut encoded.as_slice(), &mut decoded).unwrap();
+        assert_eq!(decoded, data.as_bytes());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_roundtrip_empty() {
+        let mut encoded = Vec::new();
+        enc(&mut b"".as_ref(), &mut encoded).unwrap();
+        assert_eq!(encoded, [0, 0]);
+
+        let mut decoded = Vec::new();
+        dec(&mut encoded.as_slice(), &mut decoded).unwrap();
+        assert!(decoded.is_empty());
+    }
+
+    #[test]
+    fn test_roundtrip_single_symbol() {
+        // The only symbol gets an empty code, hence no payload at all.
+        let mut encoded = Vec::new();
+        enc(&mut b"ZZZZ".as_ref(), &mut encoded).unwrap();
+        assert_eq!(encoded, [0, 1, b'Z', 4]);
+
+        let mut decoded = Vec::new();
+        dec(&mut encoded.as_slice(), &mut decoded).unwrap();
+        assert_eq!(decoded, b"ZZZZ");
+    }
+
+    #[test]
+    fn test_freq_past_u32() {
+        // Past 4 GiB of a single byte, which a `u32` count can't hold.
+        let big = u64::from(u32::MAX) + 1;
+        let freq_map = HashMap::from([(b'A', big), (b'B', 3), (b'C', 1)]);
+        let mut header = Vec::new();
+        write_header(&freq_map, &mut header).unwrap();
+        assert_eq!(header[2..8], [b'A', 0x80, 0x80, 0x80, 0x80, 0x10]);
+        assert_eq!(read_header(&mut header.as_slice()).unwrap(), freq_map);
+
+        let tree_arena = tree_from_freq_map(freq_map);
+        assert_eq!(tree_arena[0].freq(), big + 4);
+        let map = code_map_from_tree(3, &tree_arena);
+        assert_eq!(map[&b'A'], bitvec![usize, Lsb0; 1]);
+        assert_eq!(map[&b'B'], bitvec![usize, Lsb0; 0, 1]);
+    }
+
+    #[test]
+    fn test_read_header_total_overflow() {
+        let freq_map = HashMap::from([(b'A', u64::MAX), (b'B', 1)]);
+        let mut header = Vec::new();
+        write_header(&freq_map, &mut header).unwrap();
+        let error = read_header(&mut header.as_slice()).unwrap_err();
+        assert!(matches!(
+            CompressError::from(error),
+            CompressError::BadHeader(_)
+        ));
+    }
+
+    #[test]
+    fn test_encoder_reused() {
+        let sample = b"the quick brown fox jumps over the lazy dog";
+        let freq_map = freq_map_from_reader(&mut sample.as_ref()).unwrap();
+        let encoder = Encoder::new(&freq_map);
+
+        let inputs: [&[u8]; 4] = [b"the fox", b"", b"lazy dogs jump", sample];
+        let mut shared = Vec::new();
+        for input in inputs {
+            shared.clear();
+            encoder.encode(input, &mut shared).unwrap();
+            let mut independent = Vec::new();
+            Encoder::new(&freq_map)
+                .encode(input, &mut independent)
+                .unwrap();
+            assert_eq!(shared, independent);
+        }
+
+        // The sample itself is encoded as `compress` does, but for the header.
+        let mut compressed = Vec::new();
+        compress(sample, &mut compressed).unwrap();
+        assert!(compressed.ends_with(&shared));
+    }
+
+    #[test]
+    fn test_encoder_missing_byte() {
+        let encoder = Encoder::new(&HashMap::from([(b'A', 1), (b'B', 1)]));
+        let error = encoder.encode(b"ABC", &mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_dec_truncated() {
+        let mut encoded = Vec::new();
+        enc(&mut b"AAABBBAABACD".as_ref(), &mut encoded).unwrap();
+        encoded.pop();
+
+        let error = dec(&mut encoded.as_slice(), &mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_code_map_skips_zero_freq() {
+        let freq_map = HashMap::from([(b'A', 3), (b'B', 0), (b'C', 1), (b'D', 0)]);
+        let tree_arena = tree_from_freq_map(freq_map);
+        let map = code_map_from_tree(2, &tree_arena);
+
+        assert_eq!(map.len(), 2);
+        assert!(map.contains_key(&b'A'));
+        assert!(map.contains_key(&b'C'));
+        assert!(!map.contains_key(&b'B'));
+        assert!(!map.contains_key(&b'D'));
+    }
+
+    /// Decodes `bits` by matching one of the codes (which must be prefix-free)
+    /// at a time.
+    fn decode_with(code_map: &CodeMap, bits: &BitVec) -> Vec<u8> {
+        let by_code: HashMap<_, _> = code_map.iter().map(|(&char, code)| (code, char)).collect();
+        let mut decoded = Vec::new();
+        let mut code = BitVec::new();
+        for bit in bits {
+            code.push(*bit);
+            if let Some(&char) = by_code.get(&code) {
+                decoded.push(char);
+                code.clear();
+            }
+        }
+        assert!(code.is_empty());
+        decoded
+    }
+
+    #[test]
+    fn test_code_map_length_limited() {
+        // Fibonacci frequencies make the plain codes as long as possible.
+        let mut fib = (1, 1);
+        let freq_map: FreqMap = (0..24)
+            .map(|char| {
+                let freq = fib.0;
+                fib = (fib.1, fib.0 + fib.1);
+                (char, freq)
+            })
+            .collect();
+        let tree_arena = tree_from_freq_map(freq_map.clone());
+        let plain = code_map_from_tree(freq_map.len(), &tree_arena);
+        assert_eq!(plain.values().map(BitVec::len).max(), Some(23));
+
+        let data: Vec<u8> = (0..24u8)
+            .flat_map(|char| std::iter::repeat_n(char, usize::from(char) + 1))
+            .collect();
+        let cost = |code_map: &CodeMap| -> usize {
+            let freq = |char| usize::try_from(freq_map[char]).unwrap();
+            code_map
+                .iter()
+                .map(|(char, code)| freq(char) * code.len())
+                .sum()
+        };
+        let mut prev_cost = cost(&plain);
+        for max_len in [23, 15, 8, 5] {
+            let code_map = code_map_length_limited(&freq_map, max_len).unwrap();
+            assert_eq!(code_map.len(), 24);
+            assert!(code_map.values().all(|code| code.len() <= max_len as usize));
+            // Tighter limits may only cost more, and no limit costs nothing.
+            assert!(cost(&code_map) >= prev_cost, "{max_len}");
+            if max_len == 23 {
+                assert_eq!(cost(&code_map), prev_cost);
+            }
+            prev_cost = cost(&code_map);
+
+            let bits: BitVec = data.iter().flat_map(|char| &code_map[char]).collect();
+            assert_eq!(decode_with(&code_map, &bits), data);
+
+            // The code is complete (its Kraft sum is 1): no bit is wasted.
+            let kraft: u64 = code_map.values().map(|code| 1 << (23 - code.len())).sum();
+            assert_eq!(kraft, 1 << 23);
+        }
+    }
+
+    #[test]
+    fn test_code_map_length_limited_canonical() {
+        let freq_map = HashMap::from([(b'A', 6), (b'B', 4), (b'C', 1), (b'D', 1), (b'E', 0)]);
+        let code_map = code_map_length_limited(&freq_map, 15).unwrap();
+        assert_eq!(code_map.len(), 4);
+        assert_eq!(code_map[&b'A'], bitvec![usize, Lsb0; 0]);
+        assert_eq!(code_map[&b'B'], bitvec![usize, Lsb0; 1, 0]);
+        assert_eq!(code_map[&b'C'], bitvec![usize, Lsb0; 1, 1, 0]);
+        assert_eq!(code_map[&b'D'], bitvec![usize, Lsb0; 1, 1, 1]);
+
+        let code_map = code_map_length_limited(&freq_map, 2).unwrap();
+        assert!(code_map.values().all(|code| code.len() == 2));
+    }
+
+    #[test]
+    fn test_code_map_length_limited_bounds() {
+        let freq_map: FreqMap = (0..=255).map(|char| (char, 1)).collect();
+        assert!(code_map_length_limited(&freq_map, 8).is_ok());
+        let error = code_map_length_limited(&freq_map, 7).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let single = code_map_length_limited(&HashMap::from([(b'Z', 4)]), 0).unwrap();
+        assert_eq!(single[&b'Z'], BitVec::<usize, Lsb0>::new());
+        assert!(code_map_length_limited(&FreqMap::new(), 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_arena_unchecked_matches_safe() {
+        let inputs = [
+            &b"A"[..],
+            b"AB",
+            b"AAABBBAABACD",
+            &(0..=255).collect::<Vec<_>>(),
+            &(0..24u8)
+                .flat_map(|char| std::iter::repeat_n(char, 1 << char))
+                .collect::<Vec<_>>(),
+        ];
+        for data in inputs {
+            let freq_map = freq_map_from_reader(&mut &*data).unwrap();
+            let queue = || -> TreeQueue {
+                let mut stats: Vec<_> = freq_map.iter().collect();
+                stats.sort_unstable();
+                stats
+                    .into_iter()
+                    .enumerate()
+                    .map(|(order, (&char, &freq))| {
+                        Reverse((Tree::Leaf(Stat { char, freq }), order))
+                    })
+                    .collect()
+            };
+            let safe = arena_from_queue(queue(), freq_map.len());
+            // SAFETY: `data` isn't empty.
+            let unchecked = unsafe { arena_from_queue_unchecked(queue(), freq_map.len()) };
+            assert_eq!(safe, unchecked);
+            assert_eq!(safe, tree_from_freq_map(freq_map.clone()));
+            assert_eq!(
+                code_map_from_tree(freq_map.len(), &safe),
+                code_map_from_tree(freq_map.len(), &unchecked)
+            );
+        }
+    }
+
+    /// The recursive walk which [`code_map_from_tree`] replaced.
+    fn code_map_from_tree_recursive(size_hint: usize, arena: &TreeArena) -> CodeMap {
+        fn go(i: usize, arena: &TreeArena, map: &mut CodeMap, vec: BitVec) {
+            match &arena[i] {
+                Tree::Node { left, right, .. } => {
+                    let mut left_vec = vec.clone();
+                    left_vec.push(false);
+                    go(*left, arena, map, left_vec);
+
+                    let mut right_vec = vec;
+                    right_vec.push(true);
+                    go(*right, arena, map, right_vec);
+                }
+                Tree::Leaf(Stat { char, .. }) => {
+                    map.insert(*char, vec);
+                }
+            }
+        }
+
+        let mut map = HashMap::with_capacity(size_hint);
+        if !arena.is_empty() {
+            go(/* root */ 0, arena, &mut map, BitVec::new());
+        }
+        map
+    }
+
+    #[test]
+    fn test_code_map_matches_recursive() {
+        let skewed: Vec<u8> = (0..40u8)
+            .flat_map(|char| std::iter::repeat_n(char, 1 << (char / 2)))
+            .collect();
+        let inputs = [
+            &b"A"[..],
+            b"AB",
+            b"AAABBBAABACD",
+            &(0..=255).collect::<Vec<_>>(),
+            &skewed,
+        ];
+        for data in inputs {
+            let freq_map = freq_map_from_reader(&mut &*data).unwrap();
+            let arena = tree_from_freq_map(freq_map.clone());
+            assert_eq!(
+                code_map_from_tree(freq_map.len(), &arena),
+                code_map_from_tree_recursive(freq_map.len(), &arena)
+            );
+        }
+        assert!(code_map_from_tree(0, &Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn test_code_map_deep_tree() {
+        // A caterpillar tree, deeper than the test thread's stack would allow
+        // a recursive walk: each node has a leaf on its left, and the next
+        // node on its right.
+        let depth = 20_000;
+        let mut arena = Vec::new();
+        for i in 0..depth {
+            let (left, right) = (2 * i + 1, 2 * i + 2);
+            arena.push(Tree::Node {
+                freq: 0,
+                left,
+                right,
+            });
+            arena.push(Tree::Leaf(Stat {
+                freq: 0,
+                char: (i % 256) as u8,
+            }));
+        }
+        arena.push(Tree::Leaf(Stat { freq: 0, char: 0 }));
+        let code_map = code_map_from_tree(256, &arena);
+        assert_eq!(code_map.len(), 256);
+    }
+
+    #[test]
+    fn test_packed_codes() {
+        // Fibonacci frequencies make a tree as deep as it gets, here of 39
+        // levels.
+        let mut fib = (1, 1);
+        let skewed: FreqMap = (0..40)
+            .map(|char| {
+                fib = (fib.1, fib.0 + fib.1);
+                (char, fib.0)
+            })
+            .collect();
+        let text = freq_map_from_reader(&mut &b"AAABBBAABACD"[..]).unwrap();
+        for (freq_map, long) in [(text, false), (skewed, true)] {
+            let tree_arena = tree_from_freq_map(freq_map.clone());
+            let code_map = code_map_from_tree(freq_map.len(), &tree_arena);
+            let codes = PackedCodes::new(code_map.clone());
+            assert_eq!(matches!(codes, PackedCodes::Long(_)), long);
+
+            let data: Vec<u8> = freq_map.keys().copied().cycle().take(1000).collect();
+            let mut packed = Vec::new();
+            let mut writer = BitWriter::new(&mut packed);
+            assert!(codes.write(&data, &mut writer).unwrap());
+            writer.finish().unwrap();
+            let mut expected = Vec::new();
+            let mut writer = BitWriter::new(&mut expected);
+            for char in &data {
+                for bit in &code_map[char] {
+                    writer.write_bit(*bit).unwrap();
+                }
+            }
+            writer.finish().unwrap();
+            assert_eq!(packed, expected);
+
+            let mut writer = BitWriter::new(&mut packed);
+            assert!(!codes.write(&[0xff], &mut writer).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        for data in [
+            &b""[..],
+            b"A",
+            b"AB",
+            b"AAABBBAABACD",
+            &(0..=255).collect::<Vec<_>>(),
+        ] {
+            let arena = tree_from_freq_map(freq_map_from_reader(&mut &*data).unwrap());
+            assert_eq!(validate(&arena), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_validate_corrupt() {
+        let leaf = |char, freq| Tree::Leaf(Stat { char, freq });
+        let node = |freq, left, right| Tree::Node { freq, left, right };
+        // The tree of `ABB`.
+        let valid = || vec![node(3, 1, 2), leaf(b'A', 1), leaf(b'B', 2)];
+        assert_eq!(validate(&valid()), Ok(()));
+
+        let corrupt = [
+            (0, node(3, 1, 3), "out-of-bounds child 3"),
+            (0, node(3, 1, 1), "index 1 is reached more than once"),
+            (0, node(3, 0, 2), "index 0 is reached more than once"),
+            (0, node(4, 1, 2), "node 0 has frequency 4"),
+            (2, leaf(b'A', 2), "symbol 65 has more than one leaf"),
+            (0, leaf(b'A', 1), "index 1 is unreachable"),
+        ];
+        for (i, tree, error) in corrupt {
+            let mut arena = valid();
+            arena[i] = tree;
+            let result = validate(&arena).unwrap_err();
+            assert!(result.contains(error), "{result}");
+        }
+
+        let mut arena = valid();
+        arena.push(leaf(b'C', 1));
+        assert!(validate(&arena)
+            .unwrap_err()
+            .contains("index 3 is unreachable"));
+    }
+
+    #[test]
+    fn test_code_map_all_zero_freq() {
+        let freq_map = HashMap::from([(b'A', 0), (b'B', 0)]);
+        let tree_arena = tree_from_freq_map(freq_map);
+        assert!(tree_arena.is_empty());
+        assert!(code_map_from_tree(0, &tree_arena).is_empty());
     }
 }
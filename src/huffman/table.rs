@@ -0,0 +1,247 @@
+//! Table-driven decoding of canonical Huffman codes (see
+//! [`code_map_length_limited`](super::code_map_length_limited)).
+
+use std::{io, mem};
+
+use super::Char;
+use crate::shared::read_retry;
+
+/// The longest code a [`HuffmanDecodeTable`] supports, which bounds its size
+/// (to `2^MAX_TABLE_BITS` entries).
+pub const MAX_TABLE_BITS: usize = 20;
+
+// The entries store code lengths as bytes, and are indexed by `usize`s.
+const _: () = assert!(MAX_TABLE_BITS <= u8::MAX as usize && MAX_TABLE_BITS < usize::BITS as usize);
+
+/// A decoding table for canonical codes: indexed by the next `max_len` bits of
+/// the data, it tells the symbol whose code prefixes them, and the code's
+/// length. Decoding a symbol thus takes a single lookup instead of a walk down
+/// the tree, bit by bit.
+#[derive(Clone, Debug)]
+pub struct HuffmanDecodeTable {
+    max_len: usize,
+    /// `None` for the bit patterns no code prefixes (if the code is
+    /// incomplete).
+    entries: Vec<Option<(Char, u8)>>,
+}
+
+impl HuffmanDecodeTable {
+    /// Builds the table of the canonical codes with the given lengths, which
+    /// are assigned as [`code_map_length_limited`] does: ordered by length,
+    /// then by symbol, each code being the previous one plus one.
+    ///
+    /// [`code_map_length_limited`]: super::code_map_length_limited
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if a code is longer than
+    /// [`MAX_TABLE_BITS`], if a symbol is repeated, or if the lengths can't form
+    /// a prefix code (i.e., their Kraft sum exceeds one).
+    pub fn build(code_lengths: &[(Char, usize)]) -> io::Result<Self> {
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidInput, message);
+
+        let mut seen = [false; 256];
+        for &(char, _) in code_lengths {
+            if mem::replace(&mut seen[usize::from(char)], true) {
+                return Err(invalid("repeated Huffman symbol"));
+            }
+        }
+        let mut by_len = code_lengths.to_vec();
+        by_len.sort_unstable_by_key(|&(char, len)| (len, char));
+        let max_len = by_len.last().map_or(0, |&(_, len)| len);
+        if max_len > MAX_TABLE_BITS {
+            return Err(invalid("Huffman code too long for a decoding table"));
+        }
+
+        let mut entries = vec![None; 1 << max_len];
+        // The next code, left-aligned to `max_len` bits, such that the codes
+        // of each length take a contiguous range of entries.
+        let mut next = 0;
+        for (char, len) in by_len {
+            let span = 1 << (max_len - len);
+            let range = entries
+                .get_mut(next..next + span)
+                .ok_or_else(|| invalid("Huffman code lengths exceed the Kraft sum"))?;
+            // At most `MAX_TABLE_BITS`, as checked above.
+            range.fill(Some((char, len as u8)));
+            next += span;
+        }
+        Ok(Self { max_len, entries })
+    }
+
+    /// Decodes `count` symbols from `src`, whose codes are packed most
+    /// significant bit first (as the other Huffman functions write them).
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the underlying I/O operations fail (i.e., reading from
+    /// `src` or writing to `out`), with [`io::ErrorKind::UnexpectedEof`] if the
+    /// data is truncated, or with [`io::ErrorKind::InvalidData`] on bits which
+    /// no code prefixes.
+    pub fn decode(
+        &self,
+        src: &mut dyn io::Read,
+        out: &mut dyn io::Write,
+        count: u64,
+    ) -> io::Result<()> {
+        let mut bits = Bits::new(src);
+        let mut decoded = Vec::with_capacity(8 * 1024);
+        for _ in 0..count {
+            let available = bits.fill(self.max_len)?;
+            let Some((char, len)) = self.entries[bits.peek(self.max_len)] else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid Huffman code",
+                ));
+            };
+            let len = usize::from(len);
+            if len > available {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "truncated Huffman data",
+                ));
+            }
+            bits.consume(len);
+            decoded.push(char);
+            if decoded.len() == decoded.capacity() {
+                out.write_all(&decoded)?;
+                decoded.clear();
+            }
+        }
+        out.write_all(&decoded)
+    }
+}
+
+/// A reader of bits which may look ahead of the ones it consumes.
+struct Bits<'a> {
+    src: &'a mut dyn io::Read,
+    buf: Box<[u8; 8 * 1024]>,
+    pos: usize,
+    len: usize,
+    /// The pending bits, in the low `count` bits of `acc`.
+    acc: u64,
+    count: usize,
+}
+
+impl<'a> Bits<'a> {
+    fn new(src: &'a mut dyn io::Read) -> Self {
+        Self {
+            src,
+            buf: Box::new([0; 8 * 1024]),
+            pos: 0,
+            len: 0,
+            acc: 0,
+            count: 0,
+        }
+    }
+
+    /// Reads bytes until at least `n` bits are pending (unless the data ends),
+    /// returning the number of pending bits.
+    fn fill(&mut self, n: usize) -> io::Result<usize> {
+        while self.count < n {
+            if self.pos == self.len {
+                self.len = read_retry(self.src, &mut *self.buf)?;
+                self.pos = 0;
+                if self.len == 0 {
+                    break;
+                }
+            }
+            self.acc = self.acc << 8 | u64::from(self.buf[self.pos]);
+            self.pos += 1;
+            self.count += 8;
+        }
+        Ok(self.count)
+    }
+
+    /// Returns the next `n` pending bits, padded with zeroes past the end.
+    fn peek(&self, n: usize) -> usize {
+        let bits = if self.count >= n {
+            self.acc >> (self.count - n)
+        } else {
+            self.acc << (n - self.count)
+        };
+        // `n` is at most `MAX_TABLE_BITS`, which a `usize` holds.
+        (bits & ((1 << n) - 1)) as usize
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.count -= n;
+        self.acc &= (1 << self.count) - 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        huffman::{code_map_length_limited, freq_map_from_reader},
+        shared::BitWriter,
+    };
+
+    fn encode(data: &[u8], max_len: u32) -> (Vec<(Char, usize)>, Vec<u8>) {
+        let freq_map = freq_map_from_reader(&mut &*data).unwrap();
+        let code_map = code_map_length_limited(&freq_map, max_len).unwrap();
+        let mut encoded = Vec::new();
+        let mut writer = BitWriter::new(&mut encoded);
+        for char in data {
+            for bit in &code_map[char] {
+                writer.write_bit(*bit).unwrap();
+            }
+        }
+        writer.finish().unwrap();
+        let lengths = code_map
+            .iter()
+            .map(|(&char, code)| (char, code.len()))
+            .collect();
+        (lengths, encoded)
+    }
+
+    #[test]
+    fn test_decode() {
+        let data = "olá, mundo! como vai? tudo bem? ".repeat(100);
+        for max_len in [15, 6] {
+            let (lengths, encoded) = encode(data.as_bytes(), max_len);
+            let table = HuffmanDecodeTable::build(&lengths).unwrap();
+            let mut decoded = Vec::new();
+            table
+                .decode(&mut encoded.as_slice(), &mut decoded, data.len() as u64)
+                .unwrap();
+            assert_eq!(decoded, data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_decode_single_symbol() {
+        let table = HuffmanDecodeTable::build(&[(b'Z', 0)]).unwrap();
+        let mut decoded = Vec::new();
+        table.decode(&mut [].as_ref(), &mut decoded, 4).unwrap();
+        assert_eq!(decoded, b"ZZZZ");
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let (lengths, encoded) = encode(b"AAABBBAABACD", 15);
+        let table = HuffmanDecodeTable::build(&lengths).unwrap();
+        let error = table
+            .decode(&mut &encoded[..1], &mut Vec::new(), 12)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        // `11` prefixes no code of this incomplete one.
+        let table = HuffmanDecodeTable::build(&[(b'A', 1), (b'B', 2)]).unwrap();
+        let error = table
+            .decode(&mut [0b1100_0000].as_ref(), &mut Vec::new(), 1)
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let kraft = HuffmanDecodeTable::build(&[(b'A', 1), (b'B', 1), (b'C', 1)]);
+        let repeated = HuffmanDecodeTable::build(&[(b'A', 1), (b'B', 2), (b'A', 2)]);
+        let long = HuffmanDecodeTable::build(&[(b'A', 1), (b'B', MAX_TABLE_BITS + 1)]);
+        for result in [kraft, repeated, long] {
+            assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
@@ -1,4 +1,251 @@
+use std::{fmt, io};
+
+pub use codec::{registry, Codec, CodecRegistry};
+pub use error::CompressError;
+
+pub mod arithmetic;
+pub mod armor;
+pub mod codec;
+pub mod crc32;
+pub mod delta;
+pub mod error;
+pub mod frame;
+pub mod gzip;
 pub mod huffman;
 pub mod lzw;
+pub mod pipeline;
+pub mod prelude;
+pub mod range_coder;
+pub mod sniff;
+pub mod split;
+pub mod stats;
+pub mod stored;
+pub mod unix_compress;
 
 pub mod shared;
+
+/// The supported compression algorithms.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    Lzw,
+    Huffman,
+    Stored,
+}
+
+impl Algorithm {
+    /// All supported algorithms.
+    pub const ALL: [Algorithm; 3] = [Algorithm::Lzw, Algorithm::Huffman, Algorithm::Stored];
+
+    /// Returns the tag which identifies the algorithm in a [`CodecRegistry`].
+    pub fn tag(self) -> u8 {
+        match self {
+            Algorithm::Lzw => 1,
+            Algorithm::Huffman => 2,
+            Algorithm::Stored => 3,
+        }
+    }
+
+    /// Returns the algorithm with the given tag. See [`Algorithm::tag`].
+    pub fn from_tag(tag: u8) -> Option<Algorithm> {
+        Algorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.tag() == tag)
+    }
+
+    /// Returns the name of the algorithm.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Lzw => "lzw",
+            Algorithm::Huffman => "huffman",
+            Algorithm::Stored => "stored",
+        }
+    }
+
+    /// Returns a one-line description of the algorithm.
+    pub fn description(self) -> &'static str {
+        match self {
+            Algorithm::Lzw => "Lempel-Ziv-Welch dictionary coder",
+            Algorithm::Huffman => "Huffman entropy coder",
+            Algorithm::Stored => "no compression (copies the data as is)",
+        }
+    }
+
+    /// Encodes the given data using this algorithm.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the underlying I/O operations fail (i.e., reading from
+    /// `src` or writing to `out`).
+    pub fn enc(self, src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+        match self {
+            Algorithm::Lzw => lzw::enc(src, out),
+            Algorithm::Huffman => huffman::enc(src, out),
+            Algorithm::Stored => stored::enc(src, out),
+        }
+    }
+
+    /// Decodes the given data using this algorithm.
+    ///
+    /// # Errors
+    ///
+    /// Fails if any of the underlying I/O operations fail (i.e., reading from
+    /// `src` or writing to `out`), or if the data is invalid.
+    pub fn dec(self, src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+        match self {
+            Algorithm::Lzw => lzw::dec(src, out),
+            Algorithm::Huffman => huffman::dec(src, out),
+            Algorithm::Stored => stored::dec(src, out),
+        }
+    }
+}
+
+/// Information on the version of this crate and the algorithms it supports.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionInfo {
+    /// The version of the crate.
+    pub crate_version: &'static str,
+    /// The supported algorithms.
+    pub algorithms: Vec<Algorithm>,
+}
+
+impl fmt::Display for VersionInfo {
+    /// Formats the information as, e.g., `compressing 0.1.0 (lzw: 1, ...)`,
+    /// each algorithm being followed by its tag.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "compressing {} (", self.crate_version)?;
+        for (i, algorithm) in self.algorithms.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}: {}", algorithm.name(), algorithm.tag())?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Returns information on the version of this crate, e.g., to record what
+/// produced a file.
+pub fn version_info() -> VersionInfo {
+    VersionInfo {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        algorithms: Algorithm::ALL.to_vec(),
+    }
+}
+
+/// Compresses the given in-memory data.
+///
+/// # Errors
+///
+/// Fails if the underlying codec fails.
+pub fn compress(algorithm: Algorithm, data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match algorithm {
+        // Skips the buffering of the two-pass `Read`-based encoder.
+        Algorithm::Huffman => huffman::compress(data, &mut out)?,
+        _ => algorithm.enc(&mut &*data, &mut out)?,
+    }
+    Ok(out)
+}
+
+/// Decompresses the given in-memory data.
+///
+/// # Errors
+///
+/// Fails if the underlying codec fails, e.g., if the data is invalid.
+pub fn decompress(algorithm: Algorithm, data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    algorithm.dec(&mut &*data, &mut out)?;
+    Ok(out)
+}
+
+/// Compresses and then decompresses the given data in memory, returning
+/// whether the result equals the original data.
+///
+/// # Errors
+///
+/// Fails if the underlying codec fails.
+pub fn roundtrip_check(algorithm: Algorithm, data: &[u8]) -> io::Result<bool> {
+    let compressed = compress(algorithm, data)?;
+    let decompressed = decompress(algorithm, &compressed)?;
+    Ok(decompressed == data)
+}
+
+/// Round-trips a few built-in vectors through every codec of the
+/// [`registry`], e.g., to check that they work in the deployed environment.
+///
+/// # Errors
+///
+/// Fails with the error of the first codec which fails, or with
+/// [`CompressError::SelfTestFailed`] if a codec doesn't decode back to the
+/// original data.
+pub fn selftest() -> Result<(), CompressError> {
+    let all_bytes: Vec<u8> = (u8::MIN..=u8::MAX).collect();
+    let text = "olá, mundo! como vai? tudo bem? ".repeat(64);
+    let vectors: [&[u8]; 6] = [
+        b"",
+        b"A",
+        b"ABBABBBABBA",
+        b"AAAAAAAAAAAAAAAAAAAA",
+        &all_bytes,
+        text.as_bytes(),
+    ];
+
+    for entry in registry().iter() {
+        let codec = entry.codec();
+        for data in vectors {
+            let mut encoded = Vec::new();
+            codec.enc(&mut &*data, &mut encoded)?;
+            let mut decoded = Vec::new();
+            codec.dec(&mut encoded.as_slice(), &mut decoded)?;
+            if decoded != data {
+                return Err(CompressError::SelfTestFailed {
+                    codec: entry.name().to_owned(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_check() {
+        let inputs: [&[u8]; 4] = [
+            b"",
+            b"A",
+            b"ABBABBBABBA",
+            "olá, mundo! como vai?".as_bytes(),
+        ];
+        for algorithm in Algorithm::ALL {
+            for data in inputs {
+                assert!(roundtrip_check(algorithm, data).unwrap(), "{algorithm:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_selftest() {
+        selftest().unwrap();
+    }
+
+    #[test]
+    fn test_version_info() {
+        let info = version_info();
+        assert_eq!(info.crate_version, env!("CARGO_PKG_VERSION"));
+        let names: Vec<_> = info.algorithms.iter().map(|a| a.name()).collect();
+        assert!(names.contains(&"lzw"));
+        assert!(names.contains(&"huffman"));
+        assert!(info.to_string().contains("lzw: 1, huffman: 2"));
+    }
+
+    #[test]
+    fn test_compress_matches_codec() {
+        let data = b"ABABABA";
+        let mut out = Vec::new();
+        lzw::enc(&mut data.as_ref(), &mut out).unwrap();
+        assert_eq!(compress(Algorithm::Lzw, data).unwrap(), out);
+    }
+}
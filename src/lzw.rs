@@ -1,49 +1,352 @@
-use crate::shared::{read_u16, read_u8};
-use std::{collections::HashMap, io, mem};
+use crate::{
+    shared::{len_u64, read_retry, read_u16, read_u8, BitReader, BitWriter},
+    CompressError,
+};
+use std::{collections::HashMap, io, mem, ops::RangeInclusive};
+
+mod dictionary;
+pub mod generic;
+mod stream;
+
+pub use dictionary::Dictionary;
+pub use stream::StreamEncoder;
 
 pub type Code = u16;
 
-pub type EncDict = HashMap<Vec<u8>, Code>;
+/// The hasher of the encoder dictionary, which is hashed once per input byte:
+/// FxHash with the `fxhash` feature (faster, but not DoS-resistant), or else
+/// the standard SipHash.
+#[cfg(feature = "fxhash")]
+pub type DictHasher = rustc_hash::FxBuildHasher;
+#[cfg(not(feature = "fxhash"))]
+pub type DictHasher = std::collections::hash_map::RandomState;
+
+pub type EncDict = HashMap<Vec<u8>, Code, DictHasher>;
 pub type DecDict = HashMap<Code, Vec<u8>>;
 
+/// The supported range of maximum code widths of variable-width streams.
+pub const MAX_BITS_RANGE: RangeInclusive<u32> = 9..=Code::BITS;
+
+// The maximum width is written as a single byte.
+const _: () = assert!(*MAX_BITS_RANGE.end() <= u8::MAX as u32);
+
+/// Tuning options of the LZW codec. The default options are the ones used by
+/// [`enc`] and [`dec`].
+///
+/// Data must be decoded with the same options it was encoded with.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct LzwOptions {
+    /// The maximum length of a dictionary key, or `None` for unbounded keys.
+    ///
+    /// Once the current sequence reaches this length, the encoder emits it and
+    /// starts a fresh one instead of extending it, which bounds the memory
+    /// held by each key on long runs of a single byte. A cap below 1 acts as 1.
+    pub max_key_len: Option<usize>,
+
+    /// Whether codes are written with a variable width (see [`code_width`])
+    /// instead of a fixed width of 16 bits.
+    ///
+    /// A variable-width stream starts with a byte holding its maximum code
+    /// width (see [`LzwOptions::max_bits`]).
+    pub variable_width: bool,
+
+    /// The maximum width of a variable-width code, or `None` for 16 bits. A
+    /// width outside [`MAX_BITS_RANGE`] acts as the nearest bound.
+    ///
+    /// The dictionary stops growing once it holds `2^max_bits` entries, so
+    /// fewer bits encode faster but usually compress worse. The width is
+    /// recorded in the stream (hence the decoder ignores this option), and
+    /// fixed width codes ignore it too.
+    pub max_bits: Option<u32>,
+
+    /// The number of codes after which the encoder flushes its writer, or
+    /// `None` to never flush it. An interval below 1 acts as 1.
+    ///
+    /// This trades more writes for a lower latency when streaming, and doesn't
+    /// change the encoded data (hence the decoder ignores it). With variable
+    /// width codes, the bits of a partial byte are only written along with the
+    /// following codes.
+    pub flush_interval: Option<usize>,
+}
+
+impl LzwOptions {
+    fn max_key_len(&self) -> usize {
+        self.max_key_len.map_or(usize::MAX, |len| len.max(1))
+    }
+
+    fn flush_interval(&self) -> Option<usize> {
+        self.flush_interval.map(|interval| interval.max(1))
+    }
+
+    /// Returns the maximum code width, or `None` with fixed width codes.
+    fn code_bits(&self) -> Option<u32> {
+        let max_bits = self.max_bits.unwrap_or(Code::BITS);
+        let max_bits = max_bits.clamp(*MAX_BITS_RANGE.start(), *MAX_BITS_RANGE.end());
+        self.variable_width.then_some(max_bits)
+    }
+}
+
+/// Returns the number of entries after which the dictionary stops growing: all
+/// codes of the maximum width, or (with fixed width codes) unbounded, such that
+/// the dictionary overflows instead.
+fn dict_capacity(max_bits: Option<u32>) -> usize {
+    max_bits.map_or(usize::MAX, |bits| 1 << bits)
+}
+
 /// Encodes the given data.
 ///
 /// # Errors
 ///
 /// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
-/// or writing to `out`).
+/// or writing to `out`), or with [`CompressError::DictionaryOverflow`] if the
+/// dictionary grows past `u16`'s bounds.
+pub fn enc(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    enc_with(src, out, &LzwOptions::default())
+}
+
+/// Encodes the given data with the given options. See [`enc`].
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if the dictionary grows past `u16`'s bounds.
-pub fn enc(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
-    enc_returning_dict(src, out)?;
+/// Fails under the same conditions as [`enc`].
+pub fn enc_with(
+    src: &mut dyn io::Read,
+    out: &mut dyn io::Write,
+    options: &LzwOptions,
+) -> io::Result<()> {
+    enc_returning_dict(src, out, options)?;
     Ok(())
 }
 
+/// Encodes the given in-memory data, appending the output to `out`.
+///
+/// Reusing `out` (cleared in between) across many small messages saves an
+/// output allocation per message.
+///
+/// # Errors
+///
+/// Fails with [`CompressError::DictionaryOverflow`] if the dictionary grows
+/// past `u16`'s bounds.
+pub fn enc_into(input: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+    enc(&mut &*input, out)
+}
+
 #[doc(hidden)]
-pub fn enc_returning_dict(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<EncDict> {
-    let mut dict = build_default_enc_dict();
-    let mut seq = Vec::<u8>::new();
+pub fn enc_returning_dict(
+    src: &mut dyn io::Read,
+    out: &mut dyn io::Write,
+    options: &LzwOptions,
+) -> io::Result<EncDict> {
+    let mut codes = codes_with(src, options);
+    let max_bits = options.code_bits();
+    if let Some(bits) = max_bits {
+        // Clamped to `MAX_BITS_RANGE`, whose widths fit in a byte.
+        out.write_all(&[bits as u8])?;
+    }
+    let mut writer = CodeWriter::new(out, max_bits);
+    let flush_interval = options.flush_interval();
+    let mut count = 0;
+    while let Some(code) = codes.next_code()? {
+        writer.write(code)?;
+        count += 1;
+        if flush_interval.is_some_and(|interval| count % interval == 0) {
+            writer.flush()?;
+        }
+    }
+    writer.finish()?;
+    Ok(codes.state.dict.into_map())
+}
+
+/// Statistics of the encoder dictionary (see [`dict_stats`]).
+#[derive(Clone, Debug, PartialEq)]
+pub struct DictStats {
+    /// The number of entries, including the 256 single bytes.
+    pub final_size: usize,
+    /// The average length of the keys (the single bytes included).
+    pub avg_key_len: f64,
+    /// The length of the longest key.
+    pub longest_key_len: usize,
+    /// The number of codes emitted while the dictionary held `256 << i` up to
+    /// `512 << i` entries (i.e., codes `9 + i` bits wide with variable width
+    /// codes, see [`code_width`]), for each bracket `i` up to the last one the
+    /// dictionary reached.
+    pub codes_per_bracket: Vec<u64>,
+}
+
+/// Encodes the given data (with the default options, discarding the output),
+/// and summarizes the resulting dictionary, e.g., to tell whether it saturated
+/// usefully.
+///
+/// # Errors
+///
+/// Fails under the same conditions as [`enc`].
+pub fn dict_stats(src: &mut dyn io::Read) -> io::Result<DictStats> {
+    let mut encoded = Vec::new();
+    let dict = enc_returning_dict(src, &mut encoded, &LzwOptions::default())?;
+    // Fixed width codes take two bytes each.
+    let code_count = encoded.len() / 2;
+
+    let total_key_len: usize = dict.keys().map(Vec::len).sum();
+    // Each code but the last adds an entry (unbounded keys, no capacity), so
+    // the dictionary holds `256 + i` entries when it emits the `i`th code.
+    let mut codes_per_bracket = Vec::new();
+    let mut start = 0;
+    while start < code_count {
+        let end = (512 << codes_per_bracket.len()) - 256;
+        codes_per_bracket.push(len_u64(end.min(code_count) - start));
+        start = end;
+    }
+    Ok(DictStats {
+        final_size: dict.len(),
+        avg_key_len: total_key_len as f64 / dict.len() as f64,
+        longest_key_len: dict.keys().map(Vec::len).max().unwrap_or(0),
+        codes_per_bracket,
+    })
+}
+
+/// Returns an iterator over the codes the encoder emits for the given data,
+/// without serializing them.
+///
+/// The iterator yields an error (and then stops) if reading from `src` fails,
+/// or if the dictionary grows past `u16`'s bounds (see [`enc`]).
+///
+/// `src` is read in chunks, so it may be read ahead of the bytes behind the
+/// codes yielded so far.
+pub fn codes(src: &mut dyn io::Read) -> Codes<'_> {
+    codes_with(src, &LzwOptions::default())
+}
+
+/// Returns an iterator over the codes the encoder emits for the given data
+/// with the given options. See [`codes`].
+pub fn codes_with<'src>(src: &'src mut dyn io::Read, options: &LzwOptions) -> Codes<'src> {
+    Codes {
+        src,
+        buf: vec![0; READ_BUF_LEN].into_boxed_slice(),
+        pos: 0,
+        len: 0,
+        state: EncState::new(options),
+        done: false,
+    }
+}
+
+/// The state of the encoder, which is fed a byte at a time.
+struct EncState {
+    dict: Dictionary<EncDict>,
+    /// The current sequence, which is in the dictionary.
+    seq: Vec<u8>,
+    max_key_len: usize,
+    capacity: usize,
+}
+
+impl EncState {
+    fn new(options: &LzwOptions) -> Self {
+        Self {
+            dict: Dictionary::<EncDict>::new(),
+            seq: Vec::new(),
+            max_key_len: options.max_key_len(),
+            capacity: dict_capacity(options.code_bits()),
+        }
+    }
+
+    /// Feeds the next byte, returning the code to emit (if any).
+    fn push(&mut self, c: u8) -> io::Result<Option<Code>> {
+        // Advance while the next char forms a key which is in the map.
+        // When the next char forms a string which is not in the map, emits it
+        // and inserts (it + the char) in the map, unless that key would be too
+        // long or the map is full.
+        self.seq.push(c);
+        let capped = self.seq.len() > self.max_key_len;
+        if capped || !self.dict.contains(&self.seq) {
+            let prev_seq = &self.seq[..(self.seq.len() - 1)];
+            let emitted = self.dict.get(prev_seq).unwrap();
+
+            let seq = mem::replace(&mut self.seq, vec![c]);
+            if !capped && self.dict.len() < self.capacity {
+                self.dict.insert(seq)?;
+            }
+            return Ok(Some(emitted));
+        }
+        Ok(None)
+    }
+
+    /// Returns the code of the current sequence (if any), once the data ended.
+    fn finish(&mut self) -> Option<Code> {
+        if self.seq.is_empty() {
+            return None;
+        }
+        let emitted = self.dict.get(&self.seq).unwrap();
+        self.seq.clear();
+        Some(emitted)
+    }
+}
 
-    // Advance while the next char forms a key which is in the map.
-    // When the next char forms a string which is not in the map, emits it
-    // and inserts (it + the char) in the map.
-    while let Some(c) = read_u8(src)? {
-        seq.push(c);
-        if !dict.contains_key(&seq) {
-            let prev_seq = &seq[..(seq.len() - 1)];
-            emit(prev_seq, &dict, out)?;
+/// The length of the encoder's input buffer.
+const READ_BUF_LEN: usize = 8 * 1024;
 
-            let code = dict.len().try_into().unwrap();
-            dict.insert(mem::replace(&mut seq, vec![c]), code);
+/// An iterator over the emitted LZW codes. See [`codes`].
+pub struct Codes<'src> {
+    src: &'src mut dyn io::Read,
+    /// The input read ahead, of which `buf[pos..len]` is yet to be encoded.
+    /// Iterating over it (rather than reading each byte from `src`) saves a
+    /// dynamic call per byte.
+    buf: Box<[u8]>,
+    pos: usize,
+    len: usize,
+    state: EncState,
+    done: bool,
+}
+
+impl Codes<'_> {
+    /// Returns the encoder dictionary in its current state.
+    pub fn dict(&self) -> &Dictionary<EncDict> {
+        &self.state.dict
+    }
+
+    fn next_code(&mut self) -> io::Result<Option<Code>> {
+        while let Some(c) = self.next_byte()? {
+            if let Some(code) = self.state.push(c)? {
+                return Ok(Some(code));
+            }
         }
+        Ok(self.state.finish())
     }
-    if !seq.is_empty() {
-        emit(&seq, &dict, out)?;
+
+    #[inline]
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        if self.pos == self.len {
+            self.len = read_retry(self.src, &mut self.buf)?;
+            self.pos = 0;
+            if self.len == 0 {
+                return Ok(None);
+            }
+        }
+        let c = self.buf[self.pos];
+        self.pos += 1;
+        Ok(Some(c))
     }
+}
+
+impl Iterator for Codes<'_> {
+    type Item = io::Result<Code>;
 
-    Ok(dict)
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_code() {
+            Ok(Some(code)) => Some(Ok(code)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
 }
 
 /// Decodes the given data.
@@ -51,57 +354,518 @@ pub fn enc_returning_dict(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io
 /// # Errors
 ///
 /// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
-/// or writing to `out`).
+/// or writing to `out`), with [`CompressError::InvalidCode`] on a code the
+/// encoder couldn't have emitted (along with the number of bytes decoded up to
+/// it), with [`io::ErrorKind::UnexpectedEof`] if the data ends in the middle of
+/// a (fixed-width) code, or with [`CompressError::DictionaryOverflow`] if the
+/// dictionary grows past `u16`'s bounds. See [`dec_best_effort`] to recover
+/// what precedes such corruption instead.
+pub fn dec(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    dec_with(src, out, &LzwOptions::default())
+}
+
+/// Decodes the given data, which must have been encoded with the given
+/// options. See [`dec`].
 ///
-/// # Panics
+/// # Errors
 ///
-/// Panics if the dictionary grows past `u16`'s bounds.
-pub fn dec(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
-    let mut dict = build_default_dec_dict();
-    let mut seq = Vec::<u8>::new();
-
-    while let Some(code) = read_u16(src)? {
-        let decoded = dict.get(&code).map(Clone::clone).unwrap_or_else(|| {
-            let mut s = seq.clone();
-            s.push(s[0]);
-            s
-        });
-        out.write_all(&decoded)?;
-
-        if !seq.is_empty() {
-            let next_code = dict.len().try_into().unwrap();
-            dict.insert(next_code, {
-                let mut s = mem::take(&mut seq);
-                s.push(decoded[0]);
+/// Fails under the same conditions as [`dec`].
+pub fn dec_with(
+    src: &mut dyn io::Read,
+    out: &mut dyn io::Write,
+    options: &LzwOptions,
+) -> io::Result<()> {
+    let max_bits = if options.variable_width {
+        Some(read_max_bits(src)?)
+    } else {
+        None
+    };
+    let mut decoder = Decoder::new(src, options, max_bits);
+    while let Some(decoded) = decoder.next_seq()? {
+        out.write_all(decoded)?;
+    }
+    Ok(())
+}
+
+/// Decodes the given in-memory data, appending the output to `out`. See
+/// [`enc_into`].
+///
+/// # Errors
+///
+/// Fails under the same conditions as [`dec`] (but for I/O errors), leaving
+/// the bytes decoded so far in `out`.
+pub fn dec_into(input: &[u8], out: &mut Vec<u8>) -> io::Result<()> {
+    dec(&mut &*input, out)
+}
+
+/// The outcome of [`dec_best_effort`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// The number of bytes decoded (and written) before the data ended or
+    /// turned out corrupt.
+    pub recovered: u64,
+    /// Whether the data was decoded up to its end, without any corruption.
+    pub complete: bool,
+}
+
+/// Decodes as much of the given data as possible: unlike [`dec`], a truncated
+/// or invalid code ends the decoding (all bytes before it being written) rather
+/// than failing it, and the report tells whether that happened.
+///
+/// Note that a stream cut off at a code boundary is indistinguishable from a
+/// complete one, as LZW doesn't record its length.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`), or if the dictionary overflows (see [`dec`]).
+pub fn dec_best_effort(
+    src: &mut dyn io::Read,
+    out: &mut dyn io::Write,
+) -> io::Result<RecoveryReport> {
+    dec_best_effort_with(src, out, &LzwOptions::default())
+}
+
+/// Decodes as much of the given data, which must have been encoded with the
+/// given options, as possible. See [`dec_best_effort`].
+///
+/// # Errors
+///
+/// Fails under the same conditions as [`dec_best_effort`], or if a
+/// variable-width stream lacks its header.
+pub fn dec_best_effort_with(
+    src: &mut dyn io::Read,
+    out: &mut dyn io::Write,
+    options: &LzwOptions,
+) -> io::Result<RecoveryReport> {
+    let max_bits = if options.variable_width {
+        Some(read_max_bits(src)?)
+    } else {
+        None
+    };
+    let mut decoder = Decoder::new(src, options, max_bits);
+    decoder.best_effort = true;
+    while let Some(decoded) = decoder.next_seq()? {
+        out.write_all(decoded)?;
+    }
+    Ok(RecoveryReport {
+        recovered: decoder.offset,
+        complete: !decoder.corrupt,
+    })
+}
+
+/// Reads the maximum code width a variable-width stream starts with.
+fn read_max_bits(src: &mut dyn io::Read) -> io::Result<u32> {
+    let max_bits = read_u8(src)?.ok_or(CompressError::BadHeader("missing max bits"))?;
+    let max_bits = u32::from(max_bits);
+    if !MAX_BITS_RANGE.contains(&max_bits) {
+        return Err(CompressError::BadHeader("unsupported max bits").into());
+    }
+    Ok(max_bits)
+}
+
+/// Returns an iterator over the decoded bytes of the given data, which only
+/// reads as many codes from `src` as needed for the bytes consumed so far.
+///
+/// The iterator yields an error (and then stops) under the same conditions as
+/// [`dec`].
+pub fn decode_iter(src: &mut dyn io::Read) -> DecodedBytes<'_> {
+    DecodedBytes {
+        decoder: Decoder::new(src, &LzwOptions::default(), None),
+        pos: 0,
+        done: false,
+    }
+}
+
+/// An iterator over the decoded bytes. See [`decode_iter`].
+pub struct DecodedBytes<'src> {
+    decoder: Decoder<'src>,
+    /// The position of the next byte in the decoder's current sequence.
+    pos: usize,
+    done: bool,
+}
+
+impl Iterator for DecodedBytes<'_> {
+    type Item = io::Result<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if let Some(&c) = self.decoder.seq.get(self.pos) {
+            self.pos += 1;
+            return Some(Ok(c));
+        }
+        match self.decoder.next_seq() {
+            // Decoded sequences are never empty.
+            Ok(Some(decoded)) => {
+                self.pos = 1;
+                Some(Ok(decoded[0]))
+            }
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Decodes the given data, only returning its bytes in `start..start + len`
+/// (or fewer, if the decoded data ends earlier).
+///
+/// LZW lacks random access, so this still decodes everything before `start`,
+/// but discards it instead of buffering it, and stops reading `src` as soon as
+/// the range is complete.
+///
+/// # Errors
+///
+/// Fails under the same conditions as [`dec`], but only for the codes it reads.
+pub fn decompress_range(src: &mut dyn io::Read, start: u64, len: u64) -> io::Result<Vec<u8>> {
+    let end = start.saturating_add(len);
+    let mut decoder = Decoder::new(src, &LzwOptions::default(), None);
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < end {
+        let Some(decoded) = decoder.next_seq()? else {
+            break;
+        };
+        let seq_end = pos + len_u64(decoded.len());
+        if seq_end > start {
+            // Both are at most `decoded.len()`, hence fit in a `usize`.
+            let from = start.saturating_sub(pos) as usize;
+            let to = (end.min(seq_end) - pos) as usize;
+            out.extend_from_slice(&decoded[from..to]);
+        }
+        pos = seq_end;
+    }
+    Ok(out)
+}
+
+/// The state of the decoder, which decodes a sequence per code.
+struct Decoder<'src> {
+    reader: CodeReader<'src>,
+    dict: Dictionary<DecDict>,
+    /// The last decoded sequence.
+    seq: Vec<u8>,
+    max_key_len: usize,
+    capacity: usize,
+    /// The number of codes read, and of the ones that didn't get an entry
+    /// because of the key length cap (or of a full dictionary), to check that
+    /// the dictionary stays in sync with the encoder's.
+    codes: usize,
+    capped: usize,
+    /// The number of decoded bytes, which is reported on errors.
+    offset: u64,
+    /// Whether a truncated or invalid code ends the data (setting `corrupt`)
+    /// instead of being an error.
+    best_effort: bool,
+    corrupt: bool,
+}
+
+impl<'src> Decoder<'src> {
+    /// Constructs a decoder of codes with the given maximum width (see
+    /// [`CodeReader`]), the stream's header (if any) having been read already.
+    fn new(src: &'src mut dyn io::Read, options: &LzwOptions, max_bits: Option<u32>) -> Self {
+        Self {
+            reader: CodeReader::new(src, max_bits),
+            dict: Dictionary::<DecDict>::new(),
+            seq: Vec::new(),
+            max_key_len: options.max_key_len(),
+            capacity: dict_capacity(max_bits),
+            codes: 0,
+            capped: 0,
+            offset: 0,
+            best_effort: false,
+            corrupt: false,
+        }
+    }
+
+    /// Reads the next code, returning the sequence it stands for.
+    fn next_seq(&mut self) -> io::Result<Option<&[u8]>> {
+        if self.corrupt {
+            return Ok(None);
+        }
+        let code = match self.reader.read() {
+            Ok(Some(code)) => code,
+            Ok(None) => return Ok(None),
+            Err(error) if self.best_effort && error.kind() == io::ErrorKind::UnexpectedEof => {
+                self.corrupt = true;
+                return Ok(None);
+            }
+            Err(error) => return Err(error),
+        };
+        let seq = &mut self.seq;
+        let decoded = match self.dict.get(code) {
+            Some(decoded) => decoded.to_vec(),
+            // The code the encoder has just assigned: it must be the previous
+            // sequence plus its own first char.
+            None if self.dict.next_code() == Some(code)
+                && self.dict.len() < self.capacity
+                && !seq.is_empty()
+                && seq.len() < self.max_key_len =>
+            {
+                let mut s = seq.clone();
+                s.push(s[0]);
                 s
-            });
+            }
+            None if self.best_effort => {
+                self.corrupt = true;
+                return Ok(None);
+            }
+            None => {
+                let offset = self.offset;
+                return Err(CompressError::InvalidCode { code, offset }.into());
+            }
+        };
+
+        // Mirrors the encoder, which inserts an entry along with every code
+        // but the last one (which the decoder can't know of yet), unless the
+        // key would be longer than the cap or the dictionary is full.
+        if !seq.is_empty() && seq.len() < self.max_key_len && self.dict.len() < self.capacity {
+            let mut s = mem::take(seq);
+            s.push(decoded[0]);
+            self.dict.insert(s)?;
+        } else if !seq.is_empty() {
+            self.capped += 1;
         }
+        self.codes += 1;
+        // A single missing (or extra) entry would shift every following code.
+        debug_assert_eq!(self.dict.len(), 256 + self.codes - 1 - self.capped);
 
-        seq = decoded;
+        self.offset += len_u64(decoded.len());
+        *seq = decoded;
+        Ok(Some(seq))
     }
+}
 
-    Ok(())
+/// Encodes the given data, first remapping the byte values in use to the
+/// contiguous range `0..k`.
+///
+/// The output is prefixed by the remap table: `k` (as a `u16`) followed by the
+/// `k` used byte values in increasing order, such that the remapped value `i`
+/// stands for the `i`-th listed byte.
+///
+/// Note that, since codes are always 16 bits wide, the remapping alone doesn't
+/// shrink the code stream: LZW parses the input identically under any byte
+/// permutation. It only pays off when codes are allowed to start narrower.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`), or if the dictionary overflows (see [`enc`]).
+pub fn enc_remapped(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let mut data = Vec::new();
+    src.read_to_end(&mut data)?;
+
+    let mut used = [false; 256];
+    for &c in &data {
+        used[usize::from(c)] = true;
+    }
+    let table: Vec<u8> = (u8::MIN..=u8::MAX)
+        .filter(|&c| used[usize::from(c)])
+        .collect();
+
+    let mut remap = [0; 256];
+    for (i, &c) in (u8::MIN..=u8::MAX).zip(&table) {
+        remap[usize::from(c)] = i;
+    }
+    for c in &mut data {
+        *c = remap[usize::from(*c)];
+    }
+
+    // At most 256, the number of distinct bytes.
+    let table_len = table.len() as u16;
+    out.write_all(&table_len.to_be_bytes())?;
+    out.write_all(&table)?;
+    enc(&mut data.as_slice(), out)
+}
+
+/// Decodes data produced by [`enc_remapped`].
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`), if the remap table is malformed or inconsistent with
+/// the decoded data, or if the code stream is invalid (see [`dec`]).
+pub fn dec_remapped(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let table_len = read_u16(src)?.ok_or(CompressError::BadHeader("missing remap table"))?;
+    if table_len > 256 {
+        return Err(CompressError::BadHeader("remap table too long").into());
+    }
+    let mut table = vec![0; usize::from(table_len)];
+    src.read_exact(&mut table)?;
+
+    let mut data = Vec::new();
+    dec(src, &mut data)?;
+    for c in &mut data {
+        *c = *table.get(usize::from(*c)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "byte outside of the remap table",
+            )
+        })?;
+    }
+    out.write_all(&data)
+}
+
+/// Returns the width, in bits, of the `index`-th code (starting at 0) of a
+/// variable-width stream.
+///
+/// The encoder's dictionary has `256 + index` entries when it emits that code,
+/// and the width grows as soon as this size reaches `2^width`: codes `0..256`
+/// have 9 bits, codes `256..768` have 10 bits, and so on, up to 16 bits (or
+/// the stream's maximum width, see [`LzwOptions::max_bits`]).
+///
+/// The decoder is one entry behind the encoder (it only learns of an entry
+/// with the code that follows it), so it must derive the width from the code's
+/// index rather than from its own dictionary size.
+pub fn code_width(index: usize) -> u32 {
+    let size = index.saturating_add(256);
+    (usize::BITS - size.leading_zeros()).min(Code::BITS)
+}
+
+/// Returns an upper bound of the largest code which the encoder (with unbounded
+/// keys) emits for an input of `len` bytes, of which `distinct_bytes` differ,
+/// without running it. E.g., its width tells whether a lower
+/// [`max_bits`](LzwOptions#structfield.max_bits) would cost anything.
+///
+/// Every code but the last adds an entry: the emitted sequence plus the next
+/// byte. These entries are distinct, so at most `k^(l + 1)` of the emitted
+/// sequences have `l` bytes (over `k` distinct bytes), and the number of codes
+/// is at most the one of parsing the input into as many short sequences as
+/// these counts allow. The bound is loose (LZW rarely parses as finely), and
+/// saturates at [`Code::MAX`].
+pub fn max_code_for_input(len: u64, distinct_bytes: usize) -> Code {
+    let k = len_u64(distinct_bytes.clamp(1, 256));
+    let mut codes = 0u64;
+    let mut remaining = len;
+    let mut seq_len = 1u64;
+    // Past `Code::MAX` codes, the bound saturates anyway.
+    while remaining > 0 && codes <= u64::from(Code::MAX) {
+        let count = k.saturating_pow(u32::try_from(seq_len + 1).unwrap_or(u32::MAX));
+        if remaining / seq_len < count {
+            // The last sequence may be shorter.
+            codes += remaining.div_ceil(seq_len);
+            break;
+        }
+        codes += count;
+        remaining -= count * seq_len;
+        seq_len += 1;
+    }
+    // The first code adds no entry beyond the 256 single bytes.
+    let entries = codes.saturating_sub(1);
+    Code::try_from(255 + entries).unwrap_or(Code::MAX)
 }
 
-fn emit(seq: &[u8], dict: &EncDict, out: &mut dyn io::Write) -> io::Result<()> {
-    let code = Code::to_be_bytes(dict[seq]);
-    out.write_all(&code)
+/// Writes codes with a variable width of at most `max_bits`, or else with a
+/// fixed width.
+enum CodeWriter<'a> {
+    Fixed(&'a mut dyn io::Write),
+    Variable {
+        bits: BitWriter<'a>,
+        index: usize,
+        max_bits: u32,
+    },
 }
 
-fn build_default_enc_dict() -> EncDict {
-    let mut dict = HashMap::new();
-    for i in u8::MIN..=u8::MAX {
-        dict.insert(vec![i], i.into());
+impl<'a> CodeWriter<'a> {
+    fn new(out: &'a mut dyn io::Write, max_bits: Option<u32>) -> Self {
+        match max_bits {
+            Some(max_bits) => CodeWriter::Variable {
+                bits: BitWriter::new(out),
+                index: 0,
+                max_bits,
+            },
+            None => CodeWriter::Fixed(out),
+        }
+    }
+
+    fn write(&mut self, code: Code) -> io::Result<()> {
+        match self {
+            CodeWriter::Fixed(out) => out.write_all(&Code::to_be_bytes(code)),
+            CodeWriter::Variable {
+                bits,
+                index,
+                max_bits,
+            } => {
+                bits.write_bits(code.into(), code_width(*index).min(*max_bits))?;
+                *index += 1;
+                Ok(())
+            }
+        }
+    }
+
+    /// Flushes the underlying writer (see [`LzwOptions::flush_interval`]).
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CodeWriter::Fixed(out) => out.flush(),
+            CodeWriter::Variable { bits, .. } => bits.flush(),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            CodeWriter::Fixed(_) => Ok(()),
+            CodeWriter::Variable { bits, .. } => bits.finish(),
+        }
     }
-    dict
 }
 
-fn build_default_dec_dict() -> DecDict {
-    let mut dict = HashMap::new();
-    for i in u8::MIN..=u8::MAX {
-        dict.insert(i.into(), vec![i]);
+/// Reads codes written by a [`CodeWriter`] with the same maximum width.
+enum CodeReader<'a> {
+    Fixed(&'a mut dyn io::Read),
+    Variable {
+        bits: BitReader<'a>,
+        index: usize,
+        max_bits: u32,
+    },
+}
+
+impl<'a> CodeReader<'a> {
+    fn new(src: &'a mut dyn io::Read, max_bits: Option<u32>) -> Self {
+        match max_bits {
+            Some(max_bits) => CodeReader::Variable {
+                bits: BitReader::new(src),
+                index: 0,
+                max_bits,
+            },
+            None => CodeReader::Fixed(src),
+        }
+    }
+
+    fn read(&mut self) -> io::Result<Option<Code>> {
+        match self {
+            CodeReader::Fixed(src) => {
+                let Some(high) = read_u8(*src)? else {
+                    return Ok(None);
+                };
+                let low = read_u8(*src)?.ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::UnexpectedEof, "truncated LZW code")
+                })?;
+                Ok(Some(Code::from_be_bytes([high, low])))
+            }
+            CodeReader::Variable {
+                bits,
+                index,
+                max_bits,
+            } => {
+                // The padding of the last byte is always narrower than a code.
+                let width = code_width(*index).min(*max_bits);
+                let Some(code) = bits.read_bits(width)? else {
+                    return Ok(None);
+                };
+                *index += 1;
+                // `read_max_bits` bounds the width to `Code::BITS`, but a
+                // wider code would be one of an overflowing dictionary.
+                let code = Code::try_from(code).map_err(|_| CompressError::DictionaryOverflow)?;
+                Ok(Some(code))
+            }
+        }
     }
-    dict
 }
 
 #[cfg(test)]
@@ -150,6 +914,566 @@ mod tests {
         ),
     ];
 
+    #[test]
+    fn test_codes_iter() {
+        let mut src = b"ABABABA".as_ref();
+        let codes = codes(&mut src).collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(codes, [65, 66, 256, 258]);
+    }
+
+    #[test]
+    fn test_codes_iter_empty() {
+        let mut src = b"".as_ref();
+        assert!(codes(&mut src).next().is_none());
+    }
+
+    #[test]
+    fn test_remapped_roundtrip() {
+        let data = b"\xF0\x10\xF0\x80\x10\xF0\xF0\x80\x10\x10";
+        let mut out = Vec::new();
+        enc_remapped(&mut data.as_ref(), &mut out).unwrap();
+
+        // The table lists the three used bytes in increasing order.
+        assert_eq!(out[..5], [0, 3, 0x10, 0x80, 0xF0]);
+
+        let mut recovered = Vec::new();
+        dec_remapped(&mut &out[..], &mut recovered).unwrap();
+        assert_eq!(recovered, data);
+    }
+
+    #[test]
+    fn test_remapped_size() {
+        // Sparse alphabet: only `k` distinct bytes.
+        let data: Vec<u8> = (0..4096u32)
+            .map(|i| [7, 99, 200][(i % 7 % 3) as usize])
+            .collect();
+
+        let mut plain = Vec::new();
+        enc(&mut data.as_slice(), &mut plain).unwrap();
+        let mut remapped = Vec::new();
+        enc_remapped(&mut data.as_slice(), &mut remapped).unwrap();
+
+        // Same parse, so only the table is added on top of the plain stream.
+        assert_eq!(remapped.len(), plain.len() + 2 + 3);
+    }
+
+    #[test]
+    fn test_remapped_empty() {
+        let mut out = Vec::new();
+        enc_remapped(&mut b"".as_ref(), &mut out).unwrap();
+        assert_eq!(out, [0, 0]);
+
+        let mut recovered = Vec::new();
+        dec_remapped(&mut &out[..], &mut recovered).unwrap();
+        assert!(recovered.is_empty());
+    }
+
+    #[test]
+    fn test_remapped_invalid_table() {
+        let src = [0, 1, b'A', 0, 1];
+        let mut out = Vec::new();
+        let error = dec_remapped(&mut &src[..], &mut out).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_max_key_len() {
+        let data = vec![b'A'; 100_000];
+        let options = LzwOptions {
+            max_key_len: Some(8),
+            ..LzwOptions::default()
+        };
+
+        let mut src = data.as_slice();
+        let mut codes = codes_with(&mut src, &options);
+        let count = codes.by_ref().count();
+        assert!(codes.dict().map().keys().all(|key| key.len() <= 8));
+        // Only `AA`, ..., `AAAAAAAA` are ever inserted.
+        assert_eq!(codes.dict().len(), 256 + 7);
+        // `A`, `AA`, ..., `AAAAAAA` once, then `AAAAAAAA` over and over.
+        assert_eq!(count, 7 + (100_000usize - 28).div_ceil(8));
+
+        let mut encoded = Vec::new();
+        enc_with(&mut data.as_slice(), &mut encoded, &options).unwrap();
+        let mut decoded = Vec::new();
+        dec_with(&mut encoded.as_slice(), &mut decoded, &options).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_max_key_len_roundtrip() {
+        let data = b"ABBABBBABBAAAAAAAAAAAAAABABABABABABBBBBBBBBAB";
+        for max_key_len in [0, 1, 2, 3, 5] {
+            let options = LzwOptions {
+                max_key_len: Some(max_key_len),
+                ..LzwOptions::default()
+            };
+            let mut encoded = Vec::new();
+            enc_with(&mut data.as_ref(), &mut encoded, &options).unwrap();
+            let mut decoded = Vec::new();
+            dec_with(&mut encoded.as_slice(), &mut decoded, &options).unwrap();
+            assert_eq!(decoded, data, "max_key_len = {max_key_len}");
+        }
+    }
+
+    /// A writer which counts its flushes.
+    #[derive(Default)]
+    struct FlushCounter {
+        data: Vec<u8>,
+        flushes: usize,
+    }
+
+    impl io::Write for FlushCounter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.data.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_flush_interval() {
+        // `A`, `B`, `B`, `AB`, `BB`, `ABB`, `A` (7 codes).
+        let data = b"ABBABBBABBA";
+        for variable_width in [false, true] {
+            for (flush_interval, flushes) in [(None, 0), (Some(0), 7), (Some(2), 3), (Some(7), 1)] {
+                let options = LzwOptions {
+                    variable_width,
+                    flush_interval,
+                    ..LzwOptions::default()
+                };
+                let mut out = FlushCounter::default();
+                enc_with(&mut data.as_ref(), &mut out, &options).unwrap();
+                assert_eq!(out.flushes, flushes, "{flush_interval:?}");
+
+                // The encoded data doesn't depend on the interval.
+                let mut expected = Vec::new();
+                let options = LzwOptions {
+                    variable_width,
+                    ..LzwOptions::default()
+                };
+                enc_with(&mut data.as_ref(), &mut expected, &options).unwrap();
+                assert_eq!(out.data, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_code_width() {
+        assert_eq!(code_width(0), 9);
+        assert_eq!(code_width(255), 9);
+        assert_eq!(code_width(256), 10);
+        assert_eq!(code_width(767), 10);
+        assert_eq!(code_width(768), 11);
+        assert_eq!(code_width(32_511), 15);
+        assert_eq!(code_width(32_512), 16);
+        assert_eq!(code_width(65_280), 16);
+        assert_eq!(code_width(usize::MAX), 16);
+    }
+
+    #[test]
+    fn test_max_code_for_input() {
+        let mut state = 0x2545_F491_u32;
+        let random: Vec<u8> = (0..30_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                b"ACGT"[(state % 4) as usize]
+            })
+            .collect();
+        let text = "olá, mundo! como vai? tudo bem? ".repeat(200);
+        let inputs: [&[u8]; 6] = [
+            b"",
+            b"A",
+            b"ABABABA",
+            &[b'A'; 100_000],
+            &random,
+            text.as_bytes(),
+        ];
+        for input in inputs {
+            let mut src = input;
+            let max = codes(&mut src).map(Result::unwrap).max().unwrap_or(0);
+            let mut distinct = [false; 256];
+            input.iter().for_each(|&b| distinct[usize::from(b)] = true);
+            let distinct = distinct.iter().filter(|&&d| d).count();
+
+            let bound = max_code_for_input(input.len() as u64, distinct);
+            assert!(max <= bound, "{max} > {bound} ({} bytes)", input.len());
+        }
+
+        // A single byte repeated parses into `A`, `AA`, `AAA`, and so on.
+        assert!(max_code_for_input(100_000, 1) < 1024);
+        assert_eq!(max_code_for_input(u64::MAX, 256), Code::MAX);
+        assert_eq!(max_code_for_input(u64::MAX, 1), Code::MAX);
+    }
+
+    #[test]
+    fn test_variable_width_boundaries() {
+        // Pseudo-random data, so that it takes many codes.
+        let mut state = 0x2545_F491_u32;
+        let data: Vec<u8> = (0..80_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+
+        // The input consumed by the first `k` codes is `data[..ends[k]]`.
+        let mut src = data.as_slice();
+        let mut iter = codes(&mut src);
+        let all_codes = iter.by_ref().collect::<io::Result<Vec<_>>>().unwrap();
+        let mut key_lens = vec![0; iter.dict().len()];
+        for (key, &code) in iter.dict().map() {
+            key_lens[usize::from(code)] = key.len();
+        }
+        let ends: Vec<usize> = std::iter::once(0)
+            .chain(all_codes.iter().scan(0, |end, &code| {
+                *end += key_lens[usize::from(code)];
+                Some(*end)
+            }))
+            .collect();
+
+        let options = LzwOptions {
+            variable_width: true,
+            ..LzwOptions::default()
+        };
+        // The index of the first code of each width, from 10 to 16 bits.
+        for boundary in [256, 768, 1792, 3840, 7936, 16_128, 32_512] {
+            assert!(all_codes.len() > boundary + 1);
+            for count in [boundary - 1, boundary, boundary + 1, boundary + 2] {
+                let input = &data[..ends[count]];
+                let mut encoded = Vec::new();
+                enc_with(&mut &*input, &mut encoded, &options).unwrap();
+
+                let bits: u32 = (0..count).map(code_width).sum();
+                let len = 1 + bits.div_ceil(8) as usize;
+                assert_eq!(encoded.len(), len, "{count} codes");
+
+                let mut decoded = Vec::new();
+                dec_with(&mut encoded.as_slice(), &mut decoded, &options).unwrap();
+                assert!(decoded == input, "{count} codes");
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_bits() {
+        // Pseudo-random data over a few symbols, which fills 12-bit codes.
+        let mut state = 0x2545_F491_u32;
+        let data: Vec<u8> = (0..50_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                b"ACGT"[(state % 4) as usize]
+            })
+            .collect();
+
+        let mut sizes = Vec::new();
+        for max_bits in [12, 16] {
+            let options = LzwOptions {
+                variable_width: true,
+                max_bits: Some(max_bits),
+                ..LzwOptions::default()
+            };
+            let mut encoded = Vec::new();
+            enc_with(&mut data.as_slice(), &mut encoded, &options).unwrap();
+            assert_eq!(u32::from(encoded[0]), max_bits);
+            sizes.push(encoded.len());
+
+            // The width comes from the stream rather than from the options.
+            let options = LzwOptions {
+                variable_width: true,
+                ..LzwOptions::default()
+            };
+            let mut decoded = Vec::new();
+            dec_with(&mut encoded.as_slice(), &mut decoded, &options).unwrap();
+            assert!(decoded == data, "max_bits = {max_bits}");
+        }
+        assert_ne!(sizes[0], sizes[1]);
+    }
+
+    #[test]
+    fn test_max_bits_bounds() {
+        let data = b"ABBABBBABBAAAAAAAAAAAAAABABABABABABBBBBBBBBAB";
+        for (max_bits, header) in [(None, 16), (Some(0), 9), (Some(9), 9), (Some(17), 16)] {
+            let options = LzwOptions {
+                variable_width: true,
+                max_bits,
+                ..LzwOptions::default()
+            };
+            let mut encoded = Vec::new();
+            enc_with(&mut data.as_ref(), &mut encoded, &options).unwrap();
+            assert_eq!(encoded[0], header);
+            let mut decoded = Vec::new();
+            dec_with(&mut encoded.as_slice(), &mut decoded, &options).unwrap();
+            assert_eq!(decoded, data);
+        }
+
+        let options = LzwOptions {
+            variable_width: true,
+            ..LzwOptions::default()
+        };
+        for src in [&[][..], &[8, 0x41], &[17, 0x41]] {
+            let error = dec_with(&mut &*src, &mut Vec::new(), &options).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{src:?}");
+        }
+    }
+
+    #[test]
+    fn test_variable_width_kwkwk() {
+        // `ABABABA` emits 258 right after assigning it (see `test_basic_seq_3`).
+        let options = LzwOptions {
+            variable_width: true,
+            ..LzwOptions::default()
+        };
+        let mut encoded = Vec::new();
+        enc_with(&mut b"ABABABA".as_ref(), &mut encoded, &options).unwrap();
+        // The max bits, then four 9-bit codes, padded to 5 bytes.
+        assert_eq!(encoded.len(), 1 + 5);
+
+        let mut decoded = Vec::new();
+        dec_with(&mut encoded.as_slice(), &mut decoded, &options).unwrap();
+        assert_eq!(decoded, b"ABABABA");
+    }
+
+    #[test]
+    fn test_decode_iter() {
+        let data = b"ABBABBBABBA";
+        let encoded = coded(&[65, 66, 66, 256, 257, 259, 65]);
+
+        let mut src = encoded.as_slice();
+        let decoded = decode_iter(&mut src)
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(decoded, data);
+
+        for n in 0..=data.len() {
+            let mut src = encoded.as_slice();
+            let prefix = decode_iter(&mut src)
+                .take(n)
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+            assert_eq!(prefix, data[..n]);
+        }
+
+        // `ABB` takes the first three codes, and nothing else is read.
+        let mut src = encoded.as_slice();
+        let mut iter = decode_iter(&mut src);
+        iter.by_ref().take(3).for_each(drop);
+        drop(iter);
+        assert_eq!(src.len(), encoded.len() - 3 * 2);
+    }
+
+    #[test]
+    fn test_decode_iter_invalid() {
+        let src = coded(&[65, 300]);
+        let mut src = src.as_slice();
+        let mut iter = decode_iter(&mut src);
+        assert_eq!(iter.next().unwrap().unwrap(), b'A');
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_enc_into_reused_buffer() {
+        let messages: Vec<Vec<u8>> = (0..50u8)
+            .map(|i| format!("message {i}: {}", "ab".repeat(usize::from(i))).into_bytes())
+            .collect();
+        let mut encoded = Vec::new();
+        let mut decoded = Vec::new();
+        for message in &messages {
+            encoded.clear();
+            enc_into(message, &mut encoded).unwrap();
+            let mut fresh = Vec::new();
+            enc(&mut message.as_slice(), &mut fresh).unwrap();
+            assert_eq!(encoded, fresh);
+
+            decoded.clear();
+            dec_into(&encoded, &mut decoded).unwrap();
+            assert_eq!(&decoded, message);
+        }
+
+        // The output is appended.
+        let mut out = b"head".to_vec();
+        enc_into(b"ABBABBBABBA", &mut out).unwrap();
+        assert_eq!(out[..4], *b"head");
+        assert_eq!(out[4..], coded(&[65, 66, 66, 256, 257, 259, 65]));
+    }
+
+    #[test]
+    fn test_dec_best_effort() {
+        let data = b"TOBEORNOTTOBEORTOBEORNOT".repeat(20);
+        let mut encoded = Vec::new();
+        enc(&mut data.as_slice(), &mut encoded).unwrap();
+
+        let mut out = Vec::new();
+        let report = dec_best_effort(&mut encoded.as_slice(), &mut out).unwrap();
+        assert_eq!(
+            report,
+            RecoveryReport {
+                recovered: data.len() as u64,
+                complete: true
+            }
+        );
+        assert_eq!(out, data);
+
+        // Cut off in the middle of a code, which strict decoding rejects.
+        let truncated = &encoded[..encoded.len() / 2 + 1];
+        assert_eq!(truncated.len() % 2, 1);
+        let error = dec(&mut &*truncated, &mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut out = Vec::new();
+        let report = dec_best_effort(&mut &*truncated, &mut out).unwrap();
+        assert!(!report.complete);
+        assert_eq!(report.recovered, out.len() as u64);
+        assert!(out.len() > data.len() / 4);
+        assert!(data.starts_with(&out));
+    }
+
+    #[test]
+    fn test_dec_best_effort_invalid_code() {
+        let mut encoded = coded(&[65, 66, 256]);
+        encoded.extend(coded(&[1000, 65]));
+        let mut out = Vec::new();
+        let report = dec_best_effort(&mut encoded.as_slice(), &mut out).unwrap();
+        assert_eq!(
+            report,
+            RecoveryReport {
+                recovered: 4,
+                complete: false
+            }
+        );
+        assert_eq!(out, b"ABAB");
+    }
+
+    #[test]
+    fn test_decompress_range() {
+        let data = "olá, mundo! como vai? tudo bem? ".repeat(100);
+        let data = data.as_bytes();
+        let mut encoded = Vec::new();
+        enc(&mut &*data, &mut encoded).unwrap();
+
+        let total = data.len() as u64;
+        for (start, len) in [
+            (0, 0),
+            (0, 1),
+            (0, total),
+            (7, 13),
+            (1000, 555),
+            (3190, 100),
+        ] {
+            let range = decompress_range(&mut encoded.as_slice(), start, len).unwrap();
+            let from = (start as usize).min(data.len());
+            let to = ((start + len) as usize).min(data.len());
+            assert_eq!(range, data[from..to], "{start}..+{len}");
+        }
+        let range = decompress_range(&mut encoded.as_slice(), total + 1, u64::MAX).unwrap();
+        assert!(range.is_empty());
+    }
+
+    #[test]
+    fn test_decompress_range_stops_early() {
+        let mut encoded = Vec::new();
+        enc(&mut b"ABBABBBABBA".as_ref(), &mut encoded).unwrap();
+        // The trailing garbage is never read.
+        encoded.extend_from_slice(&[0xFF, 0xFF, 0xFF]);
+        let range = decompress_range(&mut encoded.as_slice(), 2, 5).unwrap();
+        assert_eq!(range, b"BABBB");
+
+        let error = decompress_range(&mut encoded.as_slice(), 2, 100).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_dict_stats() {
+        let data = b"ABABABA";
+        let dict = enc_returning_dict(&mut &data[..], &mut Vec::new(), &LzwOptions::default());
+        let stats = dict_stats(&mut &data[..]).unwrap();
+        assert_eq!(stats.final_size, dict.unwrap().len());
+        // `A`, `B`, `AB` and `ABA` add `AB`, `BA` and `ABA`.
+        assert_eq!(stats.final_size, 259);
+        assert_eq!(stats.longest_key_len, 3);
+        assert_eq!(stats.avg_key_len, (256.0 + 2.0 + 2.0 + 3.0) / 259.0);
+        assert_eq!(stats.codes_per_bracket, [4]);
+
+        // The full brackets of 256 and 512 codes, and a partial one.
+        let data: Vec<u8> = (0..=u8::MAX).flat_map(|a| [a, a.wrapping_mul(7)]).collect();
+        let stats = dict_stats(&mut data.repeat(4).as_slice()).unwrap();
+        assert_eq!(stats.codes_per_bracket[..2], [256, 512]);
+        assert_eq!(stats.codes_per_bracket.len(), 3);
+        let codes: u64 = stats.codes_per_bracket.iter().sum();
+        assert_eq!(stats.final_size, 256 + codes as usize - 1);
+
+        let stats = dict_stats(&mut [].as_ref()).unwrap();
+        assert_eq!(stats.final_size, 256);
+        assert!(stats.codes_per_bracket.is_empty());
+    }
+
+    #[test]
+    fn test_dict_sizes_in_sync() {
+        let mut state = 0x2545_F491_u32;
+        let random: Vec<u8> = (0..5_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state % 4) as u8
+            })
+            .collect();
+        // `AAA` is `A` and then the code assigned right after it, which is
+        // only right if the first code doesn't get an entry in the decoder.
+        let inputs: [&[u8]; 4] = [b"AAA", b"ABABABA", b"ABBABBBABBA", &random];
+
+        for max_key_len in [None, Some(3)] {
+            let options = LzwOptions {
+                max_key_len,
+                ..LzwOptions::default()
+            };
+            for input in inputs {
+                // The encoder's dictionary size after emitting each code.
+                let mut src = input;
+                let mut codes = codes_with(&mut src, &options);
+                let mut enc_lens = Vec::new();
+                let mut encoded = Vec::new();
+                while let Some(code) = codes.next_code().unwrap() {
+                    enc_lens.push(codes.dict().len());
+                    encoded.extend(code.to_be_bytes());
+                }
+
+                // The decoder is always one code behind.
+                let mut src = encoded.as_slice();
+                let mut decoder = Decoder::new(&mut src, &options, None);
+                let mut prev_len = 256;
+                for &enc_len in &enc_lens {
+                    decoder.next_seq().unwrap().unwrap();
+                    assert_eq!(decoder.dict.len(), prev_len);
+                    prev_len = enc_len;
+                }
+                assert!(decoder.next_seq().unwrap().is_none());
+            }
+        }
+
+        assert_eq!(
+            decompress(&coded(&[65, 256])),
+            b"AAA",
+            "the first code must not get an entry"
+        );
+    }
+
+    fn decompress(src: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        dec(&mut &*src, &mut out).unwrap();
+        out
+    }
+
     fn coded(codes: &[Code]) -> Vec<u8> {
         let mut out = Vec::new();
         for code in codes {
@@ -0,0 +1,230 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+    io,
+};
+
+use super::{Code, DecDict, DictHasher, EncDict};
+use crate::CompressError;
+
+/// The number of distinct codes, i.e., the maximum size of the dictionaries.
+const CAPACITY: usize = Code::MAX as usize + 1;
+
+/// The number of entries the maps are allocated for at first, which small
+/// messages (of up to several hundred bytes) don't outgrow.
+const INITIAL_CAPACITY: usize = 1024;
+
+/// An LZW dictionary, which assigns consecutive codes to sequences.
+///
+/// It starts with the 256 single-byte sequences (where byte `i` has code `i`),
+/// and every inserted sequence gets the [next code](Dictionary::next_code).
+/// The map `M` is either an [`EncDict`] (from sequences to codes, for the
+/// encoder) or a [`DecDict`] (from codes to sequences, for the decoder).
+#[derive(Clone, Debug)]
+pub struct Dictionary<M> {
+    map: M,
+    len: usize,
+}
+
+impl<M> Dictionary<M> {
+    /// Returns the number of entries, which is also the next code.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the dictionary is empty, which it never is.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the code the next inserted sequence gets, or `None` if every
+    /// code is already assigned.
+    pub fn next_code(&self) -> Option<Code> {
+        Code::try_from(self.len).ok()
+    }
+
+    /// Returns the width, in bits, needed to write any code assigned so far, as
+    /// well as the next one.
+    pub fn width(&self) -> u32 {
+        usize::BITS - self.len.leading_zeros()
+    }
+
+    /// Returns the underlying map.
+    pub fn map(&self) -> &M {
+        &self.map
+    }
+
+    /// Consumes the dictionary, returning the underlying map.
+    pub fn into_map(self) -> M {
+        self.map
+    }
+
+    /// Assigns the next code, failing if there is none left.
+    fn assign(&mut self) -> io::Result<Code> {
+        let code = self.next_code().ok_or(CompressError::DictionaryOverflow)?;
+        self.len += 1;
+        Ok(code)
+    }
+}
+
+/// Makes room in `map` for every code that may be assigned, once its initial
+/// capacity is used up: small messages don't pay for the full table, and large
+/// inputs rehash once rather than at every doubling.
+fn grow<K: Eq + Hash, V, S: BuildHasher>(map: &mut HashMap<K, V, S>) {
+    if map.len() == map.capacity() {
+        map.reserve(CAPACITY - map.len());
+    }
+}
+
+impl Dictionary<EncDict> {
+    /// Constructs a new encoder dictionary with the single-byte sequences.
+    pub fn new() -> Self {
+        let mut map = HashMap::with_capacity_and_hasher(INITIAL_CAPACITY, DictHasher::default());
+        for i in u8::MIN..=u8::MAX {
+            map.insert(vec![i], i.into());
+        }
+        Self { map, len: 256 }
+    }
+
+    /// Returns whether the given sequence has a code.
+    pub fn contains(&self, seq: &[u8]) -> bool {
+        self.map.contains_key(seq)
+    }
+
+    /// Returns the code of the given sequence.
+    pub fn get(&self, seq: &[u8]) -> Option<Code> {
+        self.map.get(seq).copied()
+    }
+
+    /// Inserts a sequence which doesn't have a code yet, returning its code.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`CompressError::DictionaryOverflow`] if every code is
+    /// already assigned.
+    pub fn insert(&mut self, seq: Vec<u8>) -> io::Result<Code> {
+        debug_assert!(!self.contains(&seq));
+        let code = self.assign()?;
+        grow(&mut self.map);
+        self.map.insert(seq, code);
+        Ok(code)
+    }
+}
+
+impl Default for Dictionary<EncDict> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dictionary<DecDict> {
+    /// Constructs a new decoder dictionary with the single-byte sequences.
+    pub fn new() -> Self {
+        let mut map = HashMap::with_capacity(INITIAL_CAPACITY);
+        for i in u8::MIN..=u8::MAX {
+            map.insert(i.into(), vec![i]);
+        }
+        Self { map, len: 256 }
+    }
+
+    /// Returns whether the given code is assigned.
+    pub fn contains(&self, code: Code) -> bool {
+        usize::from(code) < self.len
+    }
+
+    /// Returns the sequence of the given code.
+    pub fn get(&self, code: Code) -> Option<&[u8]> {
+        self.map.get(&code).map(Vec::as_slice)
+    }
+
+    /// Inserts a sequence, returning its (newly assigned) code.
+    ///
+    /// # Errors
+    ///
+    /// Fails with [`CompressError::DictionaryOverflow`] if every code is
+    /// already assigned.
+    pub fn insert(&mut self, seq: Vec<u8>) -> io::Result<Code> {
+        let code = self.assign()?;
+        grow(&mut self.map);
+        self.map.insert(code, seq);
+        Ok(code)
+    }
+}
+
+impl Default for Dictionary<DecDict> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enc_dictionary() {
+        let mut dict = Dictionary::<EncDict>::new();
+        assert_eq!(dict.len(), 256);
+        assert_eq!(dict.next_code(), Some(256));
+        assert_eq!(dict.width(), 9);
+        assert_eq!(dict.get(b"A"), Some(65));
+        assert!(!dict.contains(b"AB"));
+
+        assert_eq!(dict.insert(b"AB".to_vec()).unwrap(), 256);
+        assert_eq!(dict.get(b"AB"), Some(256));
+        assert_eq!(dict.len(), 257);
+        assert_eq!(dict.next_code(), Some(257));
+    }
+
+    #[test]
+    fn test_grow() {
+        let mut dict = Dictionary::<EncDict>::new();
+        assert!(dict.map().capacity() < CAPACITY);
+        let mut i = 0u32;
+        while dict.map().len() < dict.map().capacity() {
+            dict.insert(i.to_be_bytes().to_vec()).unwrap();
+            i += 1;
+        }
+        dict.insert(i.to_be_bytes().to_vec()).unwrap();
+        // Straight to every code, instead of doubling.
+        assert!(dict.map().capacity() >= CAPACITY);
+    }
+
+    #[test]
+    fn test_dec_dictionary() {
+        let mut dict = Dictionary::<DecDict>::new();
+        assert_eq!(dict.get(65), Some(&b"A"[..]));
+        assert!(!dict.contains(256));
+
+        assert_eq!(dict.insert(b"AB".to_vec()).unwrap(), 256);
+        assert!(dict.contains(256));
+        assert_eq!(dict.get(256), Some(&b"AB"[..]));
+        assert_eq!(dict.get(257), None);
+    }
+
+    #[test]
+    fn test_width() {
+        let mut dict = Dictionary::<DecDict>::new();
+        for _ in 256..511 {
+            dict.insert(Vec::new()).unwrap();
+        }
+        assert_eq!((dict.len(), dict.width()), (511, 9));
+        dict.insert(Vec::new()).unwrap();
+        assert_eq!((dict.len(), dict.width()), (512, 10));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut dict = Dictionary::<DecDict>::new();
+        for _ in 256..CAPACITY {
+            dict.insert(Vec::new()).unwrap();
+        }
+        assert_eq!(dict.next_code(), None);
+        let error = dict.insert(Vec::new()).unwrap_err();
+        assert!(matches!(
+            CompressError::from(error),
+            CompressError::DictionaryOverflow
+        ));
+        assert_eq!(dict.len(), CAPACITY);
+    }
+}
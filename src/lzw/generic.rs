@@ -0,0 +1,304 @@
+//! LZW over any symbol type, e.g., the `u16` word IDs of a tokenizer.
+//!
+//! The dictionary starts with an alphabet of single symbols (where the `i`-th
+//! symbol has code `i`) instead of the 256 bytes, and codes are `u32`s, since
+//! an alphabet may already take every `u16`. The byte codec ([`lzw::enc`] and
+//! [`lzw::dec`]) stays the specialization for `u8`, with its own options and
+//! stream format: over the alphabet `0..=255`, [`codes`] emits the same codes
+//! as [`lzw::codes`].
+//!
+//! A serialized stream ([`enc`]) holds the alphabet (its `u32` length, then
+//! its symbols in their [`Symbol`] encoding), the `u64` number of codes, and
+//! then the codes, most significant bit first, each as wide as the dictionary
+//! requires (see [`code_width`]).
+//!
+//! [`lzw::enc`]: super::enc
+//! [`lzw::dec`]: super::dec
+//! [`lzw::codes`]: super::codes
+
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    io, mem,
+};
+
+use crate::{
+    shared::{len_u64, read_u16, read_u32, read_u64, read_u8, BitReader, BitWriter},
+    CompressError,
+};
+
+/// A code of the generic codec.
+pub type Code = u32;
+
+/// A symbol which may be serialized with a fixed size.
+pub trait Symbol: Hash + Eq + Clone {
+    /// Writes the symbol.
+    fn write_to(&self, out: &mut dyn io::Write) -> io::Result<()>;
+
+    /// Reads a symbol, returning `None` at the end of the stream.
+    fn read_from(src: &mut dyn io::Read) -> io::Result<Option<Self>>;
+}
+
+macro_rules! impl_symbol {
+    ($($ty:ty => $read:ident,)+) => {
+        $(
+            impl Symbol for $ty {
+                fn write_to(&self, out: &mut dyn io::Write) -> io::Result<()> {
+                    out.write_all(&self.to_be_bytes())
+                }
+
+                fn read_from(src: &mut dyn io::Read) -> io::Result<Option<Self>> {
+                    $read(src)
+                }
+            }
+        )+
+    };
+}
+
+impl_symbol!(
+    u8 => read_u8,
+    u16 => read_u16,
+    u32 => read_u32,
+    u64 => read_u64,
+);
+
+/// Returns the LZW codes of the given symbols, over the given alphabet.
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::InvalidInput`] if the alphabet repeats a symbol
+/// or lacks one of the given symbols, or with
+/// [`CompressError::DictionaryOverflow`] if the dictionary grows past `u32`'s
+/// bounds.
+pub fn codes<S: Hash + Eq + Clone>(alphabet: &[S], symbols: &[S]) -> io::Result<Vec<Code>> {
+    let mut dict = HashMap::with_capacity(alphabet.len());
+    for (code, symbol) in alphabet.iter().enumerate() {
+        let code = Code::try_from(code).map_err(|_| CompressError::DictionaryOverflow)?;
+        if dict.insert(vec![symbol.clone()], code).is_some() {
+            return Err(invalid_input("the alphabet repeats a symbol"));
+        }
+    }
+
+    let mut out = Vec::new();
+    let mut seq = Vec::new();
+    for symbol in symbols {
+        if !dict.contains_key(std::slice::from_ref(symbol)) {
+            return Err(invalid_input("symbol outside of the alphabet"));
+        }
+        seq.push(symbol.clone());
+        if !dict.contains_key(&seq) {
+            out.push(dict[&seq[..seq.len() - 1]]);
+            let code = Code::try_from(dict.len()).map_err(|_| CompressError::DictionaryOverflow)?;
+            let seq = mem::replace(&mut seq, vec![symbol.clone()]);
+            dict.insert(seq, code);
+        }
+    }
+    if !seq.is_empty() {
+        out.push(dict[&seq]);
+    }
+    Ok(out)
+}
+
+/// Returns the symbols which the given codes (see [`codes`]) stand for.
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::InvalidData`] on a code the encoder couldn't
+/// have emitted.
+pub fn decode_codes<S: Clone>(alphabet: &[S], codes: &[Code]) -> io::Result<Vec<S>> {
+    let mut dict: Vec<Vec<S>> = alphabet.iter().map(|s| vec![s.clone()]).collect();
+    let mut out = Vec::new();
+    let mut prev: Option<usize> = None;
+    for &code in codes {
+        // A code past `usize`'s bounds is neither in the dictionary nor its
+        // length, hence invalid as well.
+        let index = usize::try_from(code).unwrap_or(usize::MAX);
+        let decoded = match (dict.get(index), prev) {
+            (Some(decoded), _) => decoded.clone(),
+            // The code the encoder has just assigned: it must be the previous
+            // sequence plus its own first symbol.
+            (None, Some(prev)) if index == dict.len() => {
+                let mut s = dict[prev].clone();
+                s.push(s[0].clone());
+                s
+            }
+            (None, _) => {
+                let message = format!("invalid code {code}");
+                return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+            }
+        };
+        if let Some(prev) = prev {
+            let mut s = dict[prev].clone();
+            s.push(decoded[0].clone());
+            dict.push(s);
+        }
+        out.extend_from_slice(&decoded);
+        prev = Some(index);
+    }
+    Ok(out)
+}
+
+/// Returns the width, in bits, of the `index`-th code (starting at 0) of a
+/// stream over an alphabet of `alphabet_len` symbols: the width of the
+/// encoder's dictionary size when it emits the code.
+pub fn code_width(alphabet_len: usize, index: usize) -> u32 {
+    let size = alphabet_len.saturating_add(index);
+    (usize::BITS - size.leading_zeros()).min(Code::BITS)
+}
+
+/// Encodes the given symbols, over the alphabet of the ones in use (in the
+/// order of their first occurrence).
+///
+/// # Errors
+///
+/// Fails if writing to `out` fails, or if the dictionary overflows (see
+/// [`codes`]).
+pub fn enc<S: Symbol>(symbols: &[S], out: &mut dyn io::Write) -> io::Result<()> {
+    let mut seen = HashSet::new();
+    let alphabet: Vec<S> = symbols
+        .iter()
+        .filter(|&symbol| seen.insert(symbol))
+        .cloned()
+        .collect();
+    let codes = codes(&alphabet, symbols)?;
+
+    let alphabet_len =
+        u32::try_from(alphabet.len()).map_err(|_| CompressError::DictionaryOverflow)?;
+    out.write_all(&alphabet_len.to_be_bytes())?;
+    for symbol in &alphabet {
+        symbol.write_to(out)?;
+    }
+    out.write_all(&len_u64(codes.len()).to_be_bytes())?;
+    let mut bits = BitWriter::new(out);
+    for (index, &code) in codes.iter().enumerate() {
+        bits.write_bits(code, code_width(alphabet.len(), index))?;
+    }
+    bits.finish()
+}
+
+/// Decodes symbols encoded by [`enc`].
+///
+/// # Errors
+///
+/// Fails if reading from `src` fails, with [`CompressError::BadHeader`] if the
+/// stream ends early, or with [`io::ErrorKind::InvalidData`] on an invalid
+/// code.
+pub fn dec<S: Symbol>(src: &mut dyn io::Read) -> io::Result<Vec<S>> {
+    let truncated = || CompressError::BadHeader("truncated generic LZW stream");
+    let alphabet_len = read_u32(src)?.ok_or_else(truncated)?;
+    let alphabet = (0..alphabet_len)
+        .map(|_| S::read_from(src)?.ok_or_else(|| truncated().into()))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let count = read_u64(src)?.ok_or_else(truncated)?;
+    // More codes than `usize` counts couldn't have been encoded here.
+    let count = usize::try_from(count)
+        .map_err(|_| CompressError::BadHeader("too many generic LZW codes"))?;
+    let mut bits = BitReader::new(src);
+    let mut codes = Vec::new();
+    for index in 0..count {
+        let width = code_width(alphabet.len(), index);
+        codes.push(bits.read_bits(width)?.ok_or_else(truncated)?);
+    }
+    decode_codes(&alphabet, &codes)
+}
+
+fn invalid_input(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pseudo-random token IDs, repeating a few phrases.
+    fn tokens() -> Vec<u16> {
+        let phrases: [&[u16]; 4] = [
+            &[1000, 42, 7, 65_535],
+            &[42, 7, 9000],
+            &[3, 1000, 1000, 42],
+            &[65_535, 0],
+        ];
+        let mut state = 0x2545_F491_u32;
+        (0..500)
+            .flat_map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                phrases[(state % 4) as usize].iter().copied()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_u16_roundtrip() {
+        let tokens = tokens();
+        let mut encoded = Vec::new();
+        enc(&tokens, &mut encoded).unwrap();
+        // Far below the 2 bytes of each token.
+        assert!(encoded.len() < tokens.len() / 2, "{}", encoded.len());
+
+        let decoded: Vec<u16> = dec(&mut encoded.as_slice()).unwrap();
+        assert_eq!(decoded, tokens);
+    }
+
+    #[test]
+    fn test_bytes_match_byte_codec() {
+        let data = b"ABBABBBABBAAAAAAAAAAAAAABABABABABABBBBBBBBBAB";
+        let alphabet: Vec<u8> = (u8::MIN..=u8::MAX).collect();
+        let expected = super::super::codes(&mut data.as_ref())
+            .map(|code| code.map(Code::from))
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        let generic = codes(&alphabet, data).unwrap();
+        assert_eq!(generic, expected);
+        assert_eq!(decode_codes(&alphabet, &generic).unwrap(), data);
+    }
+
+    #[test]
+    fn test_kwkwk() {
+        // `ABABABA` emits the code assigned right before it.
+        let alphabet = ['A', 'B'];
+        let symbols: Vec<char> = "ABABABA".chars().collect();
+        let codes = codes(&alphabet, &symbols).unwrap();
+        assert_eq!(codes, [0, 1, 2, 4]);
+        assert_eq!(decode_codes(&alphabet, &codes).unwrap(), symbols);
+    }
+
+    #[test]
+    fn test_empty_and_single() {
+        for symbols in [&[][..], &[7u32], &[7, 7, 7, 7, 7]] {
+            let mut encoded = Vec::new();
+            enc(symbols, &mut encoded).unwrap();
+            let decoded: Vec<u32> = dec(&mut encoded.as_slice()).unwrap();
+            assert_eq!(decoded, symbols);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        let error = codes(&[1, 2], &[1, 3]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        let error = codes(&[1, 1], &[1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let error = decode_codes(&[1, 2], &[3]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut encoded = Vec::new();
+        enc(&tokens(), &mut encoded).unwrap();
+        encoded.truncate(encoded.len() - 2);
+        let error = dec::<u16>(&mut encoded.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // Codes past any dictionary, and counts past any stream, are errors
+        // rather than panics.
+        let error = decode_codes(&[1, 2], &[0, Code::MAX]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let mut encoded = 1u32.to_be_bytes().to_vec();
+        encoded.push(7);
+        encoded.extend(u64::MAX.to_be_bytes());
+        let error = dec::<u8>(&mut encoded.as_slice()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
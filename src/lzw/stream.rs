@@ -0,0 +1,224 @@
+use std::{io, thread};
+
+use super::{EncState, LzwOptions};
+use crate::shared::write_all_retry;
+
+/// An encoder which is pushed the data (rather than pulling it from a reader),
+/// in the same format as [`enc`](super::enc), for non-blocking writers.
+///
+/// The codes are buffered until the writer takes them, so that a write which
+/// fails with [`io::ErrorKind::WouldBlock`] loses nothing: the input has been
+/// encoded already, and [`flush`](Self::flush) (or the next write) retries with
+/// the buffered bytes.
+///
+/// The last code is only written by [`finish`](Self::finish), hence dropping an
+/// unfinished encoder loses data: in debug builds, it panics (call
+/// [`into_inner`](Self::into_inner) to give up on the data instead).
+pub struct StreamEncoder<W> {
+    out: W,
+    state: EncState,
+    /// The encoded bytes which `out` didn't take yet.
+    pending: Vec<u8>,
+    guard: FinishGuard,
+}
+
+/// Checks that a [`StreamEncoder`] is finished (or given up on) before it's
+/// dropped, in debug builds.
+struct FinishGuard {
+    finished: bool,
+}
+
+impl Drop for FinishGuard {
+    fn drop(&mut self) {
+        // Not while unwinding, which would abort.
+        debug_assert!(
+            self.finished || thread::panicking(),
+            "a `StreamEncoder` was dropped without `finish`, losing its last code"
+        );
+    }
+}
+
+impl<W: io::Write> StreamEncoder<W> {
+    /// Constructs a new [`StreamEncoder`], with the default options.
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            state: EncState::new(&LzwOptions::default()),
+            pending: Vec::new(),
+            guard: FinishGuard { finished: false },
+        }
+    }
+
+    /// Encodes the given bytes, and writes as many codes as the writer takes.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing fails, in which case `input` was still encoded: the
+    /// error is retryable (e.g., [`io::ErrorKind::WouldBlock`]) by calling
+    /// [`flush`](Self::flush), not by writing `input` again. Fails as well if
+    /// the dictionary overflows (see [`enc`](super::enc)), or with
+    /// [`io::ErrorKind::InvalidInput`] once finished.
+    pub fn write_bytes(&mut self, input: &[u8]) -> io::Result<()> {
+        if self.guard.finished {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the LZW encoder is finished",
+            ));
+        }
+        for &c in input {
+            if let Some(code) = self.state.push(c)? {
+                self.pending.extend(code.to_be_bytes());
+            }
+        }
+        self.drain()
+    }
+
+    /// Writes the buffered codes, and flushes the writer.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing or flushing fails, and may be retried.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.drain()?;
+        self.out.flush()
+    }
+
+    /// Ends the data, writing its last code, and flushes the writer. No more
+    /// bytes may be written afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as [`flush`](Self::flush), and may be
+    /// retried (by calling it again).
+    #[must_use = "the pending codes may not be written"]
+    pub fn finish(&mut self) -> io::Result<()> {
+        if !self.guard.finished {
+            if let Some(code) = self.state.finish() {
+                self.pending.extend(code.to_be_bytes());
+            }
+            self.guard.finished = true;
+        }
+        self.flush()
+    }
+
+    /// Returns the number of encoded bytes which the writer didn't take yet.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns a reference to the writer.
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    /// Returns the writer, dropping any pending bytes (even if unfinished).
+    pub fn into_inner(mut self) -> W {
+        self.guard.finished = true;
+        self.out
+    }
+
+    /// Writes as many pending bytes as the writer takes.
+    fn drain(&mut self) -> io::Result<()> {
+        let mut left = self.pending.as_slice();
+        let result = write_all_retry(&mut self.out, &mut left);
+        let written = self.pending.len() - left.len();
+        self.pending.drain(..written);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lzw;
+
+    /// A writer which fails with `WouldBlock` on every other write, and takes
+    /// at most 3 bytes otherwise.
+    #[derive(Default)]
+    struct NonBlocking {
+        data: Vec<u8>,
+        calls: usize,
+    }
+
+    impl io::Write for NonBlocking {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls % 2 == 1 {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            let n = buf.len().min(3);
+            self.data.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Retries `op` while it would block.
+    fn retry(mut op: impl FnMut() -> io::Result<()>) {
+        loop {
+            match op() {
+                Ok(()) => return,
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {}
+                Err(error) => panic!("{error}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_would_block() {
+        let data = b"TOBEORNOTTOBEORTOBEORNOT".repeat(50);
+        let mut encoder = StreamEncoder::new(NonBlocking::default());
+        let mut blocked = 0;
+        for chunk in data.chunks(7) {
+            if let Err(error) = encoder.write_bytes(chunk) {
+                assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+                blocked += 1;
+                retry(|| encoder.flush());
+            }
+            assert_eq!(encoder.pending(), 0);
+        }
+        retry(|| encoder.finish());
+        assert!(blocked > 0);
+
+        let mut expected = Vec::new();
+        lzw::enc(&mut data.as_slice(), &mut expected).unwrap();
+        assert_eq!(encoder.into_inner().data, expected);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_drop_unfinished() {
+        let result = std::panic::catch_unwind(|| {
+            let mut encoder = StreamEncoder::new(Vec::new());
+            encoder.write_bytes(b"ABC").unwrap();
+        });
+        let payload = result.unwrap_err();
+        let message = payload.downcast_ref::<&str>().unwrap();
+        assert!(message.contains("without `finish`"), "{message}");
+
+        // Finished, or given up on.
+        let mut encoder = StreamEncoder::new(Vec::new());
+        encoder.write_bytes(b"ABC").unwrap();
+        encoder.finish().unwrap();
+        let mut encoder = StreamEncoder::new(Vec::new());
+        encoder.write_bytes(b"ABC").unwrap();
+        assert_eq!(encoder.into_inner(), [0, 65, 0, 66]);
+    }
+
+    #[test]
+    fn test_blocked_bytes_kept() {
+        let mut encoder = StreamEncoder::new(NonBlocking::default());
+        // `ABC` emits the codes of `A` and `B`, which the writer refuses.
+        let error = encoder.write_bytes(b"ABC").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(encoder.pending(), 4);
+        retry(|| encoder.finish());
+        assert_eq!(encoder.get_ref().data, [0, 65, 0, 66, 0, 67]);
+
+        let error = encoder.write_bytes(b"D").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
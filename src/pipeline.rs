@@ -0,0 +1,138 @@
+//! Chaining of codecs.
+
+use std::io;
+
+use crate::Codec;
+
+/// A codec which runs several codecs in sequence: encoding runs each stage on
+/// the output of the previous one, and decoding runs them in reverse.
+///
+/// Each intermediate result is buffered in memory.
+pub struct Pipeline {
+    stages: Vec<Box<dyn Codec>>,
+}
+
+impl Pipeline {
+    /// Constructs a new pipeline with the given stages, from the first one to
+    /// encode with.
+    pub fn new(stages: Vec<Box<dyn Codec>>) -> Self {
+        Self { stages }
+    }
+}
+
+impl Codec for Pipeline {
+    fn enc(&self, src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+        run(self.stages.iter(), src, out, |stage, src, out| {
+            stage.enc(src, out)
+        })
+    }
+
+    fn dec(&self, src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+        run(self.stages.iter().rev(), src, out, |stage, src, out| {
+            stage.dec(src, out)
+        })
+    }
+}
+
+/// Runs `f` with each stage, feeding it the previous stage's output, except
+/// for the first stage (which reads from `src`) and the last one (which writes
+/// to `out`).
+fn run<'a, F>(
+    stages: impl ExactSizeIterator<Item = &'a Box<dyn Codec>>,
+    src: &mut dyn io::Read,
+    out: &mut dyn io::Write,
+    f: F,
+) -> io::Result<()>
+where
+    F: Fn(&dyn Codec, &mut dyn io::Read, &mut dyn io::Write) -> io::Result<()>,
+{
+    let last = stages.len().saturating_sub(1);
+    let mut buf: Option<Vec<u8>> = None;
+    for (i, stage) in stages.enumerate() {
+        let mut input = buf.as_deref();
+        let src: &mut dyn io::Read = match &mut input {
+            Some(input) => input,
+            None => &mut *src,
+        };
+        if i == last {
+            return f(stage.as_ref(), src, out);
+        }
+        let mut next = Vec::new();
+        f(stage.as_ref(), src, &mut next)?;
+        buf = Some(next);
+    }
+    // No stages: the identity.
+    io::copy(src, out).map(drop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Algorithm;
+
+    fn enc(codec: &dyn Codec, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        codec.enc(&mut &*data, &mut out).unwrap();
+        out
+    }
+
+    fn dec(codec: &dyn Codec, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        codec.dec(&mut &*data, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let data = "olá, mundo! como vai? ".repeat(100).into_bytes();
+        for stages in [
+            vec![],
+            vec![Algorithm::Huffman],
+            vec![Algorithm::Lzw, Algorithm::Huffman],
+            vec![Algorithm::Stored, Algorithm::Lzw, Algorithm::Huffman],
+        ] {
+            let pipeline = Pipeline::new(
+                stages
+                    .iter()
+                    .map(|&algorithm| Box::new(algorithm) as Box<dyn Codec>)
+                    .collect(),
+            );
+            let encoded = enc(&pipeline, &data);
+            assert_eq!(dec(&pipeline, &encoded), data, "{stages:?}");
+        }
+    }
+
+    #[test]
+    fn test_order() {
+        // The stages must be undone in reverse, or the Huffman header would be
+        // read as LZW codes.
+        let data = b"ABBABBBABBA".repeat(50);
+        let pipeline = Pipeline::new(vec![Box::new(Algorithm::Lzw), Box::new(Algorithm::Huffman)]);
+        let encoded = enc(&pipeline, &data);
+
+        let lzw = enc(&Algorithm::Lzw, &data);
+        assert_eq!(encoded, enc(&Algorithm::Huffman, &lzw));
+        assert_eq!(dec(&pipeline, &encoded), data);
+    }
+
+    #[test]
+    fn test_beats_single_stages() {
+        // Random sentences: LZW catches the repeated words, and leaves its
+        // 16-bit codes (whose high bytes are skewed) for Huffman to shrink.
+        let words = [
+            "the ", "quick ", "brown ", "fox ", "jumps ", "over ", "lazy ", "dog ",
+        ];
+        let mut state = 12345_u32;
+        let mut data = Vec::new();
+        while data.len() < 100_000 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            data.extend(words[(state >> 16) as usize % words.len()].bytes());
+        }
+        let data = data.as_slice();
+        let pipeline = Pipeline::new(vec![Box::new(Algorithm::Lzw), Box::new(Algorithm::Huffman)]);
+
+        let chained = enc(&pipeline, data).len();
+        assert!(chained < enc(&Algorithm::Lzw, data).len());
+        assert!(chained < enc(&Algorithm::Huffman, data).len());
+    }
+}
@@ -0,0 +1,40 @@
+//! The commonly used items, to be glob-imported:
+//!
+//! ```
+//! use compressing::prelude::*;
+//!
+//! let compressed = compress(Algorithm::Lzw, b"ABABABA")?;
+//! assert_eq!(decompress(Algorithm::Lzw, &compressed)?, b"ABABABA");
+//! # Ok::<(), std::io::Error>(())
+//! ```
+
+pub use crate::{
+    compress, decompress, lzw::LzwOptions, roundtrip_check, Algorithm, Codec, CompressError,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prelude() {
+        let data = b"ABBABBBABBA";
+        for algorithm in Algorithm::ALL {
+            let compressed = compress(algorithm, data).unwrap();
+            assert_eq!(decompress(algorithm, &compressed).unwrap(), data);
+
+            // Through the trait.
+            let codec: &dyn Codec = &algorithm;
+            let mut decompressed = Vec::new();
+            codec
+                .dec(&mut compressed.as_slice(), &mut decompressed)
+                .unwrap();
+            assert_eq!(decompressed, data);
+        }
+
+        let options = LzwOptions::default();
+        assert_eq!(options.max_key_len, None);
+        let error = CompressError::from(decompress(Algorithm::Huffman, &[1, 1]).unwrap_err());
+        assert!(matches!(error, CompressError::BadHeader(_)));
+    }
+}
@@ -0,0 +1,317 @@
+//! A binary range coder with adaptive bit models, as used by LZMA.
+//!
+//! Each bit is coded with a [`BitModel`], which estimates the probability of
+//! the bit being 0 and adapts to the bits it sees. A bit of probability `p`
+//! takes about `-log2(p)` bits of output, so (unlike with Huffman coding) a
+//! very predictable bit takes way less than one bit.
+
+use std::io;
+
+use crate::shared::read_u8;
+
+/// The number of bits of the probabilities.
+const PROB_BITS: u32 = 11;
+/// The probability scale, i.e., a probability of 1.
+const PROB_ONE: u16 = 1 << PROB_BITS;
+/// How fast the models adapt: each bit moves the probability by `1/2^MOVE_BITS`
+/// of its distance to 0 or 1.
+const MOVE_BITS: u32 = 5;
+/// The range is renormalized (shifted by a byte) once it drops below this.
+const TOP: u32 = 1 << 24;
+
+/// An adaptive estimate of the probability of a bit being 0.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BitModel(u16);
+
+impl BitModel {
+    /// Constructs a new model, with the bits being equally likely.
+    pub fn new() -> Self {
+        BitModel(PROB_ONE / 2)
+    }
+
+    /// Returns the estimated probability of a 0 bit, out of `2^11`.
+    pub fn prob(self) -> u16 {
+        self.0
+    }
+
+    fn update(&mut self, bit: bool) {
+        if bit {
+            self.0 -= self.0 >> MOVE_BITS;
+        } else {
+            self.0 += (PROB_ONE - self.0) >> MOVE_BITS;
+        }
+    }
+}
+
+impl Default for BitModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Encodes bits into the underlying writer.
+///
+/// [`RangeEncoder::finish`] must be called once all bits are encoded.
+pub struct RangeEncoder<'a> {
+    out: &'a mut dyn io::Write,
+    /// The low end of the range. Its bit 32 is the carry into `cache`.
+    low: u64,
+    range: u32,
+    /// The last byte shifted out of `low`, which is held back (along with
+    /// `pending` bytes of `0xFF`) since a carry may still increment it.
+    cache: u8,
+    pending: u64,
+}
+
+impl<'a> RangeEncoder<'a> {
+    /// Constructs a new [`RangeEncoder`].
+    pub fn new(out: &'a mut dyn io::Write) -> Self {
+        Self {
+            out,
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            pending: 1,
+        }
+    }
+
+    /// Encodes a bit with the given model, updating it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing to the underlying writer fails.
+    pub fn encode_bit(&mut self, model: &mut BitModel, bit: bool) -> io::Result<()> {
+        let bound = (self.range >> PROB_BITS) * u32::from(model.0);
+        if bit {
+            self.low += u64::from(bound);
+            self.range -= bound;
+        } else {
+            self.range = bound;
+        }
+        model.update(bit);
+
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes the pending bytes.
+    ///
+    /// # Errors
+    ///
+    /// Fails if writing to the underlying writer fails.
+    pub fn finish(mut self) -> io::Result<()> {
+        for _ in 0..5 {
+            self.shift_low()?;
+        }
+        Ok(())
+    }
+
+    /// Shifts the top byte out of `low`, writing out the held back bytes once
+    /// they can no longer change.
+    fn shift_low(&mut self) -> io::Result<()> {
+        let carry = (self.low >> 32) as u8;
+        if self.low < 0xFF00_0000 || carry != 0 {
+            // The carry (if any) propagates through the held back `0xFF`s.
+            self.out.write_all(&[self.cache.wrapping_add(carry)])?;
+            for _ in 1..self.pending {
+                self.out.write_all(&[0xFF_u8.wrapping_add(carry)])?;
+            }
+            self.pending = 0;
+            self.cache = (self.low >> 24) as u8;
+        }
+        self.pending += 1;
+        self.low = (self.low & 0x00FF_FFFF) << 8;
+        Ok(())
+    }
+}
+
+/// Decodes bits encoded by a [`RangeEncoder`] from the underlying reader.
+pub struct RangeDecoder<'a> {
+    src: &'a mut dyn io::Read,
+    range: u32,
+    code: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    /// Constructs a new [`RangeDecoder`], reading the first bytes of the data.
+    ///
+    /// # Errors
+    ///
+    /// Fails if reading from `src` fails, or if the data is truncated.
+    pub fn new(src: &'a mut dyn io::Read) -> io::Result<Self> {
+        let mut decoder = Self {
+            src,
+            range: u32::MAX,
+            code: 0,
+        };
+        // The first byte is the encoder's initial (always zero) cache.
+        for _ in 0..5 {
+            decoder.code = (decoder.code << 8) | u32::from(decoder.next_byte()?);
+        }
+        Ok(decoder)
+    }
+
+    /// Decodes a bit with the given model (which must be in the same state as
+    /// the encoder's was), updating it.
+    ///
+    /// # Errors
+    ///
+    /// Fails if reading from the underlying reader fails, or if the data is
+    /// truncated.
+    pub fn decode_bit(&mut self, model: &mut BitModel) -> io::Result<bool> {
+        let bound = (self.range >> PROB_BITS) * u32::from(model.0);
+        let bit = self.code >= bound;
+        if bit {
+            self.code -= bound;
+            self.range -= bound;
+        } else {
+            self.range = bound;
+        }
+        model.update(bit);
+
+        while self.range < TOP {
+            self.range <<= 8;
+            self.code = (self.code << 8) | u32::from(self.next_byte()?);
+        }
+        Ok(bit)
+    }
+
+    fn next_byte(&mut self) -> io::Result<u8> {
+        read_u8(self.src)?.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::UnexpectedEof, "truncated range coder data")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns `len` pseudo-random bits, each being 1 with probability
+    /// `ones / 1000`.
+    fn biased_bits(len: usize, ones: u32) -> Vec<bool> {
+        let mut state = 0x2545_F491_u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state % 1000 < ones
+            })
+            .collect()
+    }
+
+    fn encode(bits: &[bool]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = RangeEncoder::new(&mut out);
+        let mut model = BitModel::new();
+        for &bit in bits {
+            encoder.encode_bit(&mut model, bit).unwrap();
+        }
+        encoder.finish().unwrap();
+        out
+    }
+
+    fn decode(data: &[u8], len: usize) -> Vec<bool> {
+        let mut src = data;
+        let mut decoder = RangeDecoder::new(&mut src).unwrap();
+        let mut model = BitModel::new();
+        (0..len)
+            .map(|_| decoder.decode_bit(&mut model).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for ones in [0, 1, 50, 300, 500, 700, 950, 999, 1000] {
+            let bits = biased_bits(20_000, ones);
+            assert_eq!(decode(&encode(&bits), bits.len()), bits, "{ones}/1000");
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        let encoded = encode(&[]);
+        assert_eq!(encoded, [0; 5]);
+        assert!(decode(&encoded, 0).is_empty());
+    }
+
+    #[test]
+    fn test_below_one_bit_per_bit() {
+        // With p(1) = 0.05, the entropy is ~0.286 bits per bit, whereas any
+        // Huffman code takes at least one bit per bit.
+        let bits = biased_bits(100_000, 50);
+        let encoded = encode(&bits);
+        let bits_per_bit = (encoded.len() * 8) as f64 / bits.len() as f64;
+        assert!(bits_per_bit < 0.32, "{bits_per_bit}");
+    }
+
+    #[test]
+    fn test_multiple_models() {
+        // Bits that alternate between two very different sources, each with
+        // its own model, in a fixed pattern.
+        let a = biased_bits(10_000, 20);
+        let b = biased_bits(10_000, 900);
+
+        let mut out = Vec::new();
+        let mut encoder = RangeEncoder::new(&mut out);
+        let mut models = [BitModel::new(); 2];
+        for (&a, &b) in a.iter().zip(&b) {
+            encoder.encode_bit(&mut models[0], a).unwrap();
+            encoder.encode_bit(&mut models[1], b).unwrap();
+        }
+        encoder.finish().unwrap();
+
+        let mut src = out.as_slice();
+        let mut decoder = RangeDecoder::new(&mut src).unwrap();
+        let mut models = [BitModel::new(); 2];
+        for (&a, &b) in a.iter().zip(&b) {
+            assert_eq!(decoder.decode_bit(&mut models[0]).unwrap(), a);
+            assert_eq!(decoder.decode_bit(&mut models[1]).unwrap(), b);
+        }
+    }
+
+    #[test]
+    fn test_carry() {
+        // A carry happens when a 1 bit (which raises `low`) follows a run of
+        // bytes that are all `0xFF`: long runs of likely 1s after unlikely 0s
+        // produce many such runs, which would corrupt the data if the carry
+        // didn't propagate.
+        let mut bits = Vec::new();
+        for i in 0..2_000 {
+            bits.extend(std::iter::repeat_n(true, 50 + i % 17));
+            bits.push(false);
+        }
+        assert_eq!(decode(&encode(&bits), bits.len()), bits);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bits = biased_bits(1_000, 500);
+        let encoded = encode(&bits);
+
+        let mut src = &encoded[..encoded.len() / 2];
+        let mut decoder = RangeDecoder::new(&mut src).unwrap();
+        let mut model = BitModel::new();
+        let error = (0..bits.len())
+            .try_for_each(|_| decoder.decode_bit(&mut model).map(drop))
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_model() {
+        let mut model = BitModel::new();
+        assert_eq!(model.prob(), 1024);
+        model.update(false);
+        assert_eq!(model.prob(), 1024 + 32);
+        for _ in 0..1_000 {
+            model.update(true);
+        }
+        // It never reaches 0, so a 0 bit can always be coded.
+        assert!(model.prob() > 0);
+    }
+}
@@ -1,12 +1,14 @@
+use std::io::{self, Read, Write};
+
 macro_rules! read_fn {
     ($($vis:vis fn $name:ident() -> $ty:ty ;)+) => {
         $(
             #[inline(always)]
-            $vis fn $name(src: &mut dyn ::std::io::Read) -> std::io::Result<Option<$ty>> {
-                let mut buf = [0; ::std::mem::size_of::<$ty>()];
+            $vis fn $name(src: &mut dyn Read) -> io::Result<Option<$ty>> {
+                let mut buf = [0; std::mem::size_of::<$ty>()];
                 match src.read_exact(&mut buf) {
                     Ok(_) => Ok(Some(<$ty>::from_be_bytes(buf))),
-                    Err(error) if error.kind() == ::std::io::ErrorKind::UnexpectedEof => Ok(None),
+                    Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
                     Err(error) => Err(error),
                 }
             }
@@ -17,4 +19,396 @@ macro_rules! read_fn {
 read_fn!(
     pub(crate) fn read_u8() -> u8;
     pub(crate) fn read_u16() -> u16;
+    pub(crate) fn read_u32() -> u32;
+    pub(crate) fn read_u64() -> u64;
 );
+
+/// Reads into `buf`, retrying while the read is interrupted (i.e., fails with
+/// [`ErrorKind::Interrupted`](io::ErrorKind::Interrupted)), and returns the
+/// number of bytes read.
+///
+/// Raw [`Read::read`] loops must use this, whereas `read_exact` (and the like)
+/// already retry.
+pub fn read_retry(src: &mut dyn Read, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        match src.read(buf) {
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            result => return result,
+        }
+    }
+}
+
+/// Writes all of `buf`, retrying while the write is interrupted, and advances
+/// it past the bytes written (such that, on failure, it holds the ones left).
+/// It's what [`Write::write_all`] does, spelled out for raw [`Write::write`]
+/// loops which must know how far a failed write got.
+///
+/// # Errors
+///
+/// Fails if writing fails, or with
+/// [`ErrorKind::WriteZero`](io::ErrorKind::WriteZero) if the writer stops
+/// accepting bytes.
+pub fn write_all_retry(out: &mut dyn Write, buf: &mut &[u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        match out.write(buf) {
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write the whole buffer",
+                ));
+            }
+            Ok(n) => *buf = &buf[n..],
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(())
+}
+
+// Lengths always fit in a `u64`: Rust supports no platform wider than 64 bits.
+const _: () = assert!(usize::BITS <= u64::BITS);
+
+/// Converts a length (or any `usize`) to a `u64`, which never truncates.
+#[inline(always)]
+pub(crate) fn len_u64(len: usize) -> u64 {
+    len as u64
+}
+
+/// Writes `value` as a LEB128 varint: seven bits per byte, least significant
+/// group first, with the high bit set on all bytes but the last.
+pub(crate) fn write_varint(out: &mut dyn Write, mut value: u64) -> io::Result<()> {
+    let mut buf = [0; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    out.write_all(&buf[..len])
+}
+
+/// Reads a varint written by [`write_varint`], or returns `None` if `src` is
+/// already at its end.
+///
+/// # Errors
+///
+/// Fails with [`ErrorKind::UnexpectedEof`](io::ErrorKind::UnexpectedEof)
+/// if the varint is truncated, or with
+/// [`ErrorKind::InvalidData`](io::ErrorKind::InvalidData) if it doesn't
+/// fit in a `u64`.
+pub(crate) fn read_varint(src: &mut dyn Read) -> io::Result<Option<u64>> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let Some(byte) = read_u8(src)? else {
+            if shift == 0 {
+                return Ok(None);
+            }
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated varint",
+            ));
+        };
+        let bits = u64::from(byte & 0x7f);
+        if bits << shift >> shift != bits {
+            break;
+        }
+        value |= bits << shift;
+        if byte & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "varint overflow",
+    ))
+}
+
+/// Writes individual bits to the underlying writer, most significant bit first.
+pub(crate) struct BitWriter<'a> {
+    out: &'a mut dyn Write,
+    buf: u8,
+    len: u8,
+}
+
+impl<'a> BitWriter<'a> {
+    pub(crate) fn new(out: &'a mut dyn Write) -> Self {
+        Self {
+            out,
+            buf: 0,
+            len: 0,
+        }
+    }
+
+    pub(crate) fn write_bit(&mut self, bit: bool) -> io::Result<()> {
+        self.buf = (self.buf << 1) | u8::from(bit);
+        self.len += 1;
+        if self.len == 8 {
+            self.out.write_all(&[self.buf])?;
+            self.buf = 0;
+            self.len = 0;
+        }
+        Ok(())
+    }
+
+    /// Writes the `n` (at most 32) low bits of `value`, most significant
+    /// first.
+    #[inline]
+    pub(crate) fn write_bits(&mut self, value: u32, n: u32) -> io::Result<()> {
+        debug_assert!(n <= u32::BITS);
+        // The pending bits, then `value`'s: at most 7 + 32 of them.
+        let mut len = u32::from(self.len) + n;
+        let bits = u64::from(self.buf) << n | (u64::from(value) & ((1 << n) - 1));
+        let mut bytes = [0; 5];
+        let mut count = 0;
+        while len >= 8 {
+            len -= 8;
+            bytes[count] = (bits >> len) as u8;
+            count += 1;
+        }
+        if count > 0 {
+            self.out.write_all(&bytes[..count])?;
+        }
+        // Fewer than 8 bits are left.
+        self.buf = (bits & ((1 << len) - 1)) as u8;
+        self.len = len as u8;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer. The bits of a partial byte stay pending.
+    pub(crate) fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// Writes the pending bits, padding the last byte with zeroes.
+    pub(crate) fn finish(self) -> io::Result<()> {
+        if self.len > 0 {
+            self.out.write_all(&[self.buf << (8 - self.len)])?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads individual bits from the underlying reader, most significant bit
+/// first.
+pub(crate) struct BitReader<'a> {
+    src: &'a mut dyn Read,
+    buf: u8,
+    len: u8,
+}
+
+impl<'a> BitReader<'a> {
+    pub(crate) fn new(src: &'a mut dyn Read) -> Self {
+        Self {
+            src,
+            buf: 0,
+            len: 0,
+        }
+    }
+
+    /// Reads the next bit, returning `None` at the end of the stream.
+    pub(crate) fn read_bit(&mut self) -> io::Result<Option<bool>> {
+        if self.len == 0 {
+            match read_u8(self.src)? {
+                Some(byte) => self.buf = byte,
+                None => return Ok(None),
+            }
+            self.len = 8;
+        }
+        self.len -= 1;
+        Ok(Some((self.buf >> self.len) & 1 == 1))
+    }
+
+    /// Reads an `n`-bit value, most significant bit first, returning `None`
+    /// if the stream ends before all of its bits are read.
+    pub(crate) fn read_bits(&mut self, n: u32) -> io::Result<Option<u32>> {
+        let mut value = 0;
+        for _ in 0..n {
+            match self.read_bit()? {
+                Some(bit) => value = (value << 1) | u32::from(bit),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_len_u64() {
+        assert_eq!(len_u64(0), 0);
+        assert_eq!(len_u64(usize::MAX), u64::try_from(usize::MAX).unwrap());
+    }
+
+    #[test]
+    fn test_varint() {
+        for value in [0, 1, 127, 128, 300, u64::from(u32::MAX) + 1, u64::MAX] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value).unwrap();
+            assert_eq!(read_varint(&mut buf.as_slice()).unwrap(), Some(value));
+        }
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 300).unwrap();
+        assert_eq!(buf, [0xac, 0x02]);
+
+        assert_eq!(read_varint(&mut [].as_ref()).unwrap(), None);
+        let error = read_varint(&mut [0x80].as_ref()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let error = read_varint(&mut [0xff; 10].as_ref()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = read_varint(&mut [0xff; 11].as_ref()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_write_bits() {
+        // Widths from 0 to 32, with bits set above them (which are ignored).
+        let values: Vec<(u32, u32)> = (0..=32)
+            .map(|n| (0xdead_beefu32.rotate_left(n) | 1 << n.min(31), n))
+            .collect();
+        let (mut by_bits, mut by_bit) = (Vec::new(), Vec::new());
+        let mut writer = BitWriter::new(&mut by_bits);
+        for &(value, n) in &values {
+            writer.write_bits(value, n).unwrap();
+        }
+        writer.finish().unwrap();
+        let mut writer = BitWriter::new(&mut by_bit);
+        for &(value, n) in &values {
+            for i in (0..n).rev() {
+                writer.write_bit((value >> i) & 1 == 1).unwrap();
+            }
+        }
+        writer.finish().unwrap();
+        assert_eq!(by_bits, by_bit);
+
+        let mut src = by_bits.as_slice();
+        let mut bits = BitReader::new(&mut src);
+        for &(value, n) in &values {
+            let mask = (1u64 << n) - 1;
+            assert_eq!(
+                u64::from(bits.read_bits(n).unwrap().unwrap()),
+                u64::from(value) & mask
+            );
+        }
+    }
+
+    /// A reader/writer which is interrupted `interruptions` times before each
+    /// operation, and then moves at most 3 bytes.
+    struct Flaky {
+        data: Vec<u8>,
+        interruptions: u32,
+        left: u32,
+    }
+
+    impl Flaky {
+        fn new(data: &[u8], interruptions: u32) -> Self {
+            Self {
+                data: data.to_vec(),
+                interruptions,
+                left: interruptions,
+            }
+        }
+
+        fn interrupt(&mut self) -> io::Result<()> {
+            if self.left > 0 {
+                self.left -= 1;
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            self.left = self.interruptions;
+            Ok(())
+        }
+    }
+
+    impl Read for Flaky {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.interrupt()?;
+            let n = buf.len().min(self.data.len()).min(3);
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data.drain(..n);
+            Ok(n)
+        }
+    }
+
+    impl Write for Flaky {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.interrupt()?;
+            let n = buf.len().min(3);
+            self.data.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_read_retry() {
+        let mut src = Flaky::new(b"ABBA", 2);
+        let mut buf = [0; 8];
+        assert_eq!(read_retry(&mut src, &mut buf).unwrap(), 3);
+        assert_eq!(read_retry(&mut src, &mut buf[3..]).unwrap(), 1);
+        assert_eq!(read_retry(&mut src, &mut buf[4..]).unwrap(), 0);
+        assert_eq!(buf[..4], *b"ABBA");
+
+        // Without retrying, the interruption surfaces.
+        let mut src = Flaky::new(b"ABBA", 1);
+        let error = src.read(&mut buf).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+    }
+
+    #[test]
+    fn test_write_all_retry() {
+        let mut out = Flaky::new(b"", 3);
+        let mut buf = &b"ABBABBBA"[..];
+        write_all_retry(&mut out, &mut buf).unwrap();
+        assert_eq!(out.data, b"ABBABBBA");
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_write_all_retry_write_zero() {
+        struct Full;
+
+        impl Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Ok(0)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut buf = &b"A"[..];
+        let error = write_all_retry(&mut Full, &mut buf).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+        assert_eq!(buf, b"A");
+        write_all_retry(&mut Full, &mut &b""[..]).unwrap();
+    }
+
+    #[test]
+    fn test_codecs_retry() {
+        // The codecs go through both the retrying helpers and `read_exact`.
+        let data = b"ABBABBBABBAABBABBBABBA";
+        for algorithm in crate::Algorithm::ALL {
+            let mut compressed = Vec::new();
+            algorithm
+                .enc(&mut Flaky::new(data, 2), &mut compressed)
+                .unwrap();
+            let mut out = Flaky::new(b"", 2);
+            algorithm
+                .dec(&mut Flaky::new(&compressed, 2), &mut out)
+                .unwrap();
+            assert_eq!(out.data, data, "{algorithm:?}");
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Sniffing of what an input holds, from its first bytes, to suggest the
+//! algorithm which suits it best.
+
+use std::fmt;
+
+use crate::Algorithm;
+
+/// The number of leading bytes [`sniff`] looks at.
+pub const SNIFF_LEN: usize = 512;
+
+/// The signatures of common formats whose data is already compressed (hence
+/// incompressible): the format's name, the signature's offset and its bytes.
+const COMPRESSED: &[(&str, usize, &[u8])] = &[
+    ("PNG", 0, b"\x89PNG\r\n\x1a\n"),
+    ("JPEG", 0, b"\xff\xd8\xff"),
+    ("GIF", 0, b"GIF87a"),
+    ("GIF", 0, b"GIF89a"),
+    ("WebP", 8, b"WEBP"),
+    ("ZIP", 0, b"PK\x03\x04"),
+    ("ZIP", 0, b"PK\x05\x06"),
+    ("gzip", 0, b"\x1f\x8b"),
+    (".Z", 0, b"\x1f\x9d"),
+    ("bzip2", 0, b"BZh"),
+    ("xz", 0, b"\xfd7zXZ\x00"),
+    ("Zstandard", 0, b"\x28\xb5\x2f\xfd"),
+    ("7z", 0, b"7z\xbc\xaf\x27\x1c"),
+    ("MP3", 0, b"ID3"),
+    ("MP4", 4, b"ftyp"),
+];
+
+/// What an input holds, as far as its first bytes tell.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Content {
+    /// Data of an already compressed format, e.g., `PNG`.
+    Compressed { format: &'static str },
+    /// UTF-8 text, without control characters other than whitespace.
+    Text,
+    /// Any other data.
+    Binary,
+}
+
+impl Content {
+    /// Returns whether compressing the data is likely to pay off.
+    pub fn is_compressible(self) -> bool {
+        !matches!(self, Content::Compressed { .. })
+    }
+
+    /// Returns the algorithm which likely suits the data best: stored for
+    /// already compressed data, LZW for text (whose repeated words it
+    /// captures), and Huffman for other binary data (which repeats verbatim
+    /// less often, but whose bytes are often skewed).
+    pub fn suggested_algorithm(self) -> Algorithm {
+        match self {
+            Content::Compressed { .. } => Algorithm::Stored,
+            Content::Text => Algorithm::Lzw,
+            Content::Binary => Algorithm::Huffman,
+        }
+    }
+}
+
+impl fmt::Display for Content {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Content::Compressed { format } => write!(f, "already compressed data ({format})"),
+            Content::Text => f.write_str("text"),
+            Content::Binary => f.write_str("binary data"),
+        }
+    }
+}
+
+/// Sniffs what an input holds from its first bytes (up to [`SNIFF_LEN`] of
+/// them are looked at). Empty inputs count as text.
+pub fn sniff(head: &[u8]) -> Content {
+    let head = &head[..head.len().min(SNIFF_LEN)];
+    let signature = COMPRESSED.iter().find(|(_, offset, signature)| {
+        head.get(*offset..)
+            .is_some_and(|head| head.starts_with(signature))
+    });
+    if let Some(&(format, ..)) = signature {
+        return Content::Compressed { format };
+    }
+    if is_text(head) {
+        Content::Text
+    } else {
+        Content::Binary
+    }
+}
+
+fn is_text(head: &[u8]) -> bool {
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        // The head may end in the middle of a character.
+        Err(error) if error.error_len().is_none() => {
+            std::str::from_utf8(&head[..error.valid_up_to()]).unwrap()
+        }
+        Err(_) => return false,
+    };
+    text.chars()
+        .all(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r' | '\x0c'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compressed() {
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR";
+        assert_eq!(sniff(png), Content::Compressed { format: "PNG" });
+        assert!(!sniff(png).is_compressible());
+        assert_eq!(sniff(png).suggested_algorithm(), Algorithm::Stored);
+
+        assert_eq!(
+            sniff(b"RIFF\x24\x00\x00\x00WEBPVP8 "),
+            Content::Compressed { format: "WebP" }
+        );
+        let mut gzip = Vec::new();
+        crate::gzip::write_member(&mut "olá".as_bytes(), &mut gzip, 0).unwrap();
+        assert_eq!(sniff(&gzip), Content::Compressed { format: "gzip" });
+    }
+
+    #[test]
+    fn test_text() {
+        let text = "olá, mundo!\n\tcomo vai?\r\n".repeat(100);
+        assert_eq!(sniff(text.as_bytes()), Content::Text);
+        assert!(sniff(text.as_bytes()).is_compressible());
+        assert_eq!(sniff(text.as_bytes()).suggested_algorithm(), Algorithm::Lzw);
+        assert_eq!(sniff(b""), Content::Text);
+
+        // `á` is cut in the middle by the sniffed length.
+        let text = format!("{}á", "a".repeat(SNIFF_LEN - 1));
+        assert_eq!(sniff(text.as_bytes()), Content::Text);
+    }
+
+    #[test]
+    fn test_binary() {
+        assert_eq!(sniff(b"\x00\x01\x02\x03"), Content::Binary);
+        assert_eq!(sniff(b"ol\xe1!"), Content::Binary);
+        assert_eq!(sniff(b"\x1f\x8c"), Content::Binary);
+        assert_eq!(
+            sniff(b"\x7fELF\x02\x01").suggested_algorithm(),
+            Algorithm::Huffman
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Splitting of a byte stream into size-bounded volumes.
+//!
+//! The volumes of `base` are named `base.001`, `base.002`, and so on. They
+//! compose with any codec, which only sees a single [`io::Write`] or
+//! [`io::Read`].
+
+use std::{
+    ffi::OsString,
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::shared::len_u64;
+
+/// A writer which rolls over to the next volume once the current one holds
+/// `volume_size` bytes.
+#[derive(Debug)]
+pub struct SplitWriter {
+    base: PathBuf,
+    volume_size: u64,
+    volume: File,
+    index: u32,
+    written: u64,
+}
+
+impl SplitWriter {
+    /// Constructs a new [`SplitWriter`], creating (or truncating) the first
+    /// volume right away, so that even empty output has one.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `volume_size` is zero, or if the first volume can't be created.
+    pub fn new(base: impl Into<PathBuf>, volume_size: u64) -> io::Result<Self> {
+        if volume_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "volume size must be positive",
+            ));
+        }
+        let base = base.into();
+        let volume = File::create(volume_path(&base, 1))?;
+        Ok(Self {
+            base,
+            volume_size,
+            volume,
+            index: 1,
+            written: 0,
+        })
+    }
+
+    /// Returns the number of volumes created so far.
+    pub fn volume_count(&self) -> u32 {
+        self.index
+    }
+}
+
+impl io::Write for SplitWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.written == self.volume_size {
+            self.volume.flush()?;
+            self.index += 1;
+            self.volume = File::create(volume_path(&self.base, self.index))?;
+            self.written = 0;
+        }
+        let remaining = self.volume_size - self.written;
+        let len = usize::try_from(remaining).map_or(buf.len(), |r| r.min(buf.len()));
+        let n = self.volume.write(&buf[..len])?;
+        self.written += len_u64(n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.volume.flush()
+    }
+}
+
+/// A reader over the volumes written by a [`SplitWriter`], in sequence.
+#[derive(Debug)]
+pub struct SplitReader {
+    base: PathBuf,
+    volume: Option<File>,
+    index: u32,
+}
+
+impl SplitReader {
+    /// Constructs a new [`SplitReader`], opening the first volume.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the first volume can't be opened.
+    pub fn new(base: impl Into<PathBuf>) -> io::Result<Self> {
+        let base = base.into();
+        let volume = File::open(volume_path(&base, 1))?;
+        Ok(Self {
+            base,
+            volume: Some(volume),
+            index: 1,
+        })
+    }
+}
+
+impl io::Read for SplitReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while let Some(volume) = &mut self.volume {
+            let n = volume.read(buf)?;
+            if n > 0 {
+                return Ok(n);
+            }
+            // The stream ends at the first missing volume.
+            self.index += 1;
+            self.volume = match File::open(volume_path(&self.base, self.index)) {
+                Ok(volume) => Some(volume),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+                Err(error) => return Err(error),
+            };
+        }
+        Ok(0)
+    }
+}
+
+/// Returns the path of the `index`-th volume (starting at 1) of `base`.
+pub fn volume_path(base: &Path, index: u32) -> PathBuf {
+    let mut path = OsString::from(base);
+    path.push(format!(".{index:03}"));
+    path.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{Read, Write},
+    };
+
+    use super::*;
+    use crate::Algorithm;
+
+    fn temp_base(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("compressing-{}-{name}", std::process::id()))
+    }
+
+    fn remove_volumes(base: &Path, count: u32) {
+        for i in 1..=count {
+            fs::remove_file(volume_path(base, i)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_volume_path() {
+        assert_eq!(
+            volume_path(Path::new("out.lzw"), 2),
+            Path::new("out.lzw.002")
+        );
+    }
+
+    #[test]
+    fn test_split_roundtrip() {
+        let base = temp_base("split-roundtrip");
+        let data: Vec<u8> = (0..20_000u32).map(|i| (i * 7 % 251) as u8).collect();
+
+        let mut writer = SplitWriter::new(&base, 1024).unwrap();
+        Algorithm::Lzw
+            .enc(&mut data.as_slice(), &mut writer)
+            .unwrap();
+        writer.flush().unwrap();
+        let count = writer.volume_count();
+        assert!(count > 1);
+
+        let total: u64 = (1..=count)
+            .map(|i| fs::metadata(volume_path(&base, i)).unwrap().len())
+            .inspect(|&len| assert!(len <= 1024))
+            .sum();
+        assert!(volume_path(&base, count + 1).metadata().is_err());
+
+        let mut reader = SplitReader::new(&base).unwrap();
+        let mut out = Vec::new();
+        Algorithm::Lzw.dec(&mut reader, &mut out).unwrap();
+        assert_eq!(out, data);
+
+        let mut compressed = Vec::new();
+        Algorithm::Lzw
+            .enc(&mut data.as_slice(), &mut compressed)
+            .unwrap();
+        assert_eq!(total, u64::try_from(compressed.len()).unwrap());
+
+        remove_volumes(&base, count);
+    }
+
+    #[test]
+    fn test_split_exact_boundary() {
+        let base = temp_base("split-boundary");
+        let mut writer = SplitWriter::new(&base, 4).unwrap();
+        writer.write_all(b"ABCDEFGH").unwrap();
+        // No empty trailing volume is created for a full last volume.
+        assert_eq!(writer.volume_count(), 2);
+        drop(writer);
+
+        let mut out = Vec::new();
+        SplitReader::new(&base)
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, b"ABCDEFGH");
+
+        remove_volumes(&base, 2);
+    }
+
+    #[test]
+    fn test_split_zero_volume_size() {
+        let error = SplitWriter::new(temp_base("split-zero"), 0).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
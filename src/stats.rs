@@ -0,0 +1,255 @@
+//! Statistics on data, e.g., to estimate how well it compresses, and on runs
+//! of the codecs.
+
+use std::{io, time::Duration};
+
+use crate::shared::read_retry;
+
+/// The number of occurrences of each byte value in some data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ByteHistogram {
+    counts: [u64; 256],
+}
+
+impl ByteHistogram {
+    /// Constructs a new, empty histogram.
+    pub fn new() -> Self {
+        Self { counts: [0; 256] }
+    }
+
+    /// Constructs a histogram of all the data read from `reader`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if reading from `reader` fails.
+    pub fn from_reader(reader: &mut dyn io::Read) -> io::Result<Self> {
+        let mut histogram = Self::new();
+        let mut buf = [0; 8 * 1024];
+        loop {
+            match read_retry(reader, &mut buf)? {
+                0 => return Ok(histogram),
+                n => histogram.record_slice(&buf[..n]),
+            }
+        }
+    }
+
+    /// Records an occurrence of the given byte.
+    #[inline]
+    pub fn record(&mut self, byte: u8) {
+        self.counts[usize::from(byte)] += 1;
+    }
+
+    /// Records an occurrence of each of the given bytes.
+    pub fn record_slice(&mut self, data: &[u8]) {
+        // Consecutive bytes go to distinct tables, such that runs of a single
+        // byte don't wait on each other's increments of the same counter.
+        let mut lanes = [[0u64; 256]; 4];
+        let mut chunks = data.chunks_exact(4);
+        for chunk in &mut chunks {
+            for (lane, &byte) in lanes.iter_mut().zip(chunk) {
+                lane[usize::from(byte)] += 1;
+            }
+        }
+        for &byte in chunks.remainder() {
+            self.record(byte);
+        }
+        for lane in &lanes {
+            for (count, &n) in self.counts.iter_mut().zip(lane) {
+                *count += n;
+            }
+        }
+    }
+
+    /// Returns the number of occurrences of the given byte.
+    pub fn count(&self, byte: u8) -> u64 {
+        self.counts[usize::from(byte)]
+    }
+
+    /// Returns the number of recorded bytes.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the number of distinct recorded bytes.
+    pub fn distinct(&self) -> usize {
+        self.counts.iter().filter(|&&count| count != 0).count()
+    }
+
+    /// Returns an iterator over the recorded bytes and their counts, in
+    /// increasing byte order.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, u64)> + '_ {
+        (u8::MIN..=u8::MAX)
+            .zip(self.counts)
+            .filter(|&(_, count)| count != 0)
+    }
+
+    /// Returns the (at most) `n` most frequent bytes and their counts, from the
+    /// most frequent one, and in increasing byte order among equal counts.
+    pub fn most_frequent(&self, n: usize) -> Vec<(u8, u64)> {
+        let mut entries: Vec<_> = self.iter().collect();
+        entries.sort_by_key(|&(byte, count)| (std::cmp::Reverse(count), byte));
+        entries.truncate(n);
+        entries
+    }
+
+    /// Returns the (order-0) Shannon entropy of the recorded bytes, in bits per
+    /// byte.
+    ///
+    /// It's a lower bound on the size of any encoding which codes every byte
+    /// independently (such as Huffman's), from 0 (a single distinct byte) to 8
+    /// (uniformly distributed bytes). An empty histogram has zero entropy.
+    pub fn entropy(&self) -> f64 {
+        let total = self.total() as f64;
+        self.iter()
+            .map(|(_, count)| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+}
+
+impl Default for ByteHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&[u8]> for ByteHistogram {
+    fn from(data: &[u8]) -> Self {
+        let mut histogram = Self::new();
+        histogram.record_slice(data);
+        histogram
+    }
+}
+
+/// Returns the entropy of the given data. See [`ByteHistogram::entropy`].
+pub fn entropy(data: &[u8]) -> f64 {
+    ByteHistogram::from(data).entropy()
+}
+
+/// The outcome of compressing (or decompressing) some data.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunStats {
+    pub input_bytes: u64,
+    pub output_bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl RunStats {
+    /// Returns the input size over the output one, i.e., the compression ratio
+    /// of a compression run (`NaN` if both are empty).
+    pub fn ratio(&self) -> f64 {
+        self.input_bytes as f64 / self.output_bytes as f64
+    }
+}
+
+/// Sums the [`RunStats`] of many runs, e.g., of a batch of files.
+#[derive(Clone, Debug, Default)]
+pub struct StatsAggregator {
+    total: RunStats,
+    runs: usize,
+}
+
+impl StatsAggregator {
+    /// Constructs a new aggregator, of no runs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the given run to the totals.
+    pub fn add(&mut self, run: RunStats) {
+        self.total.input_bytes += run.input_bytes;
+        self.total.output_bytes += run.output_bytes;
+        self.total.elapsed += run.elapsed;
+        self.runs += 1;
+    }
+
+    /// Returns the number of added runs.
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    /// Returns the combined run: the sums of the sizes and of the times, whose
+    /// [`ratio`](RunStats::ratio) is thus the overall one (and not the mean of
+    /// the runs' ratios).
+    pub fn summary(&self) -> RunStats {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        let mut histogram = ByteHistogram::from(&b"ABBACD"[..]);
+        histogram.record(b'D');
+        assert_eq!(histogram.count(b'A'), 2);
+        assert_eq!(histogram.count(b'D'), 2);
+        assert_eq!(histogram.count(b'Z'), 0);
+        assert_eq!(histogram.total(), 7);
+        assert_eq!(histogram.distinct(), 4);
+        assert_eq!(
+            histogram.iter().collect::<Vec<_>>(),
+            [(b'A', 2), (b'B', 2), (b'C', 1), (b'D', 2)]
+        );
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i % 3) as u8).collect();
+        let histogram = ByteHistogram::from_reader(&mut data.as_slice()).unwrap();
+        assert_eq!(histogram, ByteHistogram::from(data.as_slice()));
+        assert_eq!(histogram.count(0), 33_334);
+    }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy(b""), 0.0);
+        assert_eq!(entropy(b"AAAA"), 0.0);
+        assert_eq!(entropy(b"ABAB"), 1.0);
+        assert_eq!(entropy(b"ABCD"), 2.0);
+
+        let uniform: Vec<u8> = (u8::MIN..=u8::MAX).collect();
+        assert_eq!(entropy(&uniform), 8.0);
+
+        // p = (1/2, 1/4, 1/8, 1/8).
+        assert_eq!(entropy(b"AAAABBCD"), 1.75);
+        let mut skewed = uniform.clone();
+        skewed.extend([b'A'; 1000]);
+        assert!(entropy(&skewed) < entropy(&uniform) / 2.0);
+    }
+
+    #[test]
+    fn test_most_frequent() {
+        let histogram = ByteHistogram::from(&b"ABBCCCDDDZ"[..]);
+        assert_eq!(histogram.most_frequent(2), [(b'C', 3), (b'D', 3)]);
+        assert_eq!(
+            histogram.most_frequent(10),
+            [(b'C', 3), (b'D', 3), (b'B', 2), (b'A', 1), (b'Z', 1)]
+        );
+        assert!(ByteHistogram::new().most_frequent(3).is_empty());
+    }
+
+    #[test]
+    fn test_aggregator() {
+        let run = |input_bytes, output_bytes, ms| RunStats {
+            input_bytes,
+            output_bytes,
+            elapsed: Duration::from_millis(ms),
+        };
+        let mut aggregator = StatsAggregator::new();
+        assert_eq!(aggregator.summary(), RunStats::default());
+        aggregator.add(run(1000, 500, 10));
+        aggregator.add(run(3000, 1000, 25));
+        aggregator.add(run(4000, 500, 5));
+        assert_eq!(aggregator.runs(), 3);
+
+        let summary = aggregator.summary();
+        assert_eq!(summary, run(8000, 2000, 40));
+        // Not the mean of 2, 3 and 8.
+        assert_eq!(summary.ratio(), 4.0);
+    }
+}
@@ -0,0 +1,89 @@
+//! The *stored* codec, which copies the data as is.
+//!
+//! It's the baseline the other codecs are measured against, and the fallback
+//! for incompressible data.
+
+use std::io;
+
+use crate::shared::read_retry;
+
+/// The length of the copy buffer, far larger than the 8 KiB `io::copy` uses
+/// (it can't pick a larger one for trait objects), so that large inputs take
+/// few reads and writes.
+const BUF_LEN: usize = 256 * 1024;
+
+/// Encodes (i.e., copies) the given data.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`).
+pub fn enc(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    copy(src, out)
+}
+
+/// Decodes (i.e., copies) the given data.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`).
+pub fn dec(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    copy(src, out)
+}
+
+/// Copies `src` into `out`, like [`io::copy`], but with a [`BUF_LEN`] buffer.
+fn copy(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let mut buf = vec![0; BUF_LEN];
+    loop {
+        let n = read_retry(src, &mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        out.write_all(&buf[..n])?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i * 31 % 256) as u8).collect();
+        let mut encoded = Vec::new();
+        enc(&mut data.as_slice(), &mut encoded).unwrap();
+        assert_eq!(encoded, data);
+
+        let mut decoded = Vec::new();
+        dec(&mut encoded.as_slice(), &mut decoded).unwrap();
+        assert_eq!(decoded, data);
+    }
+
+    #[test]
+    fn test_few_reads() {
+        struct CountingReader<'a>(&'a [u8], usize);
+
+        impl io::Read for CountingReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.1 += 1;
+                self.0.read(buf)
+            }
+        }
+
+        let data = vec![7; 4 * BUF_LEN];
+        let mut src = CountingReader(&data, 0);
+        let mut out = Vec::new();
+        enc(&mut src, &mut out).unwrap();
+        assert_eq!(out, data);
+        // A read per buffer, and the last one (of nothing).
+        assert_eq!(src.1, 5);
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut encoded = Vec::new();
+        enc(&mut b"".as_ref(), &mut encoded).unwrap();
+        assert!(encoded.is_empty());
+    }
+}
@@ -0,0 +1,499 @@
+//! The `.Z` format of the Unix `compress` tool.
+//!
+//! A `.Z` file starts with a 3-byte header: the magic bytes `1f 9d`, then a
+//! byte holding the maximum code width (in its 5 low bits) and the block-mode
+//! flag (bit `0x80`). The LZW codes follow, packed least significant bit
+//! first, starting at 9 bits wide and growing (up to the maximum width) as the
+//! dictionary does. In block mode, code 256 clears the dictionary, resetting
+//! the width to 9 bits.
+//!
+//! Codes of the same width are read in groups of 8 (i.e., of `width` bytes),
+//! and a width change (or a clear) skips the rest of the current group. The
+//! dictionary is the one of [`lzw`](crate::lzw), but this framing (and this bit
+//! order) ties it to its own code reader and writer.
+
+use std::io::{self, Read};
+
+use crate::{
+    lzw::{Code, DecDict, Dictionary, EncDict, MAX_BITS_RANGE},
+    shared::{len_u64, read_u8},
+    CompressError,
+};
+
+/// The magic bytes all `.Z` files start with.
+pub const MAGIC: [u8; 2] = [0x1f, 0x9d];
+
+/// The maximum code width used by default.
+pub const DEFAULT_MAX_BITS: u32 = 16;
+
+/// The flag bits of the third header byte.
+const BLOCK_MODE: u8 = 0x80;
+const BITS_MASK: u8 = 0x1f;
+
+/// The code which (in block mode) clears the dictionary.
+const CLEAR: Code = 256;
+
+/// The initial code width.
+const INIT_BITS: u32 = 9;
+
+/// Decodes the given `.Z` data.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`), with [`CompressError::BadHeader`] if the header is
+/// malformed or unsupported, or with [`CompressError::InvalidCode`] if the data
+/// is invalid.
+pub fn decode(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let mut header = [0; 3];
+    src.read_exact(&mut header)
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::UnexpectedEof => CompressError::BadHeader("truncated .Z header").into(),
+            _ => error,
+        })?;
+    if header[..2] != MAGIC {
+        return Err(CompressError::BadHeader("not a .Z file").into());
+    }
+    if header[2] & !(BLOCK_MODE | BITS_MASK) != 0 {
+        return Err(CompressError::BadHeader("unknown .Z flags").into());
+    }
+    let max_bits = u32::from(header[2] & BITS_MASK);
+    if !MAX_BITS_RANGE.contains(&max_bits) {
+        return Err(CompressError::BadHeader("unsupported .Z max bits").into());
+    }
+    let block_mode = header[2] & BLOCK_MODE != 0;
+
+    let mut reader = CodeReader::new(src);
+    let mut width = Width::new(max_bits);
+    let mut dict = dec_dict(block_mode);
+    let mut prev: Option<Vec<u8>> = None;
+    let mut offset = 0;
+
+    loop {
+        if width.must_grow(dict.len()) {
+            reader.align(width.bits)?;
+            width.grow();
+        }
+        let Some(code) = reader.read(width.bits)? else {
+            break;
+        };
+
+        let Some(prev_seq) = &prev else {
+            let byte =
+                u8::try_from(code).map_err(|_| CompressError::InvalidCode { code, offset })?;
+            out.write_all(&[byte])?;
+            offset += 1;
+            prev = Some(vec![byte]);
+            continue;
+        };
+        if block_mode && code == CLEAR {
+            reader.align(width.bits)?;
+            width = Width::new(max_bits);
+            dict = dec_dict(block_mode);
+            prev = None;
+            continue;
+        }
+
+        let seq = if dict.contains(code) {
+            dict.get(code).unwrap().to_vec()
+        } else if usize::from(code) == dict.len() {
+            // The KwKwK case: the code being defined right now.
+            let mut seq = prev_seq.clone();
+            seq.push(prev_seq[0]);
+            seq
+        } else {
+            return Err(CompressError::InvalidCode { code, offset }.into());
+        };
+        out.write_all(&seq)?;
+        offset += len_u64(seq.len());
+
+        if dict.len() < width.max_len {
+            let mut entry = prev_seq.clone();
+            entry.push(seq[0]);
+            dict.insert(entry)?;
+        }
+        prev = Some(seq);
+    }
+
+    Ok(())
+}
+
+/// Encodes the given data as a `.Z` file, in block mode, with codes of at most
+/// `max_bits` bits.
+///
+/// Once the dictionary is full, it's cleared whenever the compression ratio
+/// stops improving, checked every 10000 input bytes (as `compress` does).
+///
+/// # Errors
+///
+/// Fails with [`io::ErrorKind::InvalidInput`] if `max_bits` isn't within
+/// `9..=16`, or if any of the underlying I/O operations fail (i.e., reading
+/// from `src` or writing to `out`).
+pub fn encode(src: &mut dyn io::Read, out: &mut dyn io::Write, max_bits: u32) -> io::Result<()> {
+    if !MAX_BITS_RANGE.contains(&max_bits) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "max bits must be within 9..=16",
+        ));
+    }
+    out.write_all(&MAGIC)?;
+    // Within `MAX_BITS_RANGE`, as checked above.
+    out.write_all(&[BLOCK_MODE | max_bits as u8])?;
+
+    let mut writer = CodeWriter::new(out);
+    let mut width = Width::new(max_bits);
+    let mut dict = enc_dict();
+    let mut ratio = RatioCheck::new();
+    let mut seq = Vec::new();
+    let mut read = 0;
+
+    for byte in io::BufReader::new(src).bytes() {
+        let byte = byte?;
+        read += 1;
+        seq.push(byte);
+        if dict.contains(&seq) {
+            continue;
+        }
+
+        seq.pop();
+        writer.write(dict.get(&seq).unwrap(), width.bits)?;
+        // The decoder grows the width before reading the next code, once it
+        // caught up with the entries inserted so far (which it does one code
+        // later than the encoder).
+        if width.must_grow(dict.len()) {
+            writer.align(width.bits)?;
+            width.grow();
+        }
+        seq.push(byte);
+        if dict.len() < width.max_len {
+            dict.insert(seq.clone())?;
+        } else if ratio.has_dropped(read, writer.written() + 3) {
+            writer.write(CLEAR, width.bits)?;
+            writer.align(width.bits)?;
+            width = Width::new(max_bits);
+            dict = enc_dict();
+        }
+        seq.clear();
+        seq.push(byte);
+    }
+
+    if !seq.is_empty() {
+        writer.write(dict.get(&seq).unwrap(), width.bits)?;
+    }
+    writer.finish()
+}
+
+/// Constructs an encoder dictionary, with [`CLEAR`] taken by the empty
+/// sequence, which is never looked up.
+fn enc_dict() -> Dictionary<EncDict> {
+    let mut dict = Dictionary::<EncDict>::new();
+    dict.insert(Vec::new()).unwrap();
+    dict
+}
+
+/// Checks the compression ratio of a full dictionary.
+struct RatioCheck {
+    checkpoint: u64,
+    ratio: u64,
+}
+
+impl RatioCheck {
+    const GAP: u64 = 10_000;
+
+    fn new() -> Self {
+        Self {
+            checkpoint: Self::GAP,
+            ratio: 0,
+        }
+    }
+
+    /// Returns whether the ratio got worse since the last checkpoint, given
+    /// the number of bytes read and written so far.
+    fn has_dropped(&mut self, read: u64, written: u64) -> bool {
+        if read < self.checkpoint {
+            return false;
+        }
+        self.checkpoint = read + Self::GAP;
+        let ratio = read * 256 / written;
+        if ratio > self.ratio {
+            self.ratio = ratio;
+            false
+        } else {
+            self.ratio = 0;
+            true
+        }
+    }
+}
+
+/// Writes codes least significant bit first, keeping track of the groups.
+struct CodeWriter<'a> {
+    out: &'a mut dyn io::Write,
+    buf: u32,
+    len: u32,
+    /// The number of bits written since the start of the current groups.
+    group: u64,
+    /// The number of bytes written.
+    written: u64,
+}
+
+impl<'a> CodeWriter<'a> {
+    fn new(out: &'a mut dyn io::Write) -> Self {
+        Self {
+            out,
+            buf: 0,
+            len: 0,
+            group: 0,
+            written: 0,
+        }
+    }
+
+    fn written(&self) -> u64 {
+        self.written
+    }
+
+    fn write(&mut self, code: Code, bits: u32) -> io::Result<()> {
+        self.buf |= u32::from(code) << self.len;
+        self.len += bits;
+        self.group += u64::from(bits);
+        self.flush_bytes()
+    }
+
+    /// Pads the current group of `bits`-wide codes with zeroes.
+    fn align(&mut self, bits: u32) -> io::Result<()> {
+        let group = u64::from(bits) * 8;
+        let mut pad = (group - self.group % group) % group;
+        while pad > 0 {
+            let n = pad.min(8);
+            // At most 8.
+            self.len += n as u32;
+            self.flush_bytes()?;
+            pad -= n;
+        }
+        self.group = 0;
+        Ok(())
+    }
+
+    fn flush_bytes(&mut self) -> io::Result<()> {
+        while self.len >= 8 {
+            self.out.write_all(&[self.buf as u8])?;
+            self.buf >>= 8;
+            self.len -= 8;
+            self.written += 1;
+        }
+        Ok(())
+    }
+
+    /// Writes the pending bits, padding the last byte with zeroes.
+    fn finish(self) -> io::Result<()> {
+        if self.len > 0 {
+            self.out.write_all(&[self.buf as u8])?;
+        }
+        Ok(())
+    }
+}
+
+/// Constructs a decoder dictionary, with [`CLEAR`] taken (in block mode) by an
+/// entry which is never looked up.
+fn dec_dict(block_mode: bool) -> Dictionary<DecDict> {
+    let mut dict = Dictionary::<DecDict>::new();
+    if block_mode {
+        dict.insert(Vec::new()).unwrap();
+    }
+    dict
+}
+
+/// The current code width, and when it grows.
+///
+/// This follows the reference decoder exactly: the width grows once the
+/// dictionary holds more entries than `max_code`, which is only lifted to the
+/// dictionary capacity once the width reaches `max_bits`.
+#[derive(Copy, Clone, Debug)]
+struct Width {
+    bits: u32,
+    max_bits: u32,
+    max_code: usize,
+    /// The capacity of the dictionary.
+    max_len: usize,
+}
+
+impl Width {
+    fn new(max_bits: u32) -> Self {
+        Self {
+            bits: INIT_BITS,
+            max_bits,
+            max_code: (1 << INIT_BITS) - 1,
+            max_len: 1 << max_bits,
+        }
+    }
+
+    fn must_grow(&self, dict_len: usize) -> bool {
+        dict_len > self.max_code
+    }
+
+    fn grow(&mut self) {
+        self.bits += 1;
+        self.max_code = if self.bits == self.max_bits {
+            self.max_len
+        } else {
+            (1 << self.bits) - 1
+        };
+    }
+}
+
+/// Reads codes least significant bit first, keeping track of the groups.
+struct CodeReader<'a> {
+    src: &'a mut dyn io::Read,
+    buf: u32,
+    len: u32,
+    /// The number of bits read since the start of the current groups.
+    read: u64,
+}
+
+impl<'a> CodeReader<'a> {
+    fn new(src: &'a mut dyn io::Read) -> Self {
+        Self {
+            src,
+            buf: 0,
+            len: 0,
+            read: 0,
+        }
+    }
+
+    /// Reads a `bits`-wide code, returning `None` once fewer than `bits` bits
+    /// are left.
+    fn read(&mut self, bits: u32) -> io::Result<Option<Code>> {
+        debug_assert!(bits <= Code::BITS);
+        while self.len < bits {
+            let Some(byte) = read_u8(self.src)? else {
+                return Ok(None);
+            };
+            self.buf |= u32::from(byte) << self.len;
+            self.len += 8;
+        }
+        let code = self.buf & ((1 << bits) - 1);
+        self.buf >>= bits;
+        self.len -= bits;
+        self.read += u64::from(bits);
+        // Masked to `bits`, which is at most `Code::BITS` wide.
+        Ok(Some(code as Code))
+    }
+
+    /// Skips to the end of the current group of `bits`-wide codes.
+    fn align(&mut self, bits: u32) -> io::Result<()> {
+        let group = u64::from(bits) * 8;
+        let mut skip = (group - self.read % group) % group;
+        // The groups are byte-aligned, so the buffered bits are the rest of
+        // the current byte, and whole bytes are left to skip.
+        skip -= u64::from(self.len);
+        self.buf = 0;
+        self.len = 0;
+        self.read = 0;
+        for _ in 0..skip / 8 {
+            if read_u8(self.src)?.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = include_bytes!("../tests/data/text.txt");
+    const TEXT_Z: &[u8] = include_bytes!("../tests/data/text.txt.Z");
+
+    fn decoded(data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        decode(&mut &*data, &mut out)?;
+        Ok(out)
+    }
+
+    fn encoded(data: &[u8], max_bits: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode(&mut &*data, &mut out, max_bits).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_encode_reference() {
+        assert_eq!(encoded(TEXT, DEFAULT_MAX_BITS), TEXT_Z);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        // Enough (poorly compressible) data to fill the dictionary and clear
+        // it, for every width.
+        let mut state = 0x2545_F491_u32;
+        let noise: Vec<u8> = (0..40_000)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state % 24) as u8
+            })
+            .collect();
+        for data in [b"".as_ref(), b"A", b"AAAAAAAAAAAAAAAAAAAA", TEXT, &noise] {
+            for max_bits in MAX_BITS_RANGE {
+                let encoded = encoded(data, max_bits);
+                assert_eq!(encoded[2], 0x80 | max_bits as u8);
+                assert_eq!(decoded(&encoded).unwrap(), data, "{max_bits}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_bad_max_bits() {
+        for max_bits in [0, 8, 17] {
+            let error = encode(&mut b"A".as_ref(), &mut Vec::new(), max_bits).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_decode_reference() {
+        assert_eq!(decoded(TEXT_Z).unwrap(), TEXT);
+    }
+
+    #[test]
+    fn test_decode_clear() {
+        // `A`, then a clear code, which skips the rest of its 9-byte group,
+        // then `B`.
+        let data = [
+            0x1f, 0x9d, 0x90, 0x41, 0x00, 0x02, 0, 0, 0, 0, 0, 0, 0x42, 0x00,
+        ];
+        assert_eq!(decoded(&data).unwrap(), b"AB");
+    }
+
+    #[test]
+    fn test_decode_no_block_mode() {
+        // Without block mode, 256 is the first sequence code (here, `AB`).
+        assert_eq!(
+            decoded(&[0x1f, 0x9d, 0x10, 0x41, 0x84, 0x00, 0x04]).unwrap(),
+            b"ABAB"
+        );
+    }
+
+    #[test]
+    fn test_decode_empty() {
+        assert!(decoded(&[0x1f, 0x9d, 0x90]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_decode_bad_header() {
+        let error = |data: &[u8]| decoded(data).unwrap_err().to_string();
+        assert!(error(b"\x1f\x8b\x90").contains("not a .Z file"));
+        assert!(error(b"\x1f\x9d").contains("truncated"));
+        assert!(error(b"\x1f\x9d\x91").contains("max bits"));
+        assert!(error(b"\x1f\x9d\x88").contains("max bits"));
+        assert!(error(b"\x1f\x9d\xd0").contains("flags"));
+    }
+
+    #[test]
+    fn test_decode_invalid_code() {
+        // A first code which isn't a literal.
+        let error = decoded(&[0x1f, 0x9d, 0x90, 0x01, 0x03]).unwrap_err();
+        assert_eq!(error.to_string(), "invalid LZW code 257 at output offset 0");
+    }
+}
@@ -1,13 +1,36 @@
-use std::io::{Read, Write};
+use std::{
+    fmt,
+    io::{BufRead, IoSlice, Read, Write},
+};
+
+mod shared;
+mod throttle;
+
+pub use shared::{SharedStat, StatHandle};
+pub use throttle::Throttle;
 
 /// A reader/writer that collects statistics on reads and writes.
-#[derive(Debug)]
+///
+/// The counters reflect the bytes which passed through this layer, hence their
+/// meaning depends on where [`Stat`] is placed when composed with buffering:
+///
+/// - As the inner layer (e.g., `BufReader<Stat<File>>`), it counts the bytes
+///   read from the file, including the ones that were buffered but not yet (or
+///   never) consumed by the caller.
+/// - As the outer layer (e.g., `Stat<BufReader<File>>`), it counts the bytes
+///   consumed by the caller, through either [`Read`] or [`BufRead`].
 pub struct Stat<I> {
     inner: I,
     read_count: u64,
     write_count: u64,
+    read_ops: u64,
+    write_ops: u64,
+    progress: Option<ProgressFn>,
 }
 
+/// A progress callback, called with the read and write counts.
+type ProgressFn = Box<dyn FnMut(u64, u64) + Send>;
+
 impl<I> Stat<I> {
     /// Constructs a new [`Stat`].
     pub fn new(inner: I) -> Self {
@@ -15,10 +38,31 @@ impl<I> Stat<I> {
             inner,
             read_count: 0,
             write_count: 0,
+            read_ops: 0,
+            write_ops: 0,
+            progress: None,
+        }
+    }
+
+    /// Sets a callback which is called with the current read and write counts
+    /// after each operation that moved any bytes.
+    pub fn set_progress<F>(&mut self, f: F)
+    where
+        F: FnMut(u64, u64) + Send + 'static,
+    {
+        self.progress = Some(Box::new(f));
+    }
+
+    fn report(&mut self, n: usize) {
+        if n == 0 {
+            return;
+        }
+        if let Some(progress) = &mut self.progress {
+            progress(self.read_count, self.write_count);
         }
     }
 
-    /// Returns the number of bytes read.
+    /// Returns the number of bytes read. See [`Stat`] for its precise meaning.
     pub fn read_count(&self) -> u64 {
         self.read_count
     }
@@ -28,24 +72,93 @@ impl<I> Stat<I> {
         self.write_count
     }
 
+    /// Returns the number of read operations, i.e., of calls to [`Read::read`]
+    /// and [`BufRead::consume`] (the latter when used through [`BufRead`]).
+    pub fn read_ops(&self) -> u64 {
+        self.read_ops
+    }
+
+    /// Returns the number of write operations, i.e., of calls to
+    /// [`Write::write`].
+    pub fn write_ops(&self) -> u64 {
+        self.write_ops
+    }
+
+    /// Returns the average number of bytes read per operation, or 0 if there
+    /// was none.
+    pub fn avg_read_size(&self) -> f64 {
+        avg(self.read_count, self.read_ops)
+    }
+
+    /// Returns the average number of bytes written per operation, or 0 if there
+    /// was none.
+    pub fn avg_write_size(&self) -> f64 {
+        avg(self.write_count, self.write_ops)
+    }
+
+    /// Returns whether any of the counters saturated at `u64::MAX`, in which
+    /// case it no longer reflects the actual number of bytes.
+    pub fn saturated(&self) -> bool {
+        self.read_count == u64::MAX || self.write_count == u64::MAX
+    }
+
     /// Returns the inner reader/writer.
     pub fn into_inner(self) -> I {
         self.inner
     }
 }
 
+impl<I: fmt::Debug> fmt::Debug for Stat<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Stat")
+            .field("inner", &self.inner)
+            .field("read_count", &self.read_count)
+            .field("write_count", &self.write_count)
+            .field("read_ops", &self.read_ops)
+            .field("write_ops", &self.write_ops)
+            .finish_non_exhaustive()
+    }
+}
+
 impl<I: Read> Read for Stat<I> {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         let n = self.inner.read(buf)?;
-        self.read_count += u64::try_from(n).unwrap();
+        add(&mut self.read_count, n);
+        add(&mut self.read_ops, 1);
+        self.report(n);
         Ok(n)
     }
 }
 
+impl<I: BufRead> BufRead for Stat<I> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt);
+        add(&mut self.read_count, amt);
+        add(&mut self.read_ops, 1);
+        self.report(amt);
+    }
+}
+
 impl<I: Write> Write for Stat<I> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         let n = self.inner.write(buf)?;
-        self.write_count += u64::try_from(n).unwrap();
+        add(&mut self.write_count, n);
+        add(&mut self.write_ops, 1);
+        self.report(n);
+        Ok(n)
+    }
+
+    /// Forwards to the inner writer's `write_vectored` (so that every slice
+    /// may be written at once), counting as a single operation.
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> std::io::Result<usize> {
+        let n = self.inner.write_vectored(bufs)?;
+        add(&mut self.write_count, n);
+        add(&mut self.write_ops, 1);
+        self.report(n);
         Ok(n)
     }
 
@@ -54,9 +167,25 @@ impl<I: Write> Write for Stat<I> {
     }
 }
 
+/// Adds `n` to the given counter, saturating at `u64::MAX` so that counting
+/// never panics.
+pub(crate) fn add(count: &mut u64, n: usize) {
+    let n = u64::try_from(n).unwrap_or(u64::MAX);
+    *count = count.saturating_add(n);
+}
+
+/// Returns `count / ops`, or 0 if there were no operations.
+fn avg(count: u64, ops: u64) -> f64 {
+    if ops == 0 {
+        0.0
+    } else {
+        count as f64 / ops as f64
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::io::{self, BufWriter};
+    use std::io::{self, BufReader, BufWriter};
 
     use super::*;
 
@@ -83,6 +212,134 @@ mod tests {
         assert_eq!(stat_w.into_inner(), "olá, mundo!".as_bytes());
     }
 
+    #[test]
+    fn test_progress() {
+        use std::sync::{Arc, Mutex};
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let mut stat_r = Stat::new("olá, mundo!".as_bytes());
+        stat_r.set_progress({
+            let reports = Arc::clone(&reports);
+            move |r, w| reports.lock().unwrap().push((r, w))
+        });
+
+        let mut buf = [0; 5];
+        stat_r.read_exact(&mut buf).unwrap();
+        io::read_to_string(&mut stat_r).unwrap();
+
+        // The final, empty read (at EOF) is not reported.
+        assert_eq!(*reports.lock().unwrap(), [(5, 0), (12, 0)]);
+    }
+
+    #[test]
+    fn test_saturation() {
+        /// A (misbehaving) reader that reports huge reads.
+        struct Huge;
+
+        impl Read for Huge {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Ok(usize::MAX / 2 + 1)
+            }
+        }
+
+        let mut stat_r = Stat::new(Huge);
+        let n = stat_r.read(&mut []).unwrap();
+        assert_eq!(stat_r.read_count(), u64::try_from(n).unwrap());
+        assert!(!stat_r.saturated());
+
+        for _ in 0..2 {
+            assert_eq!(stat_r.read(&mut []).unwrap(), n);
+        }
+        assert_eq!(stat_r.read_count(), u64::MAX);
+        assert!(stat_r.saturated());
+    }
+
+    #[test]
+    fn test_avg_sizes() {
+        let mut stat_r = Stat::new("olá, mundo! como vai?".as_bytes());
+        assert_eq!(stat_r.avg_read_size(), 0.0);
+
+        let mut buf = [0; 2];
+        for _ in 0..3 {
+            stat_r.read_exact(&mut buf).unwrap();
+        }
+        let mut buf = [0; 6];
+        stat_r.read_exact(&mut buf).unwrap();
+        assert_eq!((stat_r.read_count(), stat_r.read_ops()), (12, 4));
+        assert_eq!(stat_r.avg_read_size(), 3.0);
+
+        let mut stat_w = Stat::new(Vec::new());
+        assert_eq!(stat_w.avg_write_size(), 0.0);
+        stat_w.write_all(b"ABBA").unwrap();
+        stat_w.write_all(b"AB").unwrap();
+        assert_eq!(stat_w.write_ops(), 2);
+        assert_eq!(stat_w.avg_write_size(), 3.0);
+    }
+
+    #[test]
+    fn test_write_vectored() {
+        let mut stat_w = Stat::new(Vec::new());
+        let bufs = [
+            IoSlice::new(b"ol\xC3\xA1"),
+            IoSlice::new(b", "),
+            IoSlice::new(b""),
+            IoSlice::new(b"mundo!"),
+        ];
+        // `Vec` writes all slices at once.
+        assert_eq!(stat_w.write_vectored(&bufs).unwrap(), 12);
+        assert_eq!((stat_w.write_count(), stat_w.write_ops()), (12, 1));
+        assert_eq!(stat_w.into_inner(), "olá, mundo!".as_bytes());
+
+        /// A writer without vectored writes, which hence only writes the first
+        /// non-empty slice.
+        struct Plain(Vec<u8>);
+
+        impl Write for Plain {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut stat_w = Stat::new(Plain(Vec::new()));
+        assert_eq!(stat_w.write_vectored(&bufs).unwrap(), 4);
+        assert_eq!(stat_w.write_count(), 4);
+    }
+
+    #[test]
+    fn test_read_layering() {
+        let data = "olá, mundo! como vai?".as_bytes();
+        let mut buf = [0; 5];
+
+        // Inner `Stat`: counts what the buffer pulled from the source.
+        let mut buf_r = BufReader::new(Stat::new(data));
+        buf_r.read_exact(&mut buf).unwrap();
+        assert_eq!(buf_r.into_inner().read_count(), 22);
+
+        // Outer `Stat`: counts what the caller consumed.
+        let mut stat_r = Stat::new(BufReader::new(data));
+        stat_r.read_exact(&mut buf).unwrap();
+        assert_eq!(stat_r.read_count(), 5);
+    }
+
+    #[test]
+    fn test_buf_read_consume() {
+        let data = "olá\nmundo\n".as_bytes();
+        let mut stat_r = Stat::new(BufReader::new(data));
+
+        let mut line = String::new();
+        stat_r.read_line(&mut line).unwrap();
+        assert_eq!(line, "olá\n");
+        assert_eq!(stat_r.read_count(), 5);
+
+        stat_r.read_line(&mut line).unwrap();
+        assert_eq!(stat_r.read_count(), 11);
+        assert_eq!(stat_r.read_count(), u64::try_from(data.len()).unwrap());
+    }
+
     #[test]
     fn test_three_level_composition_with_buffering() {
         let out = Vec::<u8>::new();
@@ -0,0 +1,232 @@
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// A reader/writer that collects statistics on reads and writes, which may be
+/// observed from other threads through a [`StatHandle`].
+///
+/// Only the counter updates are done under the lock; the IO operations on the
+/// inner reader/writer are not.
+///
+/// Several [`SharedStat`]s may share the same counters (see
+/// [`SharedStat::share`]), e.g., to read from and write to the same full-duplex
+/// stream from two threads while counting both directions:
+///
+/// ```no_run
+/// # use std::{io::{Read, Write}, net::TcpStream, thread};
+/// # use stat::SharedStat;
+/// let mut writer = SharedStat::new(TcpStream::connect("localhost:8080")?);
+/// let mut reader = writer.try_clone()?;
+///
+/// let incoming = thread::spawn(move || -> std::io::Result<_> {
+///     let mut buf = Vec::new();
+///     reader.read_to_end(&mut buf)?;
+///     Ok(buf)
+/// });
+/// writer.write_all(b"hello")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct SharedStat<I> {
+    inner: I,
+    counts: Arc<Mutex<StatInner>>,
+}
+
+/// A cloneable, thread-safe view of the counters of a [`SharedStat`].
+#[derive(Clone, Debug)]
+pub struct StatHandle {
+    counts: Arc<Mutex<StatInner>>,
+}
+
+#[derive(Debug, Default)]
+struct StatInner {
+    read_count: u64,
+    write_count: u64,
+}
+
+impl<I> SharedStat<I> {
+    /// Constructs a new [`SharedStat`].
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner,
+            counts: Arc::default(),
+        }
+    }
+
+    /// Returns a handle which may be used to observe the counters.
+    pub fn handle(&self) -> StatHandle {
+        StatHandle {
+            counts: Arc::clone(&self.counts),
+        }
+    }
+
+    /// Wraps another reader/writer, which counts into the same counters as
+    /// this one.
+    pub fn share<J>(&self, inner: J) -> SharedStat<J> {
+        SharedStat {
+            inner,
+            counts: Arc::clone(&self.counts),
+        }
+    }
+
+    /// Returns the number of bytes read.
+    pub fn read_count(&self) -> u64 {
+        lock(&self.counts).read_count
+    }
+
+    /// Returns the number of bytes written.
+    pub fn write_count(&self) -> u64 {
+        lock(&self.counts).write_count
+    }
+
+    /// Returns a reference to the inner reader/writer.
+    pub fn get_ref(&self) -> &I {
+        &self.inner
+    }
+
+    /// Returns the inner reader/writer.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+}
+
+impl SharedStat<TcpStream> {
+    /// Clones the underlying stream (see [`TcpStream::try_clone`]), the clone
+    /// sharing the counters of this one.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the stream can't be cloned.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(self.share(self.inner.try_clone()?))
+    }
+}
+
+impl StatHandle {
+    /// Returns a snapshot of the number of bytes read.
+    pub fn read_count(&self) -> u64 {
+        lock(&self.counts).read_count
+    }
+
+    /// Returns a snapshot of the number of bytes written.
+    pub fn write_count(&self) -> u64 {
+        lock(&self.counts).write_count
+    }
+}
+
+impl<I: Read> Read for SharedStat<I> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        crate::add(&mut lock(&self.counts).read_count, n);
+        Ok(n)
+    }
+}
+
+impl<I: Write> Write for SharedStat<I> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        crate::add(&mut lock(&self.counts).write_count, n);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Locks the counters. Since the critical sections only update integers, a
+/// poisoned lock still holds consistent data and may be used.
+fn lock(counts: &Mutex<StatInner>) -> MutexGuard<'_, StatInner> {
+    counts.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread};
+
+    use super::*;
+
+    #[test]
+    fn test_shared_read() {
+        let mut src = "olá, mundo!".as_bytes();
+        let mut stat_r = SharedStat::new(&mut src);
+        let handle = stat_r.handle();
+
+        std::io::read_to_string(&mut stat_r).unwrap();
+
+        assert_eq!(handle.read_count(), 12);
+        assert_eq!(handle.write_count(), 0);
+        assert_eq!(stat_r.read_count(), 12);
+    }
+
+    #[test]
+    fn test_duplex_tcp() {
+        use std::net::{Shutdown, TcpListener};
+
+        // An echo server.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = stream.try_clone().unwrap();
+            io::copy(&mut reader, &mut stream).unwrap();
+        });
+
+        let mut writer = SharedStat::new(TcpStream::connect(addr).unwrap());
+        let mut reader = writer.try_clone().unwrap();
+        let handle = writer.handle();
+
+        let incoming = thread::spawn(move || {
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf).unwrap();
+            buf
+        });
+        for _ in 0..100 {
+            writer.write_all("olá, mundo!".as_bytes()).unwrap();
+        }
+        writer.get_ref().shutdown(Shutdown::Write).unwrap();
+
+        let echoed = incoming.join().unwrap();
+        server.join().unwrap();
+        assert_eq!(echoed, "olá, mundo!".repeat(100).into_bytes());
+        assert_eq!(handle.write_count(), 1200);
+        assert_eq!(handle.read_count(), 1200);
+        assert_eq!(writer.read_count(), 1200);
+    }
+
+    #[test]
+    fn test_monitor_thread() {
+        let mut stat_w = SharedStat::new(Vec::<u8>::new());
+        let handle = stat_w.handle();
+        let (tx, rx) = mpsc::channel::<()>();
+
+        let monitor = thread::spawn(move || {
+            let mut seen = Vec::new();
+            // One snapshot per chunk reported by the worker.
+            while rx.recv().is_ok() {
+                seen.push(handle.write_count());
+            }
+            seen.push(handle.write_count());
+            seen
+        });
+
+        let worker = thread::spawn(move || {
+            for _ in 0..10 {
+                stat_w.write_all(b"abcd").unwrap();
+                tx.send(()).unwrap();
+            }
+            stat_w
+        });
+
+        let stat_w = worker.join().unwrap();
+        let seen = monitor.join().unwrap();
+
+        assert_eq!(seen.len(), 11);
+        assert!(seen.windows(2).all(|w| w[0] <= w[1]));
+        assert!(seen[0] >= 4);
+        assert_eq!(*seen.last().unwrap(), 40);
+        assert_eq!(stat_w.into_inner().len(), 40);
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// A reader/writer which limits its throughput to a number of bytes per
+/// second, e.g., to simulate a slow link.
+///
+/// It's a token bucket: tokens accrue at the given rate, up to a tenth of a
+/// second's worth (so that bursts stay short), and every byte takes one. An
+/// operation takes at most a full bucket, so a large one is cut short (as a
+/// partial read or write) rather than starving small ones, and it sleeps until
+/// enough tokens accrued. The bucket starts empty, so that moving `n` bytes
+/// takes about `n / bytes_per_sec` seconds.
+pub struct Throttle<I> {
+    inner: I,
+    bytes_per_sec: f64,
+    capacity: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl<I> Throttle<I> {
+    /// Constructs a new [`Throttle`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes_per_sec` is zero.
+    pub fn new(inner: I, bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "the rate must be positive");
+        let bytes_per_sec = bytes_per_sec as f64;
+        Self {
+            inner,
+            bytes_per_sec,
+            capacity: (bytes_per_sec / 10.0).max(1.0),
+            tokens: 0.0,
+            last: Instant::now(),
+        }
+    }
+
+    /// Returns a reference to the inner reader/writer.
+    pub fn get_ref(&self) -> &I {
+        &self.inner
+    }
+
+    /// Returns the inner reader/writer.
+    pub fn into_inner(self) -> I {
+        self.inner
+    }
+
+    /// Waits until `len` bytes (capped at the bucket's capacity) may move,
+    /// taking their tokens, and returns how many bytes that is.
+    fn acquire(&mut self, len: usize) -> usize {
+        let n = len.min(self.capacity as usize).max(1);
+        self.refill();
+        let missing = n as f64 - self.tokens;
+        if missing > 0.0 {
+            thread::sleep(Duration::from_secs_f64(missing / self.bytes_per_sec));
+            self.refill();
+        }
+        self.tokens -= n as f64;
+        n
+    }
+
+    /// Gives back the tokens of the bytes which didn't move after all.
+    fn release(&mut self, n: usize) {
+        self.tokens = (self.tokens + n as f64).min(self.capacity);
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.bytes_per_sec).min(self.capacity);
+        self.last = now;
+    }
+}
+
+impl<I: fmt::Debug> fmt::Debug for Throttle<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Throttle")
+            .field("inner", &self.inner)
+            .field("bytes_per_sec", &self.bytes_per_sec)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<I: Read> Read for Throttle<I> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let allowed = self.acquire(buf.len());
+        let result = self.inner.read(&mut buf[..allowed]);
+        self.release(allowed - *result.as_ref().unwrap_or(&0));
+        result
+    }
+}
+
+impl<I: Write> Write for Throttle<I> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let allowed = self.acquire(buf.len());
+        let result = self.inner.write(&buf[..allowed]);
+        self.release(allowed - *result.as_ref().unwrap_or(&0));
+        result
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_rate() {
+        let mut throttle = Throttle::new(Vec::new(), 100_000);
+        let start = Instant::now();
+        for chunk in vec![7; 50_000].chunks(1000) {
+            throttle.write_all(chunk).unwrap();
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+        assert!((0.4..2.0).contains(&elapsed), "{elapsed} s");
+        assert_eq!(throttle.into_inner(), vec![7; 50_000]);
+    }
+
+    #[test]
+    fn test_read_rate() {
+        let data = vec![7; 50_000];
+        let mut throttle = Throttle::new(data.as_slice(), 100_000);
+        let start = Instant::now();
+        let mut out = Vec::new();
+        throttle.read_to_end(&mut out).unwrap();
+        let elapsed = start.elapsed().as_secs_f64();
+        assert!((0.4..2.0).contains(&elapsed), "{elapsed} s");
+        assert_eq!(out, data);
+    }
+
+    #[test]
+    fn test_small_writes_not_starved() {
+        let mut throttle = Throttle::new(Vec::new(), 1000);
+        // A large write only takes a bucket's worth of bytes...
+        assert_eq!(throttle.write(&[0; 10_000]).unwrap(), 100);
+        // ...so that a single byte only waits for its own token.
+        let start = Instant::now();
+        assert_eq!(throttle.write(&[1]).unwrap(), 1);
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    #[should_panic = "the rate must be positive"]
+    fn test_zero_rate() {
+        Throttle::new(Vec::<u8>::new(), 0);
+    }
+}
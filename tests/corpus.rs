@@ -0,0 +1,62 @@
+use std::io;
+
+use compressing::{huffman, lzw};
+
+type Codec = fn(&mut dyn io::Read, &mut dyn io::Write) -> io::Result<()>;
+
+const TEXT: &[u8] = include_bytes!("data/text.txt");
+const SOURCE: &[u8] = include_bytes!("data/source.rs");
+const IMAGE: &[u8] = include_bytes!("data/image.bmp");
+
+/// Compresses and decompresses `data`, asserting the round-trip and returning
+/// the compression ratio (compressed size over original size).
+fn roundtrip(enc: Codec, dec: Codec, data: &[u8]) -> f64 {
+    let mut compressed = Vec::new();
+    enc(&mut &*data, &mut compressed).unwrap();
+
+    let mut decompressed = Vec::new();
+    dec(&mut compressed.as_slice(), &mut decompressed).unwrap();
+    assert!(decompressed == data, "round-trip mismatch");
+
+    compressed.len() as f64 / data.len() as f64
+}
+
+macro_rules! test {
+    ($( ($name:ident, $enc:expr, $dec:expr, $data:expr, $ratio:expr), )+) => {
+        $(
+            #[test]
+            fn $name() {
+                let ratio = roundtrip($enc, $dec, $data);
+                let range = $ratio;
+                assert!(range.contains(&ratio), "ratio {ratio:.3} not in {range:?}");
+            }
+        )+
+    };
+}
+
+test![
+    (test_lzw_text, lzw::enc, lzw::dec, TEXT, 0.7..0.95),
+    (test_lzw_source, lzw::enc, lzw::dec, SOURCE, 0.5..0.8),
+    (test_lzw_image, lzw::enc, lzw::dec, IMAGE, 0.3..0.7),
+    (
+        test_huffman_text,
+        huffman::enc,
+        huffman::dec,
+        TEXT,
+        0.5..0.75
+    ),
+    (
+        test_huffman_source,
+        huffman::enc,
+        huffman::dec,
+        SOURCE,
+        0.5..0.75
+    ),
+    (
+        test_huffman_image,
+        huffman::enc,
+        huffman::dec,
+        IMAGE,
+        0.4..0.7
+    ),
+];
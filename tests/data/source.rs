@@ -0,0 +1,356 @@
+use crate::shared::{read_u16, read_u8};
+use std::{collections::HashMap, io, mem};
+
+pub type Code = u16;
+
+pub type EncDict = HashMap<Vec<u8>, Code>;
+pub type DecDict = HashMap<Code, Vec<u8>>;
+
+/// Encodes the given data.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`).
+///
+/// # Panics
+///
+/// Panics if the dictionary grows past `u16`'s bounds.
+pub fn enc(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    enc_returning_dict(src, out)?;
+    Ok(())
+}
+
+#[doc(hidden)]
+pub fn enc_returning_dict(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<EncDict> {
+    let mut codes = codes(src);
+    while let Some(code) = codes.next_code()? {
+        emit(code, out)?;
+    }
+    Ok(codes.dict)
+}
+
+/// Returns an iterator over the codes the encoder emits for the given data,
+/// without serializing them.
+///
+/// # Panics
+///
+/// The iterator panics if the dictionary grows past `u16`'s bounds.
+pub fn codes(src: &mut dyn io::Read) -> Codes<'_> {
+    Codes {
+        src,
+        dict: build_default_enc_dict(),
+        seq: Vec::new(),
+        done: false,
+    }
+}
+
+/// An iterator over the emitted LZW codes. See [`codes`].
+pub struct Codes<'src> {
+    src: &'src mut dyn io::Read,
+    dict: EncDict,
+    seq: Vec<u8>,
+    done: bool,
+}
+
+impl Codes<'_> {
+    /// Returns the encoder dictionary in its current state.
+    pub fn dict(&self) -> &EncDict {
+        &self.dict
+    }
+
+    fn next_code(&mut self) -> io::Result<Option<Code>> {
+        // Advance while the next char forms a key which is in the map.
+        // When the next char forms a string which is not in the map, emits it
+        // and inserts (it + the char) in the map.
+        while let Some(c) = read_u8(self.src)? {
+            self.seq.push(c);
+            if !self.dict.contains_key(&self.seq) {
+                let prev_seq = &self.seq[..(self.seq.len() - 1)];
+                let emitted = self.dict[prev_seq];
+
+                let code = self.dict.len().try_into().unwrap();
+                self.dict.insert(mem::replace(&mut self.seq, vec![c]), code);
+                return Ok(Some(emitted));
+            }
+        }
+        if self.seq.is_empty() {
+            return Ok(None);
+        }
+        let emitted = self.dict[&self.seq];
+        self.seq.clear();
+        Ok(Some(emitted))
+    }
+}
+
+impl Iterator for Codes<'_> {
+    type Item = io::Result<Code>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_code() {
+            Ok(Some(code)) => Some(Ok(code)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(error) => {
+                self.done = true;
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Decodes the given data.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`).
+///
+/// # Panics
+///
+/// Panics if the dictionary grows past `u16`'s bounds.
+pub fn dec(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let mut dict = build_default_dec_dict();
+    let mut seq = Vec::<u8>::new();
+
+    while let Some(code) = read_u16(src)? {
+        let decoded = dict.get(&code).cloned().unwrap_or_else(|| {
+            let mut s = seq.clone();
+            s.push(s[0]);
+            s
+        });
+        out.write_all(&decoded)?;
+
+        if !seq.is_empty() {
+            let next_code = dict.len().try_into().unwrap();
+            dict.insert(next_code, {
+                let mut s = mem::take(&mut seq);
+                s.push(decoded[0]);
+                s
+            });
+        }
+
+        seq = decoded;
+    }
+
+    Ok(())
+}
+
+/// Encodes the given data, first remapping the byte values in use to the
+/// contiguous range `0..k`.
+///
+/// The output is prefixed by the remap table: `k` (as a `u16`) followed by the
+/// `k` used byte values in increasing order, such that the remapped value `i`
+/// stands for the `i`-th listed byte.
+///
+/// Note that, since codes are always 16 bits wide, the remapping alone doesn't
+/// shrink the code stream: LZW parses the input identically under any byte
+/// permutation. It only pays off when codes are allowed to start narrower.
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`).
+///
+/// # Panics
+///
+/// Panics if the dictionary grows past `u16`'s bounds.
+pub fn enc_remapped(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let mut data = Vec::new();
+    src.read_to_end(&mut data)?;
+
+    let mut used = [false; 256];
+    for &c in &data {
+        used[usize::from(c)] = true;
+    }
+    let table: Vec<u8> = (u8::MIN..=u8::MAX)
+        .filter(|&c| used[usize::from(c)])
+        .collect();
+
+    let mut remap = [0; 256];
+    for (i, &c) in table.iter().enumerate() {
+        remap[usize::from(c)] = u8::try_from(i).unwrap();
+    }
+    for c in &mut data {
+        *c = remap[usize::from(*c)];
+    }
+
+    let table_len = u16::try_from(table.len()).unwrap();
+    out.write_all(&table_len.to_be_bytes())?;
+    out.write_all(&table)?;
+    enc(&mut data.as_slice(), out)
+}
+
+/// Decodes data produced by [`enc_remapped`].
+///
+/// # Errors
+///
+/// Fails if any of the underlying I/O operations fail (i.e., reading from `src`
+/// or writing to `out`), or if the remap table is truncated or inconsistent
+/// with the decoded data.
+///
+/// # Panics
+///
+/// Panics if the dictionary grows past `u16`'s bounds.
+pub fn dec_remapped(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    let table_len = read_u16(src)?.ok_or_else(|| invalid("missing remap table"))?;
+    if table_len > 256 {
+        return Err(invalid("remap table too long"));
+    }
+    let mut table = vec![0; usize::from(table_len)];
+    src.read_exact(&mut table)?;
+
+    let mut data = Vec::new();
+    dec(src, &mut data)?;
+    for c in &mut data {
+        *c = *table
+            .get(usize::from(*c))
+            .ok_or_else(|| invalid("byte outside of the remap table"))?;
+    }
+    out.write_all(&data)
+}
+
+fn emit(code: Code, out: &mut dyn io::Write) -> io::Result<()> {
+    out.write_all(&Code::to_be_bytes(code))
+}
+
+fn build_default_enc_dict() -> EncDict {
+    let mut dict = HashMap::new();
+    for i in u8::MIN..=u8::MAX {
+        dict.insert(vec![i], i.into());
+    }
+    dict
+}
+
+fn build_default_dec_dict() -> DecDict {
+    let mut dict = HashMap::new();
+    for i in u8::MIN..=u8::MAX {
+        dict.insert(i.into(), vec![i]);
+    }
+    dict
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! test {
+        ($( ($name:ident, $decoded:expr, $encoded:expr), )+) => {
+            paste::paste! {
+                $(
+                    #[test]
+                    fn [< $name _encode >]() {
+                        let mut src = ($decoded).as_ref();
+                        let mut out = Vec::new();
+                        enc(&mut src, &mut out).unwrap();
+                        assert_eq!(out, Vec::from($encoded));
+                    }
+
+                    #[test]
+                    fn [< $name _decode >]() {
+                        let src = Vec::from($encoded);
+                        let mut out = Vec::new();
+                        dec(&mut &*src, &mut out).unwrap();
+                        assert_eq!(out, $decoded);
+                    }
+                )+
+            }
+        };
+    }
+
+    test![
+        (
+            test_basic_seq_1,
+            b"ABBABBBABBA",
+            coded(&[65, 66, 66, 256, 257, 259, 65])
+        ),
+        (test_basic_seq_2, b"ABABA", coded(&[65, 66, 256, 65])),
+        (test_basic_seq_3, b"ABABABA", coded(&[65, 66, 256, 258])),
+        (
+            test_basic_seq_4,
+            b"ol\xE1, mundo! como vai?",
+            [
+                0, 111, 0, 108, 0, 225, 0, 44, 0, 32, 0, 109, 0, 117, 0, 110, 0, 100, 0, 111, 0,
+                33, 0, 32, 0, 99, 0, 111, 0, 109, 0, 111, 0, 32, 0, 118, 0, 97, 0, 105, 0, 63
+            ]
+        ),
+    ];
+
+    #[test]
+    fn test_codes_iter() {
+        let mut src = b"ABABABA".as_ref();
+        let codes = codes(&mut src).collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(codes, [65, 66, 256, 258]);
+    }
+
+    #[test]
+    fn test_codes_iter_empty() {
+        let mut src = b"".as_ref();
+        assert!(codes(&mut src).next().is_none());
+    }
+
+    #[test]
+    fn test_remapped_roundtrip() {
+        let data = b"\xF0\x10\xF0\x80\x10\xF0\xF0\x80\x10\x10";
+        let mut out = Vec::new();
+        enc_remapped(&mut data.as_ref(), &mut out).unwrap();
+
+        // The table lists the three used bytes in increasing order.
+        assert_eq!(out[..5], [0, 3, 0x10, 0x80, 0xF0]);
+
+        let mut recovered = Vec::new();
+        dec_remapped(&mut &out[..], &mut recovered).unwrap();
+        assert_eq!(recovered, data);
+    }
+
+    #[test]
+    fn test_remapped_size() {
+        // Sparse alphabet: only `k` distinct bytes.
+        let data: Vec<u8> = (0..4096u32)
+            .map(|i| [7, 99, 200][(i % 7 % 3) as usize])
+            .collect();
+
+        let mut plain = Vec::new();
+        enc(&mut data.as_slice(), &mut plain).unwrap();
+        let mut remapped = Vec::new();
+        enc_remapped(&mut data.as_slice(), &mut remapped).unwrap();
+
+        // Same parse, so only the table is added on top of the plain stream.
+        assert_eq!(remapped.len(), plain.len() + 2 + 3);
+    }
+
+    #[test]
+    fn test_remapped_empty() {
+        let mut out = Vec::new();
+        enc_remapped(&mut b"".as_ref(), &mut out).unwrap();
+        assert_eq!(out, [0, 0]);
+
+        let mut recovered = Vec::new();
+        dec_remapped(&mut &out[..], &mut recovered).unwrap();
+        assert!(recovered.is_empty());
+    }
+
+    #[test]
+    fn test_remapped_invalid_table() {
+        let src = [0, 1, b'A', 0, 1];
+        let mut out = Vec::new();
+        let error = dec_remapped(&mut &src[..], &mut out).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    fn coded(codes: &[Code]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for code in codes {
+            let data = Code::to_be_bytes(*code);
+            out.extend(data);
+        }
+        out
+    }
+}
@@ -0,0 +1,87 @@
+//! The encoders must produce the same output for the same input, whatever the
+//! run (e.g., `HashMap`s are seeded differently in each thread and process).
+
+use std::{io, thread};
+
+use compressing::{arithmetic, huffman, lzw, stored, unix_compress};
+
+type Enc = fn(&mut dyn io::Read, &mut dyn io::Write) -> io::Result<()>;
+
+const TEXT: &[u8] = include_bytes!("data/text.txt");
+const SOURCE: &[u8] = include_bytes!("data/source.rs");
+const IMAGE: &[u8] = include_bytes!("data/image.bmp");
+
+/// The Huffman encoding of `TEXT`, checked in so that a change of the output
+/// (e.g., of how tree ties are broken) doesn't go unnoticed.
+const TEXT_HUFF: &[u8] = include_bytes!("data/text.txt.huff");
+
+fn lzw_variable_width(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let options = lzw::LzwOptions {
+        variable_width: true,
+        ..lzw::LzwOptions::default()
+    };
+    lzw::enc_with(src, out, &options)
+}
+
+fn unix_compress_enc(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    unix_compress::encode(src, out, unix_compress::DEFAULT_MAX_BITS)
+}
+
+fn huffman_seekable(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    let mut data = Vec::new();
+    src.read_to_end(&mut data)?;
+    huffman::enc_seekable(&mut io::Cursor::new(data), out)
+}
+
+const ENCODERS: [(&str, Enc); 7] = [
+    ("lzw", lzw::enc),
+    ("lzw (variable width)", lzw_variable_width),
+    ("huffman", huffman::enc),
+    ("huffman (seekable)", huffman_seekable),
+    ("arithmetic", arithmetic::enc),
+    ("unix_compress", unix_compress_enc),
+    ("stored", stored::enc),
+];
+
+fn encoded(enc: Enc, data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    enc(&mut &*data, &mut out).unwrap();
+    out
+}
+
+#[test]
+fn test_same_output_across_runs() {
+    for (name, enc) in ENCODERS {
+        for data in [TEXT, SOURCE, IMAGE] {
+            let first = encoded(enc, data);
+            assert!(encoded(enc, data) == first, "{name}");
+            // A fresh thread seeds its dictionary's hasher anew.
+            let other = thread::spawn(move || encoded(enc, data)).join().unwrap();
+            assert!(other == first, "{name}");
+        }
+    }
+}
+
+#[test]
+fn test_generic_lzw_same_output() {
+    let tokens: Vec<u16> = SOURCE
+        .chunks(2)
+        .map(|c| u16::from(c[0]) << 8 | u16::from(*c.last().unwrap()))
+        .collect();
+    let encode = |tokens: &[u16]| {
+        let mut out = Vec::new();
+        lzw::generic::enc(tokens, &mut out).unwrap();
+        out
+    };
+    let first = encode(&tokens);
+    let other = thread::spawn(move || encode(&tokens)).join().unwrap();
+    assert_eq!(other, first);
+}
+
+#[test]
+fn test_huffman_golden() {
+    assert!(encoded(huffman::enc, TEXT) == TEXT_HUFF);
+    let mut compressed = Vec::new();
+    huffman::compress(TEXT, &mut compressed).unwrap();
+    assert!(compressed == TEXT_HUFF);
+}
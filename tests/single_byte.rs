@@ -0,0 +1,49 @@
+use std::io;
+
+use compressing::{arithmetic, huffman, lzw, stored, unix_compress};
+
+type Codec = fn(&mut dyn io::Read, &mut dyn io::Write) -> io::Result<()>;
+
+fn unix_compress_enc(src: &mut dyn io::Read, out: &mut dyn io::Write) -> io::Result<()> {
+    unix_compress::encode(src, out, unix_compress::DEFAULT_MAX_BITS)
+}
+
+const CODECS: [(&str, Codec, Codec); 5] = [
+    ("lzw", lzw::enc, lzw::dec),
+    ("huffman", huffman::enc, huffman::dec),
+    ("stored", stored::enc, stored::dec),
+    ("arithmetic", arithmetic::enc, arithmetic::dec),
+    ("unix_compress", unix_compress_enc, unix_compress::decode),
+];
+
+/// A single symbol is the degenerate case of most codecs (e.g., a Huffman tree
+/// with a lone leaf), so every byte value on its own must round-trip.
+#[test]
+fn test_single_byte_roundtrip() {
+    for (name, enc, dec) in CODECS {
+        for byte in u8::MIN..=u8::MAX {
+            let mut compressed = Vec::new();
+            enc(&mut [byte].as_ref(), &mut compressed).unwrap();
+
+            let mut decompressed = Vec::new();
+            dec(&mut compressed.as_slice(), &mut decompressed).unwrap();
+            assert_eq!(decompressed, [byte], "{name}: byte {byte}");
+        }
+    }
+}
+
+#[test]
+fn test_single_byte_roundtrip_variable_width() {
+    let options = lzw::LzwOptions {
+        variable_width: true,
+        ..lzw::LzwOptions::default()
+    };
+    for byte in u8::MIN..=u8::MAX {
+        let mut compressed = Vec::new();
+        lzw::enc_with(&mut [byte].as_ref(), &mut compressed, &options).unwrap();
+
+        let mut decompressed = Vec::new();
+        lzw::dec_with(&mut compressed.as_slice(), &mut decompressed, &options).unwrap();
+        assert_eq!(decompressed, [byte], "byte {byte}");
+    }
+}
@@ -0,0 +1,78 @@
+//! Interoperability with the system's `compress` and `uncompress` tools, whose
+//! tests are skipped if the tool isn't installed.
+
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+};
+
+use compressing::unix_compress;
+
+const TEXT: &[u8] = include_bytes!("data/text.txt");
+const SOURCE: &[u8] = include_bytes!("data/source.rs");
+const IMAGE: &[u8] = include_bytes!("data/image.bmp");
+
+/// Runs `program` with `args`, feeding it `input`, and returns its output, or
+/// `None` if it isn't installed.
+fn run(program: &str, args: &[&str], input: &[u8]) -> Option<Vec<u8>> {
+    let mut child = match Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {
+            eprintln!("skipped: `{program}` isn't installed");
+            return None;
+        }
+        Err(error) => panic!("{error}"),
+    };
+    let mut stdin = child.stdin.take().unwrap();
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output().unwrap();
+    writer.join().unwrap().unwrap();
+    assert!(output.status.success(), "`{program}` failed");
+    Some(output.stdout)
+}
+
+fn corpus() -> Vec<u8> {
+    // The (poorly compressible) noise after the text makes the ratio drop, so
+    // the dictionary is also cleared.
+    let mut data = [TEXT, SOURCE, IMAGE].concat().repeat(4);
+    let mut state = 0x2545_F491_u32;
+    data.extend((0..40_000).map(|_| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        (state % 24) as u8
+    }));
+    data
+}
+
+#[test]
+fn test_system_uncompress() {
+    let data = corpus();
+    for max_bits in [9, 12, 16] {
+        let mut encoded = Vec::new();
+        unix_compress::encode(&mut data.as_slice(), &mut encoded, max_bits).unwrap();
+        let Some(decoded) = run("uncompress", &["-c"], &encoded) else {
+            return;
+        };
+        assert!(decoded == data, "max bits {max_bits}");
+    }
+}
+
+#[test]
+fn test_system_compress() {
+    let data = corpus();
+    for max_bits in ["-b9", "-b12", "-b16"] {
+        let Some(encoded) = run("compress", &["-c", max_bits], &data) else {
+            return;
+        };
+        let mut decoded = Vec::new();
+        unix_compress::decode(&mut encoded.as_slice(), &mut decoded).unwrap();
+        assert!(decoded == data, "{max_bits}");
+    }
+}